@@ -1,16 +1,32 @@
-use crate::config::{Configs, IdMapConf, PipelineConf};
-use crate::processing::Pipeline;
+#[cfg(feature = "storage")]
+use crate::config::SchedulingConf;
+use crate::config::{Configs, DripConf, IdMapConf, PipelineConf, ThreadConf, TopicConf};
+#[cfg(feature = "templating")]
+use crate::processing::digest::{Digest, DigestItem};
+use crate::processing::{telegram, Pipeline};
+#[cfg(feature = "storage")]
+use crate::storage::blocking;
+#[cfg(all(feature = "storage", feature = "templating"))]
+use crate::storage::digest::{self as batches, DigestBatch};
+#[cfg(feature = "storage")]
+use crate::storage::drip::{self, Dripped};
+#[cfg(feature = "storage")]
+use crate::storage::queue::{self, QueueItem};
 use colored::Colorize;
 use rust_tdlib::client::tdlib_client::TdJson;
 use rust_tdlib::client::{Client, ClientState, SignalAuthStateHandler, Worker};
 use rust_tdlib::tdjson;
+#[cfg(feature = "storage")]
+use rust_tdlib::types::DeleteMessages;
 use rust_tdlib::types::{
-    AuthorizationState, ChatList, GetChat, LoadChats, OptionValue, OptionValueBoolean, SendMessage,
-    SetOption, TdlibParameters, Update,
+    AuthorizationState, ChatList, GetChat, InputMessageContent, LoadChats, Message,
+    MessageSchedulingState, MessageSchedulingStateSendAtDate, MessageSendOptions, OptionValue,
+    OptionValueBoolean, SendMessage, SetOption, TdlibParameters, Update, UpdateNewMessage,
 };
 use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{env, io};
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::task::JoinHandle;
@@ -22,6 +38,54 @@ pub const APP_LANG: &str = "en";
 pub type PipelineKey = (Option<i64>, Option<i64>);
 pub type Map = HashMap<i64, Vec<i64>>;
 
+/// Scheduling on Telegram server needs some time ahead, closer messages are queued
+#[cfg(feature = "storage")]
+const MIN_SERVER_SCHEDULING_DELAY: u64 = 60;
+
+/// Current unix time
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Sleep until unix time, forever when there is nothing to wait for
+async fn sleep_until(send_at: Option<u64>) {
+    match send_at {
+        Some(send_at) => {
+            tokio::time::sleep(Duration::from_secs(send_at.saturating_sub(unix_now()))).await
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Time of the earliest queued message or digest
+#[cfg(feature = "storage")]
+async fn next_queued() -> Option<u64> {
+    let send_at = blocking(queue::next_send_at).await.unwrap_or_else(|e| {
+        println!("{} {}", "Failed to read queue :".red(), e);
+        None
+    });
+
+    #[cfg(feature = "templating")]
+    let send_at = match blocking(batches::next_flush_at).await {
+        Ok(flush_at) => send_at.into_iter().chain(flush_at).min(),
+        Err(e) => {
+            println!("{} {}", "Failed to read digests :".red(), e);
+            send_at
+        }
+    };
+
+    send_at
+}
+
+/// Without storage nothing is queued
+#[cfg(not(feature = "storage"))]
+async fn next_queued() -> Option<u64> {
+    None
+}
+
 /// App struct is entry point
 #[derive(Debug)]
 pub struct App {
@@ -29,6 +93,10 @@ pub struct App {
     pub mappings_index: Arc<MappingsIndex>,
     /// Index of pipelines. Keys are "routes" (source_chat_id:dest_chat_id) and values are Pipelines
     pub pipelines_index: Arc<PipelinesIndex>,
+    /// Index of named forum topics. Keys are (chat_id, topic name) and values are message thread ids
+    pub topics_index: Arc<TopicsIndex>,
+    /// Index of drip-fed destination chats. Keys are chat ids
+    pub drips_index: Arc<DripsIndex>,
     /// Chats names
     pub indexed_chats: HashMap<i64, String>,
 }
@@ -38,6 +106,8 @@ impl From<Configs> for App {
         Self {
             mappings_index: Arc::new(MappingsIndex::from(configs.maps)),
             pipelines_index: Arc::new(PipelinesIndex::from(configs.pipelines)),
+            topics_index: Arc::new(TopicsIndex::from(configs.topics)),
+            drips_index: Arc::new(DripsIndex::from(configs.drips)),
             indexed_chats: HashMap::default(),
         }
     }
@@ -82,8 +152,10 @@ impl App {
             )
             .await;
 
+        telegram::set_client(client.clone());
         self.set_client_options(&client).await;
         self.load_chats(&client).await;
+        telegram::set_chat_titles(self.indexed_chats.clone());
         self.handle_updates(&client, receiver).await;
 
         println!("Closing client...");
@@ -100,67 +172,677 @@ impl App {
         worker.stop();
     }
 
-    /// Handle incoming updates from Telegram
+    /// Handle incoming updates from Telegram. Queued messages are sent when their time comes
     async fn handle_updates(&self, client: &Client<TdJson>, mut receiver: Receiver<Box<Update>>) {
         println!("{}...", "handle_updates started!".blue());
 
+        let mut next_send_at = next_queued().await;
+
+        loop {
+            let update = tokio::select! {
+                update = receiver.recv() => match update {
+                    Some(update) => update,
+                    None => break,
+                },
+                _ = sleep_until(next_send_at) => {
+                    self.send_queued(client).await;
+                    next_send_at = next_queued().await;
+                    continue;
+                }
+            };
+
+            match *update {
+                Update::NewMessage(new_message) => {
+                    let queued = self.handle_new_message(client, new_message).await;
+                    if queued {
+                        next_send_at = next_queued().await;
+                    }
+                }
+                #[cfg(feature = "storage")]
+                Update::DeleteMessages(deleted)
+                    if deleted.is_permanent()
+                        && self.mappings_index.get(&deleted.chat_id()).is_some() =>
+                {
+                    self.cancel_deleted(client, deleted.chat_id(), deleted.message_ids())
+                        .await;
+                    next_send_at = next_queued().await;
+                }
+                // Scheduled message gets its id from server
+                #[cfg(feature = "storage")]
+                Update::MessageSendSucceeded(sent)
+                    if sent.message().scheduling_state().is_some() =>
+                {
+                    let (chat_id, old_id, new_id) = (
+                        sent.message().chat_id(),
+                        sent.old_message_id(),
+                        sent.message().id(),
+                    );
+                    let replaced =
+                        blocking(move || queue::replace_scheduled_id(chat_id, old_id, new_id));
+                    if let Err(e) = replaced.await {
+                        println!("{} {}", "Failed to track scheduled message :".red(), e);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Map new message to its destinations. Returns whether any output was queued
+    async fn handle_new_message(
+        &self,
+        client: &Client<TdJson>,
+        new_message: UpdateNewMessage,
+    ) -> bool {
         let default_pipeline = vec![Pipeline::default()];
+        let source_chat_id = &new_message.message().chat_id();
+        let source_thread_id = new_message.message().message_thread_id();
+        let mut queued = false;
 
-        while let Some(update) = receiver.recv().await {
-            if let Update::NewMessage(new_message) = *update {
-                let source_chat_id = &new_message.message().chat_id();
-
-                if let Some(destination_chats) = self.mappings_index.get(source_chat_id) {
-                    for dest_chat_id in destination_chats {
-                        let pipelines = self
-                            .pipelines_index
-                            .find(source_chat_id, dest_chat_id)
-                            .unwrap_or_else(|_| {
-                                println!(
-                                    "{} {} {}",
-                                    "No pipelines found for source + destination".yellow(),
+        let Some(destination_chats) = self.mappings_index.get(source_chat_id) else {
+            return queued;
+        };
+
+        for dest_chat_id in destination_chats {
+            let pipelines = self
+                .pipelines_index
+                .find(source_chat_id, dest_chat_id)
+                .unwrap_or_else(|_| {
+                    println!(
+                        "{} {} {}",
+                        "No pipelines found for source + destination".yellow(),
+                        source_chat_id,
+                        dest_chat_id
+                    );
+                    &default_pipeline
+                });
+
+            println!(
+                "{}: {} {:?}",
+                "New Message".green(),
+                self.get_chat_info(source_chat_id),
+                pipelines
+            );
+
+            for pipeline in pipelines {
+                // Pipeline is bound to another topic of the source chat
+                if let Some(thread) = &pipeline.source_thread {
+                    if self.topics_index.resolve(source_chat_id, thread) != Some(source_thread_id) {
+                        continue;
+                    }
+                }
+
+                match pipeline.handle(new_message.clone(), *dest_chat_id).await {
+                    Ok(output) => {
+                        let thread_id = self.destination_thread_id(pipeline, dest_chat_id);
+                        #[cfg(feature = "templating")]
+                        if let Some(digest) = &pipeline.digest {
+                            queued |= self
+                                .collect_digest(
+                                    client,
+                                    digest,
+                                    pipeline,
+                                    new_message.message(),
+                                    dest_chat_id,
+                                    thread_id,
+                                    output.messages,
+                                )
+                                .await;
+                            continue;
+                        }
+                        match (self.drips_index.get(dest_chat_id), output.send_at) {
+                            (Some(drip), send_at) => {
+                                queued |= self
+                                    .drip_messages(
+                                        client,
+                                        drip,
+                                        pipeline,
+                                        new_message.message(),
+                                        thread_id,
+                                        send_at.unwrap_or_else(unix_now),
+                                        output.messages,
+                                    )
+                                    .await
+                            }
+                            (None, Some(send_at)) => {
+                                queued |= self
+                                    .schedule_messages(
+                                        client,
+                                        pipeline,
+                                        new_message.message(),
+                                        dest_chat_id,
+                                        thread_id,
+                                        send_at,
+                                        output.messages,
+                                    )
+                                    .await
+                            }
+                            (None, None) => {
+                                self.send_messages(
+                                    client,
                                     source_chat_id,
-                                    dest_chat_id
-                                );
-                                &default_pipeline
-                            });
-
-                        println!(
-                            "{}: {} {:?}",
-                            "New Message".green(),
-                            self.get_chat_info(source_chat_id),
-                            pipelines
-                        );
-
-                        for pipeline in pipelines {
-                            match pipeline.handle(new_message.clone()).await {
-                                Ok(output_message_content) => {
-                                    let send_message = SendMessage::builder()
-                                        .input_message_content(output_message_content)
-                                        .chat_id(*dest_chat_id)
-                                        .build();
-                                    if let Err(e) = client.send_message(send_message).await {
-                                        println!(
-                                            "{} {} {}",
-                                            "Failed on send_message :".red(),
-                                            self.get_route_info(source_chat_id, dest_chat_id),
-                                            e
-                                        );
-                                    } else {
-                                        println!(
-                                            "{} {}",
-                                            "Message sent :".green(),
-                                            self.get_route_info(source_chat_id, dest_chat_id)
-                                        );
-                                    }
-                                }
-                                Err(e) => println!("{}: {:?}", "Error in Pipeline handle".red(), e),
+                                    dest_chat_id,
+                                    thread_id,
+                                    None,
+                                    output.messages,
+                                )
+                                .await;
                             }
                         }
                     }
+                    Err(e) => println!("{}: {:?}", "Error in Pipeline handle".red(), e),
+                }
+            }
+        }
+
+        queued
+    }
+
+    /// Forum topic of destination chat where pipeline sends messages
+    fn destination_thread_id(&self, pipeline: &Pipeline, dest_chat_id: &i64) -> Option<i64> {
+        pipeline.destination_thread.as_ref().and_then(|thread| {
+            let thread_id = self.topics_index.resolve(dest_chat_id, thread);
+            if thread_id.is_none() {
+                println!("{} {:?}", "Destination topic not found :".yellow(), thread);
+            }
+            thread_id
+        })
+    }
+
+    /// Keep messages until `send_at`. Single message is scheduled on Telegram server when pipeline
+    /// allows it, otherwise (or when server refuses) messages are queued. Returns whether they were queued
+    #[cfg(feature = "storage")]
+    #[allow(clippy::too_many_arguments)]
+    async fn schedule_messages(
+        &self,
+        client: &Client<TdJson>,
+        pipeline: &Pipeline,
+        source: &Message,
+        dest_chat_id: &i64,
+        thread_id: Option<i64>,
+        send_at: u64,
+        messages: Vec<InputMessageContent>,
+    ) -> bool {
+        let item = QueueItem {
+            send_at,
+            pipeline: pipeline.name.clone(),
+            source_chat_id: source.chat_id(),
+            source_message_id: source.id(),
+            dest_chat_id: *dest_chat_id,
+            thread_id,
+            messages,
+            attempts: 0,
+            merged_sources: vec![],
+        };
+
+        // Follow-ups can't reply to scheduled message
+        if pipeline.scheduling == SchedulingConf::Server
+            && item.messages.len() == 1
+            && send_at >= unix_now() + MIN_SERVER_SCHEDULING_DELAY
+        {
+            let scheduled = self
+                .send_messages(
+                    client,
+                    &item.source_chat_id,
+                    dest_chat_id,
+                    thread_id,
+                    Some(send_at),
+                    item.messages.clone(),
+                )
+                .await;
+
+            if !scheduled.is_empty() {
+                let tracked = blocking(move || queue::track_scheduled(&item, scheduled));
+                if let Err(e) = tracked.await {
+                    println!("{} {}", "Failed to track scheduled message :".red(), e);
+                }
+                return false;
+            }
+        }
+
+        let route = self.get_route_info(&item.source_chat_id, dest_chat_id);
+        match blocking(move || queue::push(&item)).await {
+            Ok(()) => {
+                println!(
+                    "{} {} {}",
+                    "Message queued until :".yellow(),
+                    send_at,
+                    route
+                );
+                true
+            }
+            Err(e) => {
+                println!("{} {}", "Failed to queue message :".red(), e);
+                false
+            }
+        }
+    }
+
+    /// Without storage there is no queue, messages are sent at once
+    #[cfg(not(feature = "storage"))]
+    #[allow(clippy::too_many_arguments)]
+    async fn schedule_messages(
+        &self,
+        client: &Client<TdJson>,
+        _pipeline: &Pipeline,
+        source: &Message,
+        dest_chat_id: &i64,
+        thread_id: Option<i64>,
+        _send_at: u64,
+        messages: Vec<InputMessageContent>,
+    ) -> bool {
+        self.send_messages(
+            client,
+            &source.chat_id(),
+            dest_chat_id,
+            thread_id,
+            None,
+            messages,
+        )
+        .await;
+        false
+    }
+
+    /// Queue messages into drip-feed of destination chat, not earlier than `send_at`.
+    /// Returns whether they were queued or merged
+    #[cfg(feature = "storage")]
+    #[allow(clippy::too_many_arguments)]
+    async fn drip_messages(
+        &self,
+        _client: &Client<TdJson>,
+        drip: &DripConf,
+        pipeline: &Pipeline,
+        source: &Message,
+        thread_id: Option<i64>,
+        send_at: u64,
+        messages: Vec<InputMessageContent>,
+    ) -> bool {
+        let item = QueueItem {
+            send_at,
+            pipeline: pipeline.name.clone(),
+            source_chat_id: source.chat_id(),
+            source_message_id: source.id(),
+            dest_chat_id: drip.chat,
+            thread_id,
+            messages,
+            attempts: 0,
+            merged_sources: vec![],
+        };
+        let route = self.get_route_info(&item.source_chat_id, &drip.chat);
+
+        let drip = drip.clone();
+        match blocking(move || drip::push(&drip, item)).await {
+            Ok(Dripped::Queued(send_at)) => {
+                println!("{} {} {}", "Message dripped at :".yellow(), send_at, route);
+                true
+            }
+            Ok(Dripped::Merged(send_at)) => {
+                println!(
+                    "{} {} {}",
+                    "Message merged into digest at :".yellow(),
+                    send_at,
+                    route
+                );
+                true
+            }
+            Ok(Dripped::Dropped) => {
+                println!(
+                    "{} {}",
+                    "Drip backlog is full, message dropped :".yellow(),
+                    route
+                );
+                false
+            }
+            Err(e) => {
+                println!("{} {}", "Failed to queue message :".red(), e);
+                false
+            }
+        }
+    }
+
+    /// Without storage there is no queue, messages are sent at once
+    #[cfg(not(feature = "storage"))]
+    #[allow(clippy::too_many_arguments)]
+    async fn drip_messages(
+        &self,
+        client: &Client<TdJson>,
+        drip: &DripConf,
+        _pipeline: &Pipeline,
+        source: &Message,
+        thread_id: Option<i64>,
+        _send_at: u64,
+        messages: Vec<InputMessageContent>,
+    ) -> bool {
+        self.send_messages(
+            client,
+            &source.chat_id(),
+            &drip.chat,
+            thread_id,
+            None,
+            messages,
+        )
+        .await;
+        false
+    }
+
+    /// Collect output into digest of route. Digest with enough items is posted at once.
+    /// Returns whether digest is waiting for its time
+    #[cfg(all(feature = "storage", feature = "templating"))]
+    #[allow(clippy::too_many_arguments)]
+    async fn collect_digest(
+        &self,
+        client: &Client<TdJson>,
+        digest: &Digest,
+        pipeline: &Pipeline,
+        source: &Message,
+        dest_chat_id: &i64,
+        thread_id: Option<i64>,
+        messages: Vec<InputMessageContent>,
+    ) -> bool {
+        let item = DigestItem::new(source, &messages).await;
+        let batch = batches::batch(
+            &pipeline.name,
+            source.chat_id(),
+            *dest_chat_id,
+            thread_id,
+            digest.interval,
+            item,
+        );
+
+        let max_items = digest.max_items;
+        match blocking(move || batches::collect(batch, max_items)).await {
+            Ok(Some(full)) => {
+                self.post_digest(client, Some(digest), full).await;
+                false
+            }
+            Ok(None) => {
+                println!(
+                    "{} {}",
+                    "Message collected into digest :".yellow(),
+                    self.get_route_info(&source.chat_id(), dest_chat_id)
+                );
+                true
+            }
+            Err(e) => {
+                println!("{} {}", "Failed to collect digest :".red(), e);
+                false
+            }
+        }
+    }
+
+    /// Without storage digests can't be kept, every message is posted as digest of its own
+    #[cfg(all(not(feature = "storage"), feature = "templating"))]
+    #[allow(clippy::too_many_arguments)]
+    async fn collect_digest(
+        &self,
+        client: &Client<TdJson>,
+        digest: &Digest,
+        _pipeline: &Pipeline,
+        source: &Message,
+        dest_chat_id: &i64,
+        thread_id: Option<i64>,
+        messages: Vec<InputMessageContent>,
+    ) -> bool {
+        let items = [DigestItem::new(source, &messages).await];
+        self.send_messages(
+            client,
+            &source.chat_id(),
+            dest_chat_id,
+            thread_id,
+            None,
+            digest.render(&items).await,
+        )
+        .await;
+        false
+    }
+
+    /// Render and send digest of collected items. Posted items are removed from storage,
+    /// digest which isn't rendered or sent is kept and tried again later
+    #[cfg(all(feature = "storage", feature = "templating"))]
+    async fn post_digest(
+        &self,
+        client: &Client<TdJson>,
+        digest: Option<&Digest>,
+        batch: DigestBatch,
+    ) {
+        let route = self.get_route_info(&batch.source_chat_id, &batch.dest_chat_id);
+        let messages = match digest {
+            Some(digest) => digest.render(&batch.items).await,
+            None => {
+                println!(
+                    "{} {} {}",
+                    "Digest pipeline not found, digest is kept :".yellow(),
+                    batch.pipeline,
+                    route
+                );
+                vec![]
+            }
+        };
+        let sent = match messages.is_empty() {
+            true => vec![],
+            false => {
+                self.send_messages(
+                    client,
+                    &batch.source_chat_id,
+                    &batch.dest_chat_id,
+                    batch.thread_id,
+                    None,
+                    messages,
+                )
+                .await
+            }
+        };
+
+        let result = match sent.is_empty() {
+            true => blocking(move || batches::postpone(&batch)).await,
+            false => {
+                println!(
+                    "{} {} {}",
+                    "Digest posted :".green(),
+                    batch.items.len(),
+                    route
+                );
+                blocking(move || batches::remove_posted(&batch)).await
+            }
+        };
+        if let Err(e) = result {
+            println!("{} {} {}", "Failed to update digest :".red(), route, e);
+        }
+    }
+
+    /// Post digests which time has come
+    #[cfg(all(feature = "storage", feature = "templating"))]
+    async fn flush_digests(&self, client: &Client<TdJson>) {
+        let now = unix_now();
+        let due = match blocking(move || batches::due(now)).await {
+            Ok(due) => due,
+            Err(e) => {
+                println!("{} {}", "Failed to read digests :".red(), e);
+                return;
+            }
+        };
+
+        for batch in due {
+            // Pipeline may be renamed meanwhile, then digest of another pipeline of route is used
+            let pipelines = self
+                .pipelines_index
+                .find(&batch.source_chat_id, &batch.dest_chat_id)
+                .map(|pipelines| pipelines.as_slice())
+                .unwrap_or_default();
+            let digest = pipelines
+                .iter()
+                .find(|p| p.name == batch.pipeline && p.digest.is_some())
+                .or_else(|| pipelines.iter().find(|p| p.digest.is_some()))
+                .and_then(|pipeline| pipeline.digest.as_ref());
+
+            self.post_digest(client, digest, batch).await;
+        }
+    }
+
+    /// Send queued messages and post digests which time has come
+    #[cfg(feature = "storage")]
+    async fn send_queued(&self, client: &Client<TdJson>) {
+        let now = unix_now();
+        let items = match blocking(move || queue::take_due(now)).await {
+            Ok(items) => items,
+            Err(e) => {
+                println!("{} {}", "Failed to read queue :".red(), e);
+                return;
+            }
+        };
+
+        for item in items {
+            let sent = self
+                .send_messages(
+                    client,
+                    &item.source_chat_id,
+                    &item.dest_chat_id,
+                    item.thread_id,
+                    None,
+                    item.messages.clone(),
+                )
+                .await;
+            if !sent.is_empty() {
+                continue;
+            }
+
+            // Nothing is sent, e.g. connection is lost, so item is tried again later
+            let route = self.get_route_info(&item.source_chat_id, &item.dest_chat_id);
+            match blocking(move || queue::retry(item)).await {
+                Ok(true) => println!("{} {}", "Queued message is retried later :".yellow(), route),
+                Ok(false) => println!(
+                    "{} {}",
+                    "Queued message dropped after retries :".red(),
+                    route
+                ),
+                Err(e) => println!(
+                    "{} {} {}",
+                    "Failed to retry queued message :".red(),
+                    route,
+                    e
+                ),
+            }
+        }
+
+        #[cfg(feature = "templating")]
+        self.flush_digests(client).await;
+    }
+
+    #[cfg(not(feature = "storage"))]
+    async fn send_queued(&self, _client: &Client<TdJson>) {}
+
+    /// Cancel queued and scheduled on server messages of deleted source messages
+    #[cfg(feature = "storage")]
+    async fn cancel_deleted(&self, client: &Client<TdJson>, chat_id: i64, message_ids: &[i64]) {
+        let ids = message_ids.to_vec();
+        match blocking(move || queue::cancel_source(chat_id, &ids)).await {
+            Ok(0) => {}
+            Ok(cancelled) => println!(
+                "{} {} {}",
+                "Queued messages cancelled :".yellow(),
+                cancelled,
+                self.get_chat_info(&chat_id)
+            ),
+            Err(e) => println!("{} {}", "Failed to cancel queued messages :".red(), e),
+        }
+
+        let ids = message_ids.to_vec();
+        let scheduled = blocking(move || queue::take_scheduled(chat_id, &ids))
+            .await
+            .unwrap_or_else(|e| {
+                println!("{} {}", "Failed to cancel scheduled messages :".red(), e);
+                vec![]
+            });
+        for (dest_chat_id, message_ids) in scheduled {
+            let delete_messages = DeleteMessages::builder()
+                .chat_id(dest_chat_id)
+                .message_ids(message_ids)
+                .revoke(true)
+                .build();
+
+            match client.delete_messages(delete_messages).await {
+                Ok(_) => println!(
+                    "{} {}",
+                    "Scheduled messages cancelled :".yellow(),
+                    self.get_route_info(&chat_id, &dest_chat_id)
+                ),
+                Err(e) => println!(
+                    "{} {} {}",
+                    "Failed on delete_messages :".red(),
+                    self.get_route_info(&chat_id, &dest_chat_id),
+                    e
+                ),
+            }
+        }
+    }
+
+    /// Send pipeline output messages, scheduled on server when `send_at` is provided.
+    /// Follow-ups of media message are sent as replies to it. Returns ids of sent messages
+    async fn send_messages(
+        &self,
+        client: &Client<TdJson>,
+        source_chat_id: &i64,
+        dest_chat_id: &i64,
+        thread_id: Option<i64>,
+        send_at: Option<u64>,
+        output_messages: Vec<InputMessageContent>,
+    ) -> Vec<i64> {
+        let reply_to_first = output_messages
+            .first()
+            .is_some_and(|m| !matches!(m, InputMessageContent::InputMessageText(_)));
+        let mut sent = vec![];
+
+        for output_message_content in output_messages {
+            let mut builder = SendMessage::builder();
+            if let Some(thread_id) = thread_id {
+                builder.message_thread_id(thread_id);
+            }
+            if let Some(message_id) = sent.first().filter(|_| reply_to_first) {
+                builder.reply_to_message_id(*message_id);
+            }
+            if let Some(send_at) = send_at {
+                let scheduling_state = MessageSchedulingState::SendAtDate(
+                    MessageSchedulingStateSendAtDate::builder()
+                        .send_date(send_at as i32)
+                        .build(),
+                );
+                builder.options(
+                    MessageSendOptions::builder()
+                        .scheduling_state(scheduling_state)
+                        .build(),
+                );
+            }
+            let send_message = builder
+                .input_message_content(output_message_content)
+                .chat_id(*dest_chat_id)
+                .build();
+
+            match client.send_message(send_message).await {
+                Ok(message) => {
+                    sent.push(message.id());
+                    println!(
+                        "{} {}",
+                        match send_at {
+                            Some(_) => "Message scheduled :".green(),
+                            None => "Message sent :".green(),
+                        },
+                        self.get_route_info(source_chat_id, dest_chat_id)
+                    );
+                }
+                Err(e) => {
+                    println!(
+                        "{} {} {}",
+                        "Failed on send_message :".red(),
+                        self.get_route_info(source_chat_id, dest_chat_id),
+                        e
+                    );
+                    // Follow-ups make no sense without the first message
+                    break;
                 }
             }
         }
+
+        sent
     }
 
     /// Authentication process with signal auth handler
@@ -516,10 +1198,64 @@ impl From<Vec<PipelineConf>> for PipelinesIndex {
     }
 }
 
+/// Named forum topics of chats. Indexed by (chat_id, topic name)
+#[derive(Debug, Clone)]
+pub struct TopicsIndex {
+    map: HashMap<(i64, String), i64>,
+}
+
+impl TopicsIndex {
+    /// Find message thread id of the topic in the chat
+    pub fn resolve(&self, chat_id: &i64, thread: &ThreadConf) -> Option<i64> {
+        match thread {
+            ThreadConf::Id(id) => Some(*id),
+            ThreadConf::Name(name) => self.map.get(&(*chat_id, name.clone())).copied(),
+        }
+    }
+}
+
+impl From<Vec<TopicConf>> for TopicsIndex {
+    fn from(topics_conf: Vec<TopicConf>) -> Self {
+        let map = topics_conf
+            .into_iter()
+            .map(|topic| ((topic.chat, topic.name), topic.id))
+            .collect();
+
+        TopicsIndex { map }
+    }
+}
+
+/// Drip-feeds of destination chats. Indexed by chat id
+#[derive(Debug, Clone)]
+pub struct DripsIndex {
+    map: HashMap<i64, DripConf>,
+}
+
+impl DripsIndex {
+    /// Drip-feed of the chat if it's configured
+    pub fn get(&self, chat_id: &i64) -> Option<&DripConf> {
+        self.map.get(chat_id)
+    }
+}
+
+impl From<Vec<DripConf>> for DripsIndex {
+    fn from(drips_conf: Vec<DripConf>) -> Self {
+        let map = drips_conf
+            .into_iter()
+            .map(|drip| (drip.chat, drip))
+            .collect();
+
+        DripsIndex { map }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::app::{MappingsIndex, PipelinesIndex};
-    use crate::config::{IdMapConf, PipelineConf, RouteConf};
+    use crate::app::{DripsIndex, MappingsIndex, PipelinesIndex, TopicsIndex};
+    use crate::config::{
+        DripConf, DripFullConf, IdMapConf, OverflowConf, PipelineConf, RouteConf, SchedulingConf,
+        ThreadConf, TopicConf,
+    };
     use std::collections::HashMap;
 
     fn mapping_example() -> MappingsIndex {
@@ -545,9 +1281,17 @@ mod tests {
             route: RouteConf {
                 source: src,
                 destination: dest,
+                src_thread: None,
+                dest_thread: None,
             },
             filters: vec![],
             pipes: vec![],
+            overflow: OverflowConf::default(),
+            photo_size: None,
+            delay: None,
+            scheduling: SchedulingConf::default(),
+            #[cfg(feature = "templating")]
+            digest: None,
         }
     }
 
@@ -603,4 +1347,47 @@ mod tests {
 
         assert_eq!(2, pipelines.find(&1, &10).unwrap().len());
     }
+
+    #[test]
+    fn test_topics_index() {
+        let topics = TopicsIndex::from(vec![TopicConf {
+            chat: 1,
+            id: 5,
+            name: "Announcements".to_string(),
+        }]);
+
+        let announcements = ThreadConf::Name("Announcements".to_string());
+        assert_eq!(Some(5), topics.resolve(&1, &announcements));
+        assert_eq!(None, topics.resolve(&2, &announcements));
+        assert_eq!(Some(7), topics.resolve(&2, &ThreadConf::Id(7)));
+    }
+
+    #[test]
+    fn test_drips_index() {
+        let drips: Vec<DripConf> = serde_json::from_str(
+            r#"[{"chat": 1}, {"chat": 2, "interval": 60, "when_full": "digest"}]"#,
+        )
+        .unwrap();
+        let drips = DripsIndex::from(drips);
+
+        let drip = drips.get(&1).unwrap();
+        assert_eq!(600, drip.interval);
+        assert_eq!(100, drip.max_backlog);
+        assert_eq!(DripFullConf::DropOldest, drip.when_full);
+        assert_eq!(DripFullConf::Digest, drips.get(&2).unwrap().when_full);
+        assert!(drips.get(&3).is_none());
+    }
+
+    #[test]
+    fn test_route_threads_deserialize() {
+        let route: RouteConf =
+            serde_json::from_str(r#"{"src": 1, "src_thread": "Announcements", "dest_thread": 5}"#)
+                .unwrap();
+
+        assert_eq!(
+            Some(ThreadConf::Name("Announcements".to_string())),
+            route.src_thread
+        );
+        assert_eq!(Some(ThreadConf::Id(5)), route.dest_thread);
+    }
 }
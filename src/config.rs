@@ -1,6 +1,7 @@
 use rust_tdlib::types::FormattedText;
 use serde::{Deserialize, Serialize};
 use serde_json::from_reader;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::BufReader;
@@ -28,6 +29,98 @@ pub struct Configs {
     /// Chat's pipelines. How to transform and filter messages before sending to destinations
     #[serde(default)]
     pub pipelines: Vec<PipelineConf>,
+    /// Named forum topics, so routes can refer to topics by name instead of thread id
+    #[serde(default)]
+    pub topics: Vec<TopicConf>,
+    /// Where filters keep their state, e.g. seen messages of Unique
+    #[serde(default)]
+    pub storage: StorageConf,
+    /// Destination chats which receive messages one per interval
+    #[serde(default)]
+    pub drips: Vec<DripConf>,
+}
+
+/// Drip-feed of destination chat. Its messages are queued and sent one per interval
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DripConf {
+    /// Destination chat
+    pub chat: i64,
+    /// Seconds between messages
+    #[serde(default = "default_drip_interval")]
+    pub interval: u64,
+    /// Maximum number of queued messages
+    #[serde(default = "default_drip_max_backlog")]
+    pub max_backlog: usize,
+    #[serde(default)]
+    pub when_full: DripFullConf,
+}
+
+/// What happens to new message when drip-feed backlog is full
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DripFullConf {
+    /// Drop the earliest queued message
+    #[default]
+    DropOldest,
+    /// Drop new message
+    DropNewest,
+    /// Merge new message into the last queued one
+    Digest,
+}
+
+fn default_drip_interval() -> u64 {
+    10 * 60
+}
+
+fn default_drip_max_backlog() -> usize {
+    100
+}
+
+/// Storage of filters' state
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageConf {
+    #[serde(default)]
+    pub backend: StorageBackendConf,
+    /// Database file path, used by sqlite backend
+    #[serde(default = "default_storage_path")]
+    pub path: String,
+    /// Seconds for which entries are kept after the last change, by namespace
+    /// (e.g. "unique/news") or by filter (e.g. "unique")
+    #[serde(default)]
+    pub retention: BTreeMap<String, u64>,
+    /// Seconds between removals of expired and outdated entries
+    #[serde(default = "default_compaction_interval")]
+    pub compaction_interval: u64,
+}
+
+impl Default for StorageConf {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackendConf::default(),
+            path: default_storage_path(),
+            retention: BTreeMap::new(),
+            compaction_interval: default_compaction_interval(),
+        }
+    }
+}
+
+fn default_storage_path() -> String {
+    "storage/telemap.db".to_string()
+}
+
+fn default_compaction_interval() -> u64 {
+    60 * 60
+}
+
+/// Storage implementation
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackendConf {
+    /// SQLite database file
+    #[default]
+    Sqlite,
+    /// Kept in memory until restart
+    Memory,
 }
 
 /// Map struct of source and destinations chats.
@@ -42,6 +135,25 @@ pub struct IdMapConf {
     pub destinations: Vec<i64>,
 }
 
+/// Forum topic name of a chat. Topic's id is the message_thread_id of the messages inside it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TopicConf {
+    /// Chat which the topic belongs to
+    pub chat: i64,
+    /// Message thread id of the topic
+    pub id: i64,
+    /// Topic name, used in routes
+    pub name: String,
+}
+
+/// Forum topic (message thread) given by thread id or by topic name from `topics`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ThreadConf {
+    Id(i64),
+    Name(String),
+}
+
 /// Routing configuration with optional source and destination, but one of them is required.
 /// For incoming message there could be multiple routes, so there are rules
 /// 1. route with src and dest - 1 priority
@@ -49,12 +161,19 @@ pub struct IdMapConf {
 /// 3. route with only src - 3 priority
 ///
 /// The highest priority route will be used.
+/// Optional src_thread and dest_thread narrow the route down to forum topics.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RouteConf {
     #[serde(rename(serialize = "src", deserialize = "src"))]
     pub source: Option<i64>,
     #[serde(rename(serialize = "dest", deserialize = "dest"))]
     pub destination: Option<i64>,
+    /// Pipeline applies only to messages from this topic of source chat
+    #[serde(default)]
+    pub src_thread: Option<ThreadConf>,
+    /// Messages are sent into this topic of destination chat
+    #[serde(default)]
+    pub dest_thread: Option<ThreadConf>,
 }
 
 /// Used for default routing. 0 -> 0 routing is for all chats which has not concrete routing specified.
@@ -63,6 +182,8 @@ impl Default for RouteConf {
         RouteConf {
             source: Some(0),
             destination: Some(0),
+            src_thread: None,
+            dest_thread: None,
         }
     }
 }
@@ -81,12 +202,274 @@ pub struct PipelineConf {
     /// List of pipelines
     #[serde(default)]
     pub pipes: Vec<PipeConf>,
+    /// What to do with text/caption longer than Telegram allows
+    #[serde(default)]
+    pub overflow: OverflowConf,
+    /// Size of copied photos, the largest by default
+    #[serde(default)]
+    pub photo_size: Option<PhotoSizeConf>,
+    /// Seconds after source message when output is sent
+    #[serde(default)]
+    pub delay: Option<u64>,
+    /// Where delayed messages wait for their time
+    #[serde(default)]
+    pub scheduling: SchedulingConf,
+    /// Collect output into periodic digest instead of sending every message
+    #[cfg(feature = "templating")]
+    #[serde(default)]
+    pub digest: Option<DigestConf>,
 }
 
+/// Digest of route. Collected messages are posted as one summary
+#[cfg(feature = "templating")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestConf {
+    /// Seconds after the first collected message when digest is posted
+    #[serde(default = "default_digest_interval")]
+    pub interval: u64,
+    /// Number of collected messages which posts digest at once
+    #[serde(default = "default_digest_max_items")]
+    pub max_items: usize,
+    /// Jinja template of digest, rendered with list of `items`
+    #[serde(default = "default_digest_template")]
+    pub template: String,
+    #[serde(default)]
+    pub parse_mode: Option<ParseMode>,
+}
+
+#[cfg(feature = "templating")]
+fn default_digest_interval() -> u64 {
+    60 * 60
+}
+
+#[cfg(feature = "templating")]
+fn default_digest_max_items() -> usize {
+    20
+}
+
+#[cfg(feature = "templating")]
+fn default_digest_template() -> String {
+    "{% for item in items %}{% if item.title %}{{ item.title }}: {% endif %}{{ item.first_line }}\
+     {% if item.link %}\n{{ item.link }}{% endif %}\n\n{% endfor %}"
+        .to_string()
+}
+
+/// Where delayed messages wait for their time
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchedulingConf {
+    /// Scheduled message on Telegram server when possible, otherwise local queue
+    #[default]
+    Server,
+    /// Local queue in storage
+    Local,
+}
+
+/// Photo size choice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PhotoSizeConf {
+    /// The largest size not exceeding dimension (width and height) in pixels
+    MaxDimension(i32),
+    /// Size type, e.g. "s", "m", "x", "y", "w"
+    Type(String),
+}
+
+/// Handling of text (4096) and caption (1024) overflow
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowConf {
+    /// Split into several messages on paragraph/sentence boundaries. Rest of caption is sent as replies to media
+    #[default]
+    Split,
+    /// Move caption to follow-up text message replying to media. Texts are split
+    Reply,
+    /// Cut with ellipsis
+    Truncate,
+    /// Send as it is, sending fails
+    None,
+}
+
+/// Sender rules, message matches when sender matches any of them
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SendersConf {
+    /// Sender user ids
+    #[serde(default)]
+    pub users: Vec<i64>,
+    /// Sender users or chats by @username
+    #[serde(default)]
+    pub usernames: Vec<String>,
+    /// Sender chat ids (channel posts and anonymous admins)
+    #[serde(default)]
+    pub chats: Vec<i64>,
+    /// Author signatures of channel posts and anonymous admins
+    #[serde(default)]
+    pub signatures: Vec<String>,
+    /// Any bot
+    #[serde(default)]
+    pub bots: bool,
+    /// Any administrator of the source chat
+    #[serde(default)]
+    pub admins: bool,
+}
+
+/// Syntax of Format template
+#[cfg(feature = "templating")]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateSyntax {
+    /// Rust-style `{variable}` substitution
+    #[default]
+    Simple,
+    /// Jinja-like templates with conditions, loops and filters
+    Jinja,
+}
+
+/// Text formatting syntax parsed into text entities
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParseMode {
+    /// Telegram Bot API "Markdown"
+    Markdown,
+    /// Telegram Bot API "MarkdownV2"
+    MarkdownV2,
+    /// Telegram Bot API "HTML"
+    Html,
+}
+
+fn default_forward_origin_template() -> String {
+    "Originally from {origin}".to_string()
+}
+
+#[cfg(feature = "templating")]
+fn default_date_format() -> String {
+    "%Y-%m-%d %H:%M".to_string()
+}
+
+#[cfg(feature = "openai")]
 fn default_model() -> String {
     "gpt-3.5-turbo".to_string()
 }
 
+/// Part of message which makes its identity for Unique filter
+#[cfg(feature = "storage")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UniqueKey {
+    /// Text/caption as it is
+    Text,
+    /// Lowercase text/caption without punctuation and extra whitespace
+    NormalizedText,
+    /// Unique id of file (for photo - of the size which is sent)
+    File,
+}
+
+/// How Counter chooses messages
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CounterMode {
+    /// Skip `count` messages, pass the next one
+    #[default]
+    Skip,
+    /// Pass every `count`-th message
+    EveryNth,
+    /// Pass first `count` messages of every `period`
+    FirstNPerPeriod,
+    /// Pass messages with `probability`
+    RandomSample,
+}
+
+/// What Counter counts separately
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CounterKey {
+    /// Source and destination chats
+    #[default]
+    Route,
+    /// Source chat
+    Source,
+    /// Message sender
+    Sender,
+}
+
+/// What RateLimit limits separately
+#[cfg(feature = "storage")]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// Source and destination chats
+    #[default]
+    Route,
+    /// Source chat
+    Source,
+    /// Message sender
+    Sender,
+    /// Destination chat
+    Dest,
+}
+
+/// What RateLimit does with messages over the limit
+#[cfg(feature = "storage")]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitOverflow {
+    /// Drop message
+    #[default]
+    Reject,
+    /// Send message when the limit allows it
+    Defer,
+}
+
+/// Time which Schedule checks
+#[cfg(feature = "schedule")]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleTime {
+    /// Date of message
+    #[default]
+    Message,
+    /// Current time
+    Now,
+}
+
+#[cfg(feature = "storage")]
+fn default_rate_limit_max_delay() -> u64 {
+    60 * 60
+}
+
+fn default_counter_period() -> u64 {
+    60 * 60
+}
+
+fn default_counter_probability() -> f64 {
+    1.0
+}
+
+#[cfg(feature = "storage")]
+fn default_unique_keys() -> Vec<UniqueKey> {
+    vec![UniqueKey::Text, UniqueKey::File]
+}
+
+#[cfg(feature = "storage")]
+fn default_near_duplicate_distance() -> u32 {
+    3
+}
+
+#[cfg(feature = "similar_image")]
+fn default_similar_image_distance() -> u32 {
+    5
+}
+
+#[cfg(feature = "similar_image")]
+fn default_similar_image_per_destination() -> bool {
+    true
+}
+
+#[cfg(feature = "storage")]
+fn default_near_duplicate_window() -> u64 {
+    24 * 60 * 60
+}
+
 /// All available Filters
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "@type")]
@@ -97,6 +480,15 @@ pub enum FilterConf {
     Photo,
     Animation,
     Document,
+    Audio,
+    Voice,
+    VideoNote,
+    Sticker,
+    Poll,
+    Location,
+    Venue,
+    Contact,
+    Dice,
     AnyFile,
     Duration {
         duration: i32,
@@ -107,13 +499,45 @@ pub enum FilterConf {
         op: String,
     },
     Counter {
+        /// Skipped messages (skip) or N (every_nth, first_n_per_period)
+        #[serde(default)]
+        count: u32,
+        #[serde(default)]
+        mode: CounterMode,
+        #[serde(default)]
+        per: CounterKey,
+        /// Seconds of first_n_per_period window
+        #[serde(default = "default_counter_period")]
+        period: u64,
+        /// Chance to pass in random_sample mode, from 0 to 1
+        #[serde(default = "default_counter_probability")]
+        probability: f64,
+        /// Seed of random_sample, makes sample reproducible
         #[serde(default)]
-        count: u8,
+        seed: Option<u64>,
+        /// Namespace of counts, pipeline name by default
+        #[serde(default)]
+        namespace: Option<String>,
     },
     FileSize {
         size: f32,
         op: String,
     },
+    Mime {
+        #[serde(default)]
+        allow: Vec<String>,
+        #[serde(default)]
+        deny: Vec<String>,
+    },
+    Extension {
+        #[serde(default)]
+        allow: Vec<String>,
+        #[serde(default)]
+        deny: Vec<String>,
+    },
+    FileName {
+        exp: String,
+    },
     Regexp {
         exp: String,
     },
@@ -123,8 +547,124 @@ pub enum FilterConf {
     WhiteList {
         words: Vec<String>,
     },
+    Forwarded,
+    NotForwarded,
+    HiddenForward,
+    ForwardedFrom {
+        #[serde(default)]
+        chats: Vec<i64>,
+        #[serde(default)]
+        usernames: Vec<String>,
+    },
+    ForwardAge {
+        age: i64,
+        op: String,
+    },
+    HasEntity {
+        types: Vec<String>,
+    },
+    MaxLinks {
+        count: usize,
+    },
+    LinkDomain {
+        #[serde(default)]
+        allow: Vec<String>,
+        #[serde(default)]
+        deny: Vec<String>,
+    },
+    Hashtag {
+        any: Vec<String>,
+    },
+    Cashtag {
+        any: Vec<String>,
+    },
+    Mention {
+        any: Vec<String>,
+    },
+    BotCommand {
+        any: Vec<String>,
+    },
+    AllowSenders(SendersConf),
+    DenySenders(SendersConf),
+    Variable {
+        name: String,
+        #[serde(default)]
+        value: Option<String>,
+    },
     #[cfg(feature = "storage")]
-    Unique,
+    Unique {
+        /// Namespace of seen messages, pipeline name by default
+        #[serde(default)]
+        namespace: Option<String>,
+        /// Seconds after which seen message passes again
+        #[serde(default)]
+        ttl: Option<u64>,
+        #[serde(default = "default_unique_keys")]
+        keys: Vec<UniqueKey>,
+        /// Same message may be sent once to every destination
+        #[serde(default)]
+        per_destination: bool,
+    },
+    #[cfg(feature = "schedule")]
+    Schedule {
+        /// Timezone of rules, e.g. "Europe/Berlin". UTC by default
+        #[serde(default)]
+        tz: Option<String>,
+        /// Windows ("Mon-Fri 09:00-18:00") or cron expressions when messages pass
+        #[serde(default)]
+        allow: Vec<String>,
+        /// Windows or cron expressions when messages are rejected
+        #[serde(default)]
+        deny: Vec<String>,
+        /// Dates ("2025-12-24" or "12-25" every year) when messages are rejected
+        #[serde(default)]
+        holidays: Vec<String>,
+        #[serde(default)]
+        time: ScheduleTime,
+    },
+    #[cfg(feature = "storage")]
+    RateLimit {
+        /// Messages passed in `per_seconds`
+        max: u32,
+        per_seconds: u64,
+        #[serde(default)]
+        key: RateLimitKey,
+        #[serde(default)]
+        on_exceed: RateLimitOverflow,
+        /// Longest delay of deferred message in seconds, later messages are rejected
+        #[serde(default = "default_rate_limit_max_delay")]
+        max_delay: u64,
+        /// Namespace of token buckets, pipeline name by default
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    #[cfg(feature = "storage")]
+    NearDuplicate {
+        /// Namespace of seen texts, pipeline name by default
+        #[serde(default)]
+        namespace: Option<String>,
+        /// Maximum Hamming distance of similar texts' fingerprints
+        #[serde(default = "default_near_duplicate_distance")]
+        distance: u32,
+        /// Seconds for which seen texts are kept
+        #[serde(default = "default_near_duplicate_window")]
+        window: u64,
+    },
+    #[cfg(feature = "similar_image")]
+    SimilarImage {
+        /// Namespace of seen photos, pipeline name by default
+        #[serde(default)]
+        namespace: Option<String>,
+        /// Maximum Hamming distance of similar photos' hashes
+        #[serde(default = "default_similar_image_distance")]
+        distance: u32,
+        /// Seconds for which seen photos are kept
+        #[serde(default = "default_near_duplicate_window")]
+        window: u64,
+        /// Photos are compared with ones sent to the same destination only
+        #[serde(default = "default_similar_image_per_destination")]
+        per_destination: bool,
+    },
     #[cfg(feature = "openai")]
     OpenAi {
         #[serde(default = "default_model")]
@@ -141,12 +681,37 @@ pub enum FilterConf {
 #[serde(tag = "@type")]
 pub enum PipeConf {
     Transform,
+    #[cfg(feature = "templating")]
     Format {
         template: String,
+        #[serde(default)]
+        parse_mode: Option<ParseMode>,
+        #[serde(default)]
+        timezone: Option<String>,
+        #[serde(default = "default_date_format")]
+        date_format: String,
+        #[serde(default)]
+        default: String,
+        #[serde(default)]
+        syntax: TemplateSyntax,
+    },
+    /// Delay output until time which Schedule rules allow
+    #[cfg(feature = "schedule")]
+    Schedule {
+        #[serde(default)]
+        tz: Option<String>,
+        #[serde(default)]
+        allow: Vec<String>,
+        #[serde(default)]
+        deny: Vec<String>,
+        #[serde(default)]
+        holidays: Vec<String>,
     },
     StaticText {
         #[serde(default)]
         formatted_text: FormattedText,
+        #[serde(default)]
+        parse_mode: Option<ParseMode>,
     },
     StaticPhoto {
         path: String,
@@ -163,4 +728,20 @@ pub enum PipeConf {
         #[serde(default)]
         all: bool,
     },
+    ForwardOrigin {
+        #[serde(default = "default_forward_origin_template")]
+        template: String,
+    },
+    Links {
+        #[serde(default)]
+        remove: Vec<String>,
+        #[serde(default)]
+        replace_domain: BTreeMap<String, String>,
+        #[serde(default)]
+        add_query: BTreeMap<String, String>,
+        #[serde(default)]
+        strip_query: Vec<String>,
+        #[serde(default)]
+        unwrap_text_urls: bool,
+    },
 }
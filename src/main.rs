@@ -1,6 +1,8 @@
 mod app;
 mod config;
 mod processing;
+#[cfg(feature = "storage")]
+mod storage;
 
 use crate::app::App;
 use crate::config::read_configs;
@@ -14,6 +16,9 @@ pub struct CliArgs {
     #[argh(option, short = 'c')]
     /// path to the configuration json file
     pub config_path: String,
+    #[cfg(feature = "storage")]
+    #[argh(subcommand)]
+    pub command: Option<storage::command::StorageCommand>,
 }
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 8)]
@@ -22,7 +27,24 @@ async fn main() {
     env_logger::init();
 
     let args: CliArgs = from_env();
-    let mut app = App::from(read_configs(Path::new(&args.config_path)).unwrap());
+    let configs = read_configs(Path::new(&args.config_path)).unwrap();
+
+    #[cfg(feature = "storage")]
+    {
+        storage::init(&configs.storage).expect("Storage is not opened");
+
+        if let Some(command) = args.command {
+            if let Err(e) = command.run() {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+
+        storage::start_compaction(&configs.storage);
+    }
+
+    let mut app = App::from(configs);
 
     app.start().await;
 }
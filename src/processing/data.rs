@@ -1,7 +1,34 @@
-use rust_tdlib::types::{
-    FormattedText, InputMessageAnimation, InputMessageContent, InputMessagePhoto, InputMessageText,
-    InputMessageVideo, UpdateNewMessage,
-};
+use crate::config::PhotoSizeConf;
+#[cfg(feature = "storage")]
+use crate::processing::helpers::find_sender_id;
+use crate::processing::helpers::with_message_text;
+use rust_tdlib::types::{FormattedText, InputMessageContent, UpdateNewMessage};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Variables of one pipeline run (one message and destination), shared between filters and pipes.
+/// Filters get read-only DataHub, so variables are set through shared reference
+#[derive(Debug, Default)]
+pub struct Variables(Mutex<BTreeMap<String, String>>);
+
+impl Variables {
+    pub fn set(&self, name: &str, value: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), value.to_string());
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.0.lock().unwrap().get(name).cloned()
+    }
+
+    /// All variables
+    #[cfg(feature = "templating")]
+    pub fn all(&self) -> BTreeMap<String, String> {
+        self.0.lock().unwrap().clone()
+    }
+}
 
 #[derive(Debug)]
 /// Main struct which contains input message and output builder.
@@ -10,6 +37,16 @@ pub struct DataHub {
     pub input: UpdateNewMessage,
     /// Optional message content. This is used in SendMessage::builder().input_message_content(here)
     pub output: Option<InputMessageContent>,
+    /// Destination chat id. None when message isn't handled by pipeline (e.g. tests)
+    pub destination: Option<i64>,
+    /// Name of pipeline handling message. None in tests
+    pub pipeline: Option<String>,
+    /// Variables set by filters and pipes, e.g. Regexp named captures
+    pub variables: Variables,
+    /// Size of photo which is sent, the largest by default
+    pub photo_size: Option<PhotoSizeConf>,
+    /// Unix time to send output at. Set by filters deferring message, None to send at once
+    send_at: Mutex<Option<u64>>,
 }
 
 impl DataHub {
@@ -17,41 +54,59 @@ impl DataHub {
         DataHub {
             input,
             output: None,
+            destination: None,
+            pipeline: None,
+            variables: Variables::default(),
+            photo_size: None,
+            send_at: Mutex::new(None),
         }
     }
 
-    pub fn set_output_text(&mut self, text: FormattedText) {
-        match &self.output {
-            Some(InputMessageContent::InputMessageVideo(m)) => {
-                self.output = Some(InputMessageContent::InputMessageVideo(
-                    InputMessageVideo::builder()
-                        .video(m.video())
-                        .caption(text)
-                        .build(),
-                ));
-            }
-            Some(InputMessageContent::InputMessagePhoto(m)) => {
-                self.output = Some(InputMessageContent::InputMessagePhoto(
-                    InputMessagePhoto::builder()
-                        .photo(m.photo())
-                        .caption(text)
-                        .build(),
-                ));
-            }
-            Some(InputMessageContent::InputMessageAnimation(m)) => {
-                self.output = Some(InputMessageContent::InputMessageAnimation(
-                    InputMessageAnimation::builder()
-                        .animation(m.animation())
-                        .caption(text)
-                        .build(),
-                ));
-            }
-            Some(InputMessageContent::InputMessageText(_)) => {
-                self.output = Some(InputMessageContent::InputMessageText(
-                    InputMessageText::builder().text(text).build(),
-                ))
+    /// Defer sending of output. The latest time wins when several filters defer message
+    pub fn defer_until(&self, send_at: u64) {
+        let mut current = self.send_at.lock().unwrap();
+        *current = Some(current.map_or(send_at, |at| at.max(send_at)));
+    }
+
+    /// Unix time to send output at, None to send at once
+    pub fn send_at(&self) -> Option<u64> {
+        *self.send_at.lock().unwrap()
+    }
+
+    /// Default namespace of stored state: pipeline name, or route (source and destination chats)
+    /// for pipelines without name, so they don't share state
+    #[cfg(feature = "storage")]
+    pub fn default_namespace(&self) -> String {
+        let chat_id = self.input.message().chat_id();
+
+        match (&self.pipeline, self.destination) {
+            (Some(pipeline), _) if !pipeline.is_empty() => pipeline.clone(),
+            (_, Some(destination)) => format!("route:{}:{}", chat_id, destination),
+            (_, None) => format!("route:{}", chat_id),
+        }
+    }
+
+    /// Source chat, sender and destination chat ids, stored state is related to them
+    #[cfg(feature = "storage")]
+    pub fn related_ids(&self) -> Vec<i64> {
+        let message = self.input.message();
+
+        let mut ids = vec![message.chat_id()];
+        for id in std::iter::once(find_sender_id(message)).chain(self.destination) {
+            if !ids.contains(&id) {
+                ids.push(id);
             }
-            _ => (),
-        };
+        }
+        ids
+    }
+
+    pub fn set_output_text(&mut self, text: FormattedText) {
+        if let Some(output) = self
+            .output
+            .as_ref()
+            .and_then(|m| with_message_text(m, text))
+        {
+            self.output = Some(output);
+        }
     }
 }
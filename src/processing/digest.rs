@@ -0,0 +1,325 @@
+//! Digests: several outputs joined into one post
+#[cfg(feature = "templating")]
+use crate::config::{DigestConf, OverflowConf, ParseMode};
+#[cfg(feature = "templating")]
+use crate::processing::helpers::find_output_message_text;
+#[cfg(feature = "storage")]
+use crate::processing::helpers::with_message_text;
+#[cfg(feature = "templating")]
+use crate::processing::overflow::split_output;
+#[cfg(feature = "storage")]
+use crate::processing::overflow::MAX_TEXT_LENGTH;
+#[cfg(feature = "templating")]
+use crate::processing::telegram::{chat_title, message_link, parse_text};
+#[cfg(feature = "templating")]
+use crate::processing::templating::{environment, validate};
+#[cfg(feature = "storage")]
+use crate::processing::text_editor::TextEditor;
+#[cfg(feature = "templating")]
+use minijinja::{context, Environment};
+#[cfg(any(feature = "storage", feature = "templating"))]
+use rust_tdlib::types::{FormattedText, InputMessageContent};
+#[cfg(feature = "templating")]
+use rust_tdlib::types::{InputMessageText, Message};
+#[cfg(feature = "templating")]
+use serde::{Deserialize, Serialize};
+
+/// Separator of joined texts
+#[cfg(feature = "storage")]
+const SEPARATOR: &str = "\n\n";
+
+/// Append messages to digest. Text is joined to the last text message while it fits Telegram limit,
+/// other messages are appended as they are
+#[cfg(feature = "storage")]
+pub(crate) fn append_to_digest(
+    digest: &mut Vec<InputMessageContent>,
+    messages: Vec<InputMessageContent>,
+) {
+    for message in messages {
+        let joined = match (digest.last(), &message) {
+            (
+                Some(last @ InputMessageContent::InputMessageText(last_text)),
+                InputMessageContent::InputMessageText(text),
+            ) => join_texts(last_text.text(), text.text())
+                .and_then(|joined| with_message_text(last, joined)),
+            _ => None,
+        };
+
+        match (joined, digest.last_mut()) {
+            (Some(joined), Some(last)) => *last = joined,
+            _ => digest.push(message),
+        }
+    }
+}
+
+/// Texts joined with separator, None when they don't fit one message
+#[cfg(feature = "storage")]
+fn join_texts(first: &FormattedText, second: &FormattedText) -> Option<FormattedText> {
+    let mut editor = TextEditor::new(first);
+    editor.insert(editor.len(), SEPARATOR);
+    editor.insert_formatted(editor.len(), second);
+
+    (editor.len() <= MAX_TEXT_LENGTH).then(|| editor.build())
+}
+
+/// Collected message of digest
+#[cfg(feature = "templating")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestItem {
+    /// Source chat title
+    pub title: String,
+    /// Link to source message, empty when chat has no links
+    pub link: String,
+    /// The first non-empty line of output text
+    pub first_line: String,
+    /// Unix time of source message
+    pub date: u64,
+}
+
+#[cfg(feature = "templating")]
+impl DigestItem {
+    /// Item of source message with its pipeline output
+    pub async fn new(source: &Message, output: &[InputMessageContent]) -> Self {
+        let first_line = output
+            .first()
+            .and_then(find_output_message_text)
+            .and_then(|text| {
+                text.text()
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .map(str::to_string)
+            })
+            .unwrap_or_default();
+
+        DigestItem {
+            title: chat_title(source.chat_id()).await.unwrap_or_default(),
+            link: message_link(source.chat_id(), source.id())
+                .await
+                .unwrap_or_default(),
+            first_line,
+            date: source.date() as u64,
+        }
+    }
+}
+
+/// Collects pipeline output and posts it as periodic summary
+#[cfg(feature = "templating")]
+#[derive(Debug, Clone)]
+pub struct Digest {
+    /// Seconds after the first collected item when digest is posted
+    #[cfg(feature = "storage")]
+    pub interval: u64,
+    /// Number of items which posts digest at once
+    #[cfg(feature = "storage")]
+    pub max_items: usize,
+    template: String,
+    /// Parse rendered markdown/HTML into entities
+    parse_mode: Option<ParseMode>,
+    env: Environment<'static>,
+}
+
+#[cfg(feature = "templating")]
+impl From<DigestConf> for Digest {
+    fn from(conf: DigestConf) -> Self {
+        // Template is validated on config load
+        let env = environment(conf.parse_mode == Some(ParseMode::Html));
+        if let Err(e) = validate(&env, &conf.template) {
+            panic!("Invalid Digest template: {}", e);
+        }
+
+        Digest {
+            #[cfg(feature = "storage")]
+            interval: conf.interval,
+            #[cfg(feature = "storage")]
+            max_items: conf.max_items.max(1),
+            template: conf.template,
+            parse_mode: conf.parse_mode,
+            env,
+        }
+    }
+}
+
+#[cfg(feature = "templating")]
+impl Digest {
+    /// Render items into messages which fit Telegram limits. Nothing when there are no items or template fails
+    pub async fn render(&self, items: &[DigestItem]) -> Vec<InputMessageContent> {
+        if items.is_empty() {
+            return vec![];
+        }
+        let rendered = match self.env.render_str(
+            &self.template,
+            context!(items => items, count => items.len()),
+        ) {
+            Ok(rendered) => rendered.trim().to_string(),
+            Err(e) => {
+                eprintln!("Digest not rendered: {}", e);
+                return vec![];
+            }
+        };
+        if rendered.is_empty() {
+            return vec![];
+        }
+
+        let text = match &self.parse_mode {
+            Some(parse_mode) => parse_text(&rendered, parse_mode).await,
+            None => FormattedText::builder().text(rendered).build(),
+        };
+        split_output(
+            InputMessageContent::InputMessageText(InputMessageText::builder().text(text).build()),
+            &OverflowConf::Split,
+        )
+    }
+}
+
+#[cfg(all(test, any(feature = "storage", feature = "templating")))]
+mod tests {
+    #[cfg(feature = "storage")]
+    use super::append_to_digest;
+    #[cfg(feature = "templating")]
+    use super::DigestItem;
+    #[cfg(feature = "templating")]
+    use crate::config::DigestConf;
+    #[cfg(feature = "templating")]
+    use crate::processing::digest::Digest;
+    use crate::processing::helpers::find_output_message_text;
+    use crate::processing::overflow::MAX_TEXT_LENGTH;
+    #[cfg(feature = "templating")]
+    use crate::processing::test_helpers::entities_message_example;
+    use rust_tdlib::types::{FormattedText, InputMessageContent, InputMessageText, TextEntity};
+    #[cfg(feature = "storage")]
+    use rust_tdlib::types::{
+        InputFile, InputFileRemote, InputMessagePhoto, TextEntityType, TextEntityTypeBold,
+    };
+
+    fn text_message(text: &str, entities: Vec<TextEntity>) -> InputMessageContent {
+        InputMessageContent::InputMessageText(
+            InputMessageText::builder()
+                .text(
+                    FormattedText::builder()
+                        .text(text)
+                        .entities(entities)
+                        .build(),
+                )
+                .build(),
+        )
+    }
+
+    #[cfg(feature = "storage")]
+    fn bold(offset: i32, length: i32) -> TextEntity {
+        TextEntity::builder()
+            .offset(offset)
+            .length(length)
+            .type_(TextEntityType::Bold(TextEntityTypeBold::builder().build()))
+            .build()
+    }
+
+    #[cfg(feature = "storage")]
+    #[test]
+    fn test_join_texts() {
+        let mut digest = vec![text_message("First", vec![bold(0, 5)])];
+        append_to_digest(&mut digest, vec![text_message("Second", vec![bold(0, 6)])]);
+
+        assert_eq!(1, digest.len());
+        let text = find_output_message_text(&digest[0]).unwrap();
+        assert_eq!("First\n\nSecond", text.text());
+        assert_eq!(
+            vec![(0, 5), (7, 6)],
+            text.entities()
+                .iter()
+                .map(|e| (e.offset(), e.length()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "storage")]
+    #[test]
+    fn test_append_messages() {
+        let photo = InputMessageContent::InputMessagePhoto(
+            InputMessagePhoto::builder()
+                .photo(InputFile::Remote(
+                    InputFileRemote::builder().id("1").build(),
+                ))
+                .build(),
+        );
+        let mut digest = vec![];
+        append_to_digest(&mut digest, vec![photo, text_message("Caption", vec![])]);
+        // Too long text isn't joined
+        let long_text = "a".repeat(MAX_TEXT_LENGTH - 5);
+        append_to_digest(&mut digest, vec![text_message(&long_text, vec![])]);
+
+        assert_eq!(3, digest.len());
+        assert!(matches!(
+            digest[0],
+            InputMessageContent::InputMessagePhoto(_)
+        ));
+        assert_eq!(
+            long_text,
+            find_output_message_text(&digest[2])
+                .unwrap()
+                .text()
+                .as_str()
+        );
+    }
+
+    #[cfg(feature = "templating")]
+    fn item(title: &str, first_line: &str) -> DigestItem {
+        DigestItem {
+            title: title.to_string(),
+            link: String::new(),
+            first_line: first_line.to_string(),
+            date: 0,
+        }
+    }
+
+    #[cfg(feature = "templating")]
+    #[tokio::test]
+    async fn test_item() {
+        let source = entities_message_example("Source", vec![]);
+        let output = vec![text_message("\n  Headline \nBody", vec![])];
+
+        let item = DigestItem::new(source.message(), &output).await;
+        assert_eq!("Headline", item.first_line);
+        // No client in tests, so there is no title and link
+        assert_eq!("", item.title);
+        assert_eq!("", item.link);
+    }
+
+    #[cfg(feature = "templating")]
+    #[tokio::test]
+    async fn test_render() {
+        let digest = Digest::from(serde_json::from_str::<DigestConf>("{}").unwrap());
+        let mut first = item("News", "First");
+        first.link = "https://t.me/news/1".to_string();
+
+        let messages = digest.render(&[first, item("Blog", "Second")]).await;
+        assert_eq!(1, messages.len());
+        assert_eq!(
+            "News: First\nhttps://t.me/news/1\n\nBlog: Second",
+            find_output_message_text(&messages[0]).unwrap().text()
+        );
+    }
+
+    #[cfg(feature = "templating")]
+    #[tokio::test]
+    async fn test_render_split() {
+        let digest = Digest::from(
+            serde_json::from_str::<DigestConf>(
+                r#"{"template": "{{ count }} posts\n{% for item in items %}{{ item.first_line }}\n{% endfor %}"}"#,
+            )
+            .unwrap(),
+        );
+        let items: Vec<DigestItem> = (0..10).map(|_| item("", &"a".repeat(1000))).collect();
+
+        let messages = digest.render(&items).await;
+        assert_eq!(3, messages.len());
+        assert!(find_output_message_text(&messages[0])
+            .unwrap()
+            .text()
+            .starts_with("10 posts\n"));
+        assert!(messages
+            .iter()
+            .all(|m| find_output_message_text(m).unwrap().text().len() <= MAX_TEXT_LENGTH));
+        assert!(digest.render(&[]).await.is_empty());
+    }
+}
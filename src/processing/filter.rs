@@ -1,9 +1,18 @@
-use crate::config::FilterConf;
+use crate::config::{FilterConf, SendersConf};
 use crate::processing::data::DataHub;
+#[cfg(feature = "openai")]
+use crate::processing::filters::OpenAi;
+#[cfg(feature = "schedule")]
+use crate::processing::filters::Schedule;
+#[cfg(feature = "similar_image")]
+use crate::processing::filters::SimilarImage;
 use crate::processing::filters::{
-    Counter, Duration, FileSize, Incoming, MessageType, OpenAi, Regexp, TextLength, Unique,
-    WordList, WordListType,
+    Counter, Duration, EntityValue, Extension, FileName, FileSize, Forward, ForwardAge,
+    ForwardedFrom, HasEntity, Incoming, LinkDomain, MaxLinks, MessageType, Mime, Regexp, Sender,
+    SenderListType, TextLength, Variable, WordList, WordListType,
 };
+#[cfg(feature = "storage")]
+use crate::processing::filters::{NearDuplicate, RateLimit, Unique};
 
 /// Filters return Ok/Err instead of true/false
 pub type FilterResult = Result<(), ()>;
@@ -17,9 +26,14 @@ pub trait Filter {
 pub enum FilterType {
     /// Filter only incoming messages
     Incoming(Incoming),
-    /// Filter by counter. Counter will be decremented on message receive. When counter becomes 0
-    /// filter will return Ok otherwise Err
+    /// Filter by counter: every nth message, first n per period or random sample
     Counter(Counter),
+    /// Filter by time of message: windows, cron expressions and holidays
+    #[cfg(feature = "schedule")]
+    Schedule(Schedule),
+    /// Limit messages with token bucket, reject or defer messages over the limit
+    #[cfg(feature = "storage")]
+    RateLimit(RateLimit),
     /// Only text messages
     Text(MessageType),
     /// Only video messages
@@ -30,10 +44,34 @@ pub enum FilterType {
     Animation(MessageType),
     /// Only document messages
     Document(MessageType),
-    /// Only file messages, includes photo, video, animation and document messages
+    /// Only audio messages
+    Audio(MessageType),
+    /// Only voice note messages
+    Voice(MessageType),
+    /// Only video note (round video) messages
+    VideoNote(MessageType),
+    /// Only sticker messages
+    Sticker(MessageType),
+    /// Only poll messages
+    Poll(MessageType),
+    /// Only location messages
+    Location(MessageType),
+    /// Only venue messages
+    Venue(MessageType),
+    /// Only contact messages
+    Contact(MessageType),
+    /// Only dice messages
+    Dice(MessageType),
+    /// Only file messages, includes photo, video, animation, document, audio, voice note and video note messages
     AnyFile(MessageType),
     /// Filter by file size
     FileSize(FileSize),
+    /// Filter by MIME type of file
+    Mime(Mime),
+    /// Filter by file name extension
+    Extension(Extension),
+    /// Filter by file name where regular expression matches
+    FileName(FileName),
     /// Filter by video/animation duration
     Duration(Duration),
     /// Filter by text/caption length
@@ -44,9 +82,45 @@ pub enum FilterType {
     WhiteList(WordList),
     /// This filter rejects when message matches any word in wordlist
     BlackList(WordList),
+    /// Only forwarded messages
+    Forwarded(Forward),
+    /// Only not forwarded messages
+    NotForwarded(Forward),
+    /// Only messages forwarded from users who hide their accounts
+    HiddenForward(Forward),
+    /// Only messages forwarded from provided chats/users
+    ForwardedFrom(ForwardedFrom),
+    /// Filter by age of forwarded message's original date
+    ForwardAge(ForwardAge),
+    /// Filter by text/caption entity types
+    HasEntity(HasEntity),
+    /// Filter by count of links in text/caption
+    MaxLinks(MaxLinks),
+    /// Filter by domains of links in text/caption
+    LinkDomain(LinkDomain),
+    /// Only messages with any of provided hashtags
+    Hashtag(EntityValue),
+    /// Only messages with any of provided cashtags
+    Cashtag(EntityValue),
+    /// Only messages with any of provided mentions
+    Mention(EntityValue),
+    /// Only messages with any of provided bot commands
+    BotCommand(EntityValue),
+    /// This filter passes when message sender matches any rule
+    AllowSenders(Sender),
+    /// This filter rejects when message sender matches any rule
+    DenySenders(Sender),
+    /// Filter by variable set by previous filters
+    Variable(Variable),
     /// Filter duplicates, pass unique messages
     #[cfg(feature = "storage")]
     Unique(Unique),
+    /// Filter texts similar to recently seen ones
+    #[cfg(feature = "storage")]
+    NearDuplicate(NearDuplicate),
+    /// Filter photos similar to recently seen ones
+    #[cfg(feature = "similar_image")]
+    SimilarImage(SimilarImage),
     /// Filter by context using LLM
     #[cfg(feature = "openai")]
     OpenAi(OpenAi),
@@ -57,20 +131,55 @@ impl Filter for FilterType {
         match self {
             Self::Incoming(f) => f.filter(data).await,
             Self::Counter(f) => f.filter(data).await,
+            #[cfg(feature = "schedule")]
+            Self::Schedule(f) => f.filter(data).await,
+            #[cfg(feature = "storage")]
+            Self::RateLimit(f) => f.filter(data).await,
             Self::Text(f) => f.filter(data).await,
             Self::Video(f) => f.filter(data).await,
             Self::Photo(f) => f.filter(data).await,
             Self::Document(f) => f.filter(data).await,
             Self::Animation(f) => f.filter(data).await,
+            Self::Audio(f) => f.filter(data).await,
+            Self::Voice(f) => f.filter(data).await,
+            Self::VideoNote(f) => f.filter(data).await,
+            Self::Sticker(f) => f.filter(data).await,
+            Self::Poll(f) => f.filter(data).await,
+            Self::Location(f) => f.filter(data).await,
+            Self::Venue(f) => f.filter(data).await,
+            Self::Contact(f) => f.filter(data).await,
+            Self::Dice(f) => f.filter(data).await,
             Self::AnyFile(f) => f.filter(data).await,
             Self::FileSize(f) => f.filter(data).await,
+            Self::Mime(f) => f.filter(data).await,
+            Self::Extension(f) => f.filter(data).await,
+            Self::FileName(f) => f.filter(data).await,
             Self::Duration(f) => f.filter(data).await,
             Self::TextLength(f) => f.filter(data).await,
             Self::Regexp(f) => f.filter(data).await,
             Self::WhiteList(f) => f.filter(data).await,
             Self::BlackList(f) => f.filter(data).await,
+            Self::Forwarded(f) => f.filter(data).await,
+            Self::NotForwarded(f) => f.filter(data).await,
+            Self::HiddenForward(f) => f.filter(data).await,
+            Self::ForwardedFrom(f) => f.filter(data).await,
+            Self::ForwardAge(f) => f.filter(data).await,
+            Self::HasEntity(f) => f.filter(data).await,
+            Self::MaxLinks(f) => f.filter(data).await,
+            Self::LinkDomain(f) => f.filter(data).await,
+            Self::Hashtag(f) => f.filter(data).await,
+            Self::Cashtag(f) => f.filter(data).await,
+            Self::Mention(f) => f.filter(data).await,
+            Self::BotCommand(f) => f.filter(data).await,
+            Self::AllowSenders(f) => f.filter(data).await,
+            Self::DenySenders(f) => f.filter(data).await,
+            Self::Variable(f) => f.filter(data).await,
             #[cfg(feature = "storage")]
             Self::Unique(f) => f.filter(data).await,
+            #[cfg(feature = "storage")]
+            Self::NearDuplicate(f) => f.filter(data).await,
+            #[cfg(feature = "similar_image")]
+            Self::SimilarImage(f) => f.filter(data).await,
             #[cfg(feature = "openai")]
             Self::OpenAi(f) => f.filter(data).await,
         }
@@ -82,14 +191,78 @@ impl From<FilterConf> for FilterType {
         match filter_conf {
             FilterConf::Incoming => FilterType::Incoming(Incoming),
 
-            FilterConf::Counter { count } => {
-                FilterType::Counter(Counter::builder().count(count).build())
-            }
+            FilterConf::Counter {
+                count,
+                mode,
+                per,
+                period,
+                probability,
+                seed,
+                namespace,
+            } => FilterType::Counter(
+                Counter::builder()
+                    .count(count)
+                    .mode(mode)
+                    .per(per)
+                    .period(period)
+                    .probability(probability)
+                    .seed(seed)
+                    .namespace(namespace)
+                    .build(),
+            ),
+
+            #[cfg(feature = "schedule")]
+            FilterConf::Schedule {
+                tz,
+                allow,
+                deny,
+                holidays,
+                time,
+            } => FilterType::Schedule(
+                Schedule::builder()
+                    .tz(tz)
+                    .allow(allow)
+                    .deny(deny)
+                    .holidays(holidays)
+                    .time(time)
+                    .build(),
+            ),
+
+            #[cfg(feature = "storage")]
+            FilterConf::RateLimit {
+                max,
+                per_seconds,
+                key,
+                on_exceed,
+                max_delay,
+                namespace,
+            } => FilterType::RateLimit(
+                RateLimit::builder()
+                    .max(max)
+                    .per_seconds(per_seconds)
+                    .key(key)
+                    .on_exceed(on_exceed)
+                    .max_delay(max_delay)
+                    .namespace(namespace)
+                    .build(),
+            ),
 
             FilterConf::FileSize { size, op } => {
                 FilterType::FileSize(FileSize::builder().size(size).operator(op).build())
             }
 
+            FilterConf::Mime { allow, deny } => {
+                FilterType::Mime(Mime::builder().allow(allow).deny(deny).build())
+            }
+
+            FilterConf::Extension { allow, deny } => {
+                FilterType::Extension(Extension::builder().allow(allow).deny(deny).build())
+            }
+
+            FilterConf::FileName { exp } => {
+                FilterType::FileName(FileName::builder().expression(exp).build())
+            }
+
             FilterConf::Duration { duration, op } => {
                 FilterType::Duration(Duration::builder().duration(duration).operator(op).build())
             }
@@ -116,6 +289,75 @@ impl From<FilterConf> for FilterType {
                     .build(),
             ),
 
+            FilterConf::Forwarded => FilterType::Forwarded(Forward::Forwarded),
+
+            FilterConf::NotForwarded => FilterType::NotForwarded(Forward::NotForwarded),
+
+            FilterConf::HiddenForward => FilterType::HiddenForward(Forward::HiddenUser),
+
+            FilterConf::ForwardedFrom { chats, usernames } => FilterType::ForwardedFrom(
+                ForwardedFrom::builder()
+                    .chats(chats)
+                    .usernames(usernames)
+                    .build(),
+            ),
+
+            FilterConf::ForwardAge { age, op } => {
+                FilterType::ForwardAge(ForwardAge::builder().age(age).operator(op).build())
+            }
+
+            FilterConf::HasEntity { types } => {
+                FilterType::HasEntity(HasEntity::builder().types(types).build())
+            }
+
+            FilterConf::MaxLinks { count } => {
+                FilterType::MaxLinks(MaxLinks::builder().count(count).build())
+            }
+
+            FilterConf::LinkDomain { allow, deny } => {
+                FilterType::LinkDomain(LinkDomain::builder().allow(allow).deny(deny).build())
+            }
+
+            FilterConf::Hashtag { any } => FilterType::Hashtag(
+                EntityValue::builder()
+                    .entity_type("Hashtag")
+                    .values(any)
+                    .build(),
+            ),
+
+            FilterConf::Cashtag { any } => FilterType::Cashtag(
+                EntityValue::builder()
+                    .entity_type("Cashtag")
+                    .values(any)
+                    .build(),
+            ),
+
+            FilterConf::Mention { any } => FilterType::Mention(
+                EntityValue::builder()
+                    .entity_type("Mention")
+                    .values(any)
+                    .build(),
+            ),
+
+            FilterConf::BotCommand { any } => FilterType::BotCommand(
+                EntityValue::builder()
+                    .entity_type("BotCommand")
+                    .values(any)
+                    .build(),
+            ),
+
+            FilterConf::AllowSenders(senders) => {
+                FilterType::AllowSenders(sender_filter(senders, SenderListType::Allow))
+            }
+
+            FilterConf::DenySenders(senders) => {
+                FilterType::DenySenders(sender_filter(senders, SenderListType::Deny))
+            }
+
+            FilterConf::Variable { name, value } => {
+                FilterType::Variable(Variable::builder().name(name).value(value).build())
+            }
+
             FilterConf::Text => FilterType::Text(MessageType::Text),
 
             FilterConf::Video => FilterType::Video(MessageType::Video),
@@ -126,10 +368,68 @@ impl From<FilterConf> for FilterType {
 
             FilterConf::Animation => FilterType::Animation(MessageType::Animation),
 
+            FilterConf::Audio => FilterType::Audio(MessageType::Audio),
+
+            FilterConf::Voice => FilterType::Voice(MessageType::Voice),
+
+            FilterConf::VideoNote => FilterType::VideoNote(MessageType::VideoNote),
+
+            FilterConf::Sticker => FilterType::Sticker(MessageType::Sticker),
+
+            FilterConf::Poll => FilterType::Poll(MessageType::Poll),
+
+            FilterConf::Location => FilterType::Location(MessageType::Location),
+
+            FilterConf::Venue => FilterType::Venue(MessageType::Venue),
+
+            FilterConf::Contact => FilterType::Contact(MessageType::Contact),
+
+            FilterConf::Dice => FilterType::Dice(MessageType::Dice),
+
             FilterConf::AnyFile => FilterType::AnyFile(MessageType::AnyFile),
 
             #[cfg(feature = "storage")]
-            FilterConf::Unique => FilterType::Unique(Unique),
+            FilterConf::Unique {
+                namespace,
+                ttl,
+                keys,
+                per_destination,
+            } => FilterType::Unique(
+                Unique::builder()
+                    .namespace(namespace)
+                    .ttl(ttl)
+                    .keys(keys)
+                    .per_destination(per_destination)
+                    .build(),
+            ),
+
+            #[cfg(feature = "storage")]
+            FilterConf::NearDuplicate {
+                namespace,
+                distance,
+                window,
+            } => FilterType::NearDuplicate(
+                NearDuplicate::builder()
+                    .namespace(namespace)
+                    .distance(distance)
+                    .window(window)
+                    .build(),
+            ),
+
+            #[cfg(feature = "similar_image")]
+            FilterConf::SimilarImage {
+                namespace,
+                distance,
+                window,
+                per_destination,
+            } => FilterType::SimilarImage(
+                SimilarImage::builder()
+                    .namespace(namespace)
+                    .distance(distance)
+                    .window(window)
+                    .per_destination(per_destination)
+                    .build(),
+            ),
 
             #[cfg(feature = "openai")]
             FilterConf::OpenAi {
@@ -146,3 +446,15 @@ impl From<FilterConf> for FilterType {
         }
     }
 }
+
+fn sender_filter(senders: SendersConf, list_type: SenderListType) -> Sender {
+    Sender::builder()
+        .list_type(list_type)
+        .users(senders.users)
+        .usernames(senders.usernames)
+        .chats(senders.chats)
+        .signatures(senders.signatures)
+        .bots(senders.bots)
+        .admins(senders.admins)
+        .build()
+}
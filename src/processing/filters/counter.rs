@@ -1,13 +1,53 @@
+use crate::config::{CounterKey, CounterMode};
 use crate::processing::data::DataHub;
 use crate::processing::filter::{Filter, FilterResult};
-use std::sync::atomic::{AtomicU8, Ordering};
+use crate::processing::helpers::find_sender_id;
+#[cfg(feature = "storage")]
+use crate::storage::{blocking, now, storage, StorageResult};
+#[cfg(feature = "storage")]
+use colored::Colorize;
+#[cfg(not(feature = "storage"))]
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+#[cfg(not(feature = "storage"))]
+use std::sync::Mutex;
 
-/// Filter by counter
-#[derive(Debug, Default, Clone)]
+/// Filter by counter. Counts are kept in storage per route, source chat or sender.
+/// Without storage they are kept in memory until restart
+#[derive(Debug, Clone)]
 pub struct Counter {
-    atomic: Arc<AtomicU8>,
-    count: u8,
+    mode: CounterMode,
+    /// Skipped messages (skip), N (every_nth and first_n_per_period)
+    count: u32,
+    /// Seconds of first_n_per_period window
+    period: u64,
+    /// Chance of random_sample to pass
+    probability: f64,
+    /// State of random_sample generator
+    random: Arc<AtomicU64>,
+    per: CounterKey,
+    /// Namespace of counts, pipeline name or route when not provided
+    namespace: Option<String>,
+    /// Window and count by key, when there is no storage
+    #[cfg(not(feature = "storage"))]
+    counts: Arc<Mutex<HashMap<String, (u64, u64)>>>,
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Counter {
+            mode: CounterMode::default(),
+            count: 0,
+            period: 60 * 60,
+            probability: 1.0,
+            random: Arc::new(AtomicU64::new(now())),
+            per: CounterKey::default(),
+            namespace: None,
+            #[cfg(not(feature = "storage"))]
+            counts: Arc::default(),
+        }
+    }
 }
 
 impl Counter {
@@ -15,17 +55,147 @@ impl Counter {
         let inner = Counter::default();
         CounterBuilder { inner }
     }
+
+    #[cfg(feature = "storage")]
+    fn namespace(&self, data: &DataHub) -> String {
+        let namespace = self
+            .namespace
+            .clone()
+            .unwrap_or_else(|| data.default_namespace());
+
+        format!("counter/{}", namespace)
+    }
+
+    fn key(&self, data: &DataHub) -> String {
+        let message = data.input.message();
+
+        match self.per {
+            CounterKey::Route => format!(
+                "route:{}:{}",
+                message.chat_id(),
+                data.destination.unwrap_or_default()
+            ),
+            CounterKey::Source => format!("source:{}", message.chat_id()),
+            CounterKey::Sender => format!("sender:{}", find_sender_id(message)),
+        }
+    }
+
+    /// Window of first_n_per_period, its count starts over in the next window
+    fn window(&self) -> Option<u64> {
+        match self.mode {
+            CounterMode::FirstNPerPeriod => Some(now() / self.period.max(1)),
+            _ => None,
+        }
+    }
+
+    /// Increment count kept in memory, returns new count
+    #[cfg(not(feature = "storage"))]
+    async fn increment(&self, data: &DataHub) -> Option<u64> {
+        let window = self.window().unwrap_or_default();
+        let mut counts = self.counts.lock().unwrap();
+        let (counted_window, count) = counts.entry(self.key(data)).or_default();
+
+        if *counted_window != window {
+            *counted_window = window;
+            *count = 0;
+        }
+        *count += 1;
+
+        Some(*count)
+    }
+
+    /// Increment stored count, returns new count
+    #[cfg(feature = "storage")]
+    async fn increment(&self, data: &DataHub) -> Option<u64> {
+        let counter = self.clone();
+        let namespace = self.namespace(data);
+        let key = self.key(data);
+        let related = data.related_ids();
+
+        blocking(move || counter.increment_stored(&namespace, &key, &related))
+            .await
+            .map_err(|e| println!("{} {}", "Failed to count message :".red(), e))
+            .ok()
+    }
+
+    /// Increment count of key in storage. Count of first_n_per_period is
+    /// stored with its window ("window:count") and starts over in the next window
+    #[cfg(feature = "storage")]
+    fn increment_stored(&self, namespace: &str, key: &str, related: &[i64]) -> StorageResult<u64> {
+        let storage = storage();
+        let window = self.window();
+        let ttl = window.map(|_| self.period);
+
+        loop {
+            let current = storage.get(namespace, key)?;
+            let count = match (window, current.as_deref()) {
+                (Some(window), Some(current)) => current
+                    .split_once(':')
+                    .filter(|(stored_window, _)| *stored_window == window.to_string())
+                    .and_then(|(_, count)| count.parse().ok())
+                    .unwrap_or(0),
+                (None, Some(current)) => current.parse().unwrap_or(0),
+                (_, None) => 0,
+            } + 1;
+            let value = match window {
+                Some(window) => format!("{}:{}", window, count),
+                None => count.to_string(),
+            };
+
+            // Changed concurrently, try again
+            if storage.compare_and_set(namespace, key, current.as_deref(), &value, ttl, related)? {
+                return Ok(count);
+            }
+        }
+    }
+
+    /// Next number of splitmix64 sequence in [0, 1)
+    fn next_random(&self) -> f64 {
+        let state = self
+            .random
+            .fetch_add(0x9e3779b97f4a7c15, Ordering::Relaxed)
+            .wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Unix time in seconds
+#[cfg(not(feature = "storage"))]
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
 }
 
 impl Filter for Counter {
-    async fn filter(&self, _: &DataHub) -> FilterResult {
-        match self.atomic.fetch_sub(1, Ordering::Relaxed) {
-            0 => {
-                self.atomic.store(self.count, Ordering::Relaxed);
-                Ok(())
-            }
-            _ => Err(()),
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let count = self.count as u64;
+
+        let number = match self.mode {
+            CounterMode::RandomSample => None,
+            _ => self.increment(data).await,
+        };
+        // Number of message is available to the next filters and pipes
+        if let Some(number) = number {
+            data.variables.set("counter", &number.to_string());
         }
+
+        let passes = match (&self.mode, number) {
+            (CounterMode::RandomSample, _) => self.next_random() < self.probability,
+            // Storage errors don't stop messages
+            (_, None) => true,
+            (CounterMode::Skip, Some(n)) => n % (count + 1) == 0,
+            (CounterMode::EveryNth, Some(n)) => n % count.max(1) == 0,
+            (CounterMode::FirstNPerPeriod, Some(n)) => n <= count,
+        };
+
+        passes.then_some(()).ok_or(())
     }
 }
 
@@ -34,9 +204,40 @@ pub struct CounterBuilder {
 }
 
 impl CounterBuilder {
-    pub fn count(&mut self, count: u8) -> &mut CounterBuilder {
+    pub fn mode(&mut self, mode: CounterMode) -> &mut CounterBuilder {
+        self.inner.mode = mode;
+        self
+    }
+
+    pub fn count(&mut self, count: u32) -> &mut CounterBuilder {
         self.inner.count = count;
-        self.inner.atomic = Arc::new(AtomicU8::from(count));
+        self
+    }
+
+    pub fn period(&mut self, period: u64) -> &mut CounterBuilder {
+        self.inner.period = period;
+        self
+    }
+
+    pub fn probability(&mut self, probability: f64) -> &mut CounterBuilder {
+        self.inner.probability = probability;
+        self
+    }
+
+    pub fn seed(&mut self, seed: Option<u64>) -> &mut CounterBuilder {
+        if let Some(seed) = seed {
+            self.inner.random = Arc::new(AtomicU64::new(seed));
+        }
+        self
+    }
+
+    pub fn per(&mut self, per: CounterKey) -> &mut CounterBuilder {
+        self.inner.per = per;
+        self
+    }
+
+    pub fn namespace(&mut self, namespace: Option<String>) -> &mut CounterBuilder {
+        self.inner.namespace = namespace;
         self
     }
 
@@ -47,11 +248,31 @@ impl CounterBuilder {
 
 #[cfg(test)]
 mod tests {
-    use crate::config::FilterConf;
+    use crate::config::{CounterKey, CounterMode, FilterConf};
     use crate::processing::data::DataHub;
     use crate::processing::filter::{Filter, FilterType};
     use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
 
+    fn counter_conf(namespace: &str, mode: CounterMode, count: u32) -> FilterConf {
+        FilterConf::Counter {
+            count,
+            mode,
+            per: CounterKey::Route,
+            period: 3600,
+            probability: 1.0,
+            seed: None,
+            namespace: Some(namespace.to_string()),
+        }
+    }
+
+    async fn results(filter: &FilterType, data: &DataHub, times: usize) -> Vec<bool> {
+        let mut results = vec![];
+        for _ in 0..times {
+            results.push(filter.filter(data).await.is_ok());
+        }
+        results
+    }
+
     #[tokio::test]
     async fn test_counter() {
         let data = DataHub::new(message_example(
@@ -61,10 +282,78 @@ mod tests {
         ));
 
         // This will pass only third message, first two must be ignored
-        let filter = FilterType::from(FilterConf::Counter { count: 2 });
+        let filter = FilterType::from(counter_conf("test_skip", CounterMode::Skip, 2));
 
         assert_eq!(Err(()), filter.filter(&data).await);
         assert_eq!(Err(()), filter.filter(&data).await);
         assert_eq!(Ok(()), filter.filter(&data).await);
+        assert_eq!(Some("3".to_string()), data.variables.get("counter"));
+    }
+
+    #[tokio::test]
+    async fn test_counter_every_nth_per_route() {
+        let mut data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(counter_conf("test_every_nth", CounterMode::EveryNth, 2));
+
+        data.destination = Some(1);
+        assert_eq!(
+            vec![false, true, false, true],
+            results(&filter, &data, 4).await
+        );
+
+        // Another route has own count
+        data.destination = Some(2);
+        assert_eq!(vec![false, true], results(&filter, &data, 2).await);
+    }
+
+    #[tokio::test]
+    async fn test_counter_first_n_per_period() {
+        let data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(counter_conf(
+            "test_first_n",
+            CounterMode::FirstNPerPeriod,
+            3,
+        ));
+
+        assert_eq!(
+            vec![true, true, true, false, false],
+            results(&filter, &data, 5).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_counter_random_sample() {
+        let data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let sample = |seed| {
+            FilterType::from(FilterConf::Counter {
+                count: 0,
+                mode: CounterMode::RandomSample,
+                per: CounterKey::Route,
+                period: 3600,
+                probability: 0.3,
+                seed: Some(seed),
+                namespace: None,
+            })
+        };
+
+        // The same seed makes the same sample
+        let first = results(&sample(42), &data, 100).await;
+        assert_eq!(first, results(&sample(42), &data, 100).await);
+        assert_ne!(first, results(&sample(7), &data, 100).await);
+
+        let passed = first.iter().filter(|passed| **passed).count();
+        assert!((15..=45).contains(&passed));
     }
 }
@@ -0,0 +1,140 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::{
+    entity_text, entity_type_name, find_input_message_formatted_text,
+};
+use rust_tdlib::types::TextEntityType;
+
+/// Filter messages which have any of provided hashtags, cashtags, mentions or bot commands
+#[derive(Debug, Default, Clone)]
+pub struct EntityValue {
+    /// Entity type name (Hashtag, Cashtag, Mention, BotCommand)
+    entity_type: String,
+    /// Normalized values
+    values: Vec<String>,
+}
+
+impl EntityValue {
+    pub fn builder() -> EntityValueBuilder {
+        let inner = EntityValue::default();
+        EntityValueBuilder { inner }
+    }
+}
+
+/// Lowercase value without leading #, $, @ and / and without bot username of commands (/start@bot)
+fn normalize(value: &str) -> String {
+    let value = value.trim().trim_start_matches(['#', '$', '@', '/']);
+    let value = value.split('@').next().unwrap_or(value);
+
+    value.to_lowercase()
+}
+
+impl Filter for EntityValue {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let formatted_text = find_input_message_formatted_text(data.input.message()).ok_or(())?;
+
+        formatted_text
+            .entities()
+            .iter()
+            .filter_map(|entity| match entity.type_() {
+                // Mentions of users without username are matched by user id
+                TextEntityType::MentionName(m) if self.entity_type == "Mention" => {
+                    Some(m.user_id().to_string())
+                }
+                entity_type if entity_type_name(entity_type) == self.entity_type => {
+                    Some(normalize(&entity_text(formatted_text.text(), entity)))
+                }
+                _ => None,
+            })
+            .any(|value| self.values.contains(&value))
+            .then_some(())
+            .ok_or(())
+    }
+}
+
+pub struct EntityValueBuilder {
+    inner: EntityValue,
+}
+
+impl EntityValueBuilder {
+    pub fn entity_type(&mut self, entity_type: &str) -> &mut EntityValueBuilder {
+        self.inner.entity_type = entity_type.to_string();
+        self
+    }
+
+    pub fn values(&mut self, values: Vec<String>) -> &mut EntityValueBuilder {
+        self.inner.values = values.iter().map(|value| normalize(value)).collect();
+        self
+    }
+
+    pub fn build(&self) -> EntityValue {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{entities_message_example, entity_example};
+    use rust_tdlib::types::{
+        TextEntityType, TextEntityTypeBotCommand, TextEntityTypeHashtag, TextEntityTypeMention,
+    };
+
+    #[tokio::test]
+    async fn test_hashtag() {
+        // Hashtag after emoji, offsets are in UTF-16 code units
+        let data = DataHub::new(entities_message_example(
+            "🔥 #Breaking news",
+            vec![entity_example(
+                3,
+                9,
+                TextEntityType::Hashtag(TextEntityTypeHashtag::builder().build()),
+            )],
+        ));
+
+        let success_filter = FilterType::from(FilterConf::Hashtag {
+            any: vec!["breaking".to_string()],
+        });
+        let fail_filter = FilterType::from(FilterConf::Hashtag {
+            any: vec!["#sport".to_string()],
+        });
+
+        assert_eq!(Ok(()), success_filter.filter(&data).await);
+        assert_eq!(Err(()), fail_filter.filter(&data).await);
+    }
+
+    #[tokio::test]
+    async fn test_mention_and_bot_command() {
+        let data = DataHub::new(entities_message_example(
+            "/start@some_bot by @Author",
+            vec![
+                entity_example(
+                    0,
+                    15,
+                    TextEntityType::BotCommand(TextEntityTypeBotCommand::builder().build()),
+                ),
+                entity_example(
+                    19,
+                    7,
+                    TextEntityType::Mention(TextEntityTypeMention::builder().build()),
+                ),
+            ],
+        ));
+
+        let mention_filter = FilterType::from(FilterConf::Mention {
+            any: vec!["@author".to_string()],
+        });
+        let command_filter = FilterType::from(FilterConf::BotCommand {
+            any: vec!["/start".to_string()],
+        });
+        let hashtag_filter = FilterType::from(FilterConf::Hashtag {
+            any: vec!["author".to_string()],
+        });
+
+        assert_eq!(Ok(()), mention_filter.filter(&data).await);
+        assert_eq!(Ok(()), command_filter.filter(&data).await);
+        assert_eq!(Err(()), hashtag_filter.filter(&data).await);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::find_input_message_file_name;
+
+/// Filter by file name extension of document, audio, video or animation, case-insensitive
+#[derive(Debug, Default, Clone)]
+pub struct Extension {
+    /// When not empty, extension must be one of these
+    allow: Vec<String>,
+    /// Extension must not be any of these
+    deny: Vec<String>,
+}
+
+impl Extension {
+    pub fn builder() -> ExtensionBuilder {
+        let inner = Extension::default();
+        ExtensionBuilder { inner }
+    }
+}
+
+/// Lowercase extension without leading dot
+fn normalize(extension: &str) -> String {
+    extension.trim_start_matches('.').to_lowercase()
+}
+
+impl Filter for Extension {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let extension = find_input_message_file_name(data.input.message())
+            .and_then(|file_name| file_name.rsplit_once('.'))
+            .map(|(_, extension)| normalize(extension));
+        let matches =
+            |extensions: &Vec<String>| extension.as_ref().is_some_and(|e| extensions.contains(e));
+
+        let allowed = self.allow.is_empty() || matches(&self.allow);
+        let denied = matches(&self.deny);
+
+        (allowed && !denied).then_some(()).ok_or(())
+    }
+}
+
+pub struct ExtensionBuilder {
+    inner: Extension,
+}
+
+impl ExtensionBuilder {
+    pub fn allow(&mut self, allow: Vec<String>) -> &mut ExtensionBuilder {
+        self.inner.allow = allow.iter().map(|e| normalize(e)).collect();
+        self
+    }
+
+    pub fn deny(&mut self, deny: Vec<String>) -> &mut ExtensionBuilder {
+        self.inner.deny = deny.iter().map(|e| normalize(e)).collect();
+        self
+    }
+
+    pub fn build(&self) -> Extension {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
+
+    #[tokio::test]
+    async fn test_extension() {
+        let pdf = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::NamedDocument("report.final.pdf", "application/pdf"),
+            false,
+        ));
+        let exe = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::NamedDocument("setup.EXE", "application/octet-stream"),
+            false,
+        ));
+        let no_extension = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::NamedDocument("README", "text/plain"),
+            false,
+        ));
+
+        let deny_filter = FilterType::from(FilterConf::Extension {
+            allow: vec![],
+            deny: vec!["apk".to_string(), ".exe".to_string()],
+        });
+        let allow_filter = FilterType::from(FilterConf::Extension {
+            allow: vec!["pdf".to_string()],
+            deny: vec![],
+        });
+
+        assert_eq!(Ok(()), deny_filter.filter(&pdf).await);
+        assert_eq!(Err(()), deny_filter.filter(&exe).await);
+        assert_eq!(Ok(()), deny_filter.filter(&no_extension).await);
+
+        assert_eq!(Ok(()), allow_filter.filter(&pdf).await);
+        assert_eq!(Err(()), allow_filter.filter(&exe).await);
+        assert_eq!(Err(()), allow_filter.filter(&no_extension).await);
+    }
+}
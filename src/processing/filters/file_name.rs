@@ -0,0 +1,78 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::find_input_message_file_name;
+use regex::Regex;
+
+/// Filter by file name of document, audio, video or animation where regular expression matches
+#[derive(Debug, Default, Clone)]
+pub struct FileName {
+    exp: String,
+    pattern: Option<Regex>,
+}
+
+impl FileName {
+    pub fn builder() -> FileNameBuilder {
+        let inner = FileName::default();
+        FileNameBuilder { inner }
+    }
+}
+
+impl Filter for FileName {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let file_name = find_input_message_file_name(data.input.message()).ok_or(())?;
+
+        let pattern = self.pattern.as_ref().ok_or(())?;
+        pattern.is_match(file_name).then_some(()).ok_or(())
+    }
+}
+
+pub struct FileNameBuilder {
+    inner: FileName,
+}
+
+impl FileNameBuilder {
+    pub fn expression(&mut self, exp: String) -> &mut FileNameBuilder {
+        self.inner.exp = exp;
+        self.inner.pattern = Some(Regex::new(&self.inner.exp).unwrap());
+        self
+    }
+
+    pub fn build(&self) -> FileName {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
+
+    #[tokio::test]
+    async fn test_file_name() {
+        let invoice = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::NamedDocument("invoice_2024_01.pdf", "application/pdf"),
+            false,
+        ));
+        let photo = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::NamedDocument("photo.jpg", "image/jpeg"),
+            false,
+        ));
+        let text = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(Some("invoice_2024_01.pdf".to_string())),
+            false,
+        ));
+
+        let filter = FilterType::from(FilterConf::FileName {
+            exp: r"^invoice_\d{4}_\d{2}\.pdf$".to_string(),
+        });
+
+        assert_eq!(Ok(()), filter.filter(&invoice).await);
+        assert_eq!(Err(()), filter.filter(&photo).await);
+        assert_eq!(Err(()), filter.filter(&text).await);
+    }
+}
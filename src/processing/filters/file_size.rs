@@ -20,7 +20,7 @@ impl FileSize {
 
 impl Filter for FileSize {
     async fn filter(&self, data: &DataHub) -> FilterResult {
-        let file = find_input_message_file(data.input.message());
+        let file = find_input_message_file(data.input.message(), &data.photo_size);
 
         match cmp(
             &self.op,
@@ -55,7 +55,7 @@ impl FileSizeBuilder {
 
 #[cfg(test)]
 mod tests {
-    use crate::config::FilterConf;
+    use crate::config::{FilterConf, PhotoSizeConf};
     use crate::processing::data::DataHub;
     use crate::processing::filter::{Filter, FilterType};
     use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
@@ -71,7 +71,7 @@ mod tests {
         // 1mb photo
         let photo = DataHub::new(message_example(
             sender_user_example(),
-            MessageMock::Photo(None, 1 * 1000 * 1000),
+            MessageMock::Photo(None, 1000 * 1000),
             false,
         ));
         // 20mb document
@@ -82,7 +82,7 @@ mod tests {
         ));
 
         let filter = FilterType::from(FilterConf::FileSize {
-            size: 10 as f32,
+            size: 10_f32,
             op: ">=".to_string(),
         });
 
@@ -90,4 +90,34 @@ mod tests {
         assert_eq!(Err(()), filter.filter(&photo).await);
         assert_eq!(Ok(()), filter.filter(&document).await);
     }
+
+    #[tokio::test]
+    async fn test_file_size_of_chosen_photo_size() {
+        let mut photo = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::PhotoSizes(vec![
+                ("s", 90, 10 * 1000),
+                ("x", 800, 5 * 1000 * 1000),
+                ("m", 320, 100 * 1000),
+            ]),
+            false,
+        ));
+
+        let filter = FilterType::from(FilterConf::FileSize {
+            size: 1_f32,
+            op: ">=".to_string(),
+        });
+
+        // the largest size by default
+        assert_eq!(Ok(()), filter.filter(&photo).await);
+
+        photo.photo_size = Some(PhotoSizeConf::Type("m".to_string()));
+        assert_eq!(Err(()), filter.filter(&photo).await);
+
+        photo.photo_size = Some(PhotoSizeConf::MaxDimension(1000));
+        assert_eq!(Ok(()), filter.filter(&photo).await);
+
+        photo.photo_size = Some(PhotoSizeConf::MaxDimension(100));
+        assert_eq!(Err(()), filter.filter(&photo).await);
+    }
 }
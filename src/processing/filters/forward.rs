@@ -0,0 +1,88 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use rust_tdlib::types::MessageForwardOrigin;
+
+/// Filter by forward info
+#[derive(Debug, Clone)]
+pub enum Forward {
+    Forwarded,
+    NotForwarded,
+    HiddenUser,
+}
+
+impl Filter for Forward {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let forward_info = data.input.message().forward_info();
+
+        let pass = match self {
+            Forward::Forwarded => forward_info.is_some(),
+            Forward::NotForwarded => forward_info.is_none(),
+            Forward::HiddenUser => matches!(
+                forward_info.as_ref().map(|info| info.origin()),
+                Some(MessageForwardOrigin::HiddenUser(_))
+            ),
+        };
+
+        pass.then_some(()).ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{
+        forwarded_message_example, message_example, sender_user_example, MessageMock,
+    };
+    use rust_tdlib::types::{
+        MessageForwardOrigin, MessageForwardOriginChannel, MessageForwardOriginHiddenUser,
+    };
+
+    #[tokio::test]
+    async fn test_forwarded() {
+        let forwarded_data = DataHub::new(forwarded_message_example(
+            MessageForwardOrigin::Channel(
+                MessageForwardOriginChannel::builder().chat_id(5).build(),
+            ),
+            0,
+        ));
+        let data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+
+        let forwarded_filter = FilterType::from(FilterConf::Forwarded);
+        let not_forwarded_filter = FilterType::from(FilterConf::NotForwarded);
+
+        assert_eq!(Ok(()), forwarded_filter.filter(&forwarded_data).await);
+        assert_eq!(Err(()), forwarded_filter.filter(&data).await);
+
+        assert_eq!(Ok(()), not_forwarded_filter.filter(&data).await);
+        assert_eq!(Err(()), not_forwarded_filter.filter(&forwarded_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_hidden_forward() {
+        let hidden_data = DataHub::new(forwarded_message_example(
+            MessageForwardOrigin::HiddenUser(
+                MessageForwardOriginHiddenUser::builder()
+                    .sender_name("Someone")
+                    .build(),
+            ),
+            0,
+        ));
+        let channel_data = DataHub::new(forwarded_message_example(
+            MessageForwardOrigin::Channel(
+                MessageForwardOriginChannel::builder().chat_id(5).build(),
+            ),
+            0,
+        ));
+
+        let filter = FilterType::from(FilterConf::HiddenForward);
+
+        assert_eq!(Ok(()), filter.filter(&hidden_data).await);
+        assert_eq!(Err(()), filter.filter(&channel_data).await);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::cmp;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Filter forwarded messages by age of the original message in seconds
+#[derive(Debug, Default, Clone)]
+pub struct ForwardAge {
+    age: i64,
+    /// Operator (<,>,=)
+    op: String,
+}
+
+impl ForwardAge {
+    pub fn builder() -> ForwardAgeBuilder {
+        let inner = ForwardAge::default();
+        ForwardAgeBuilder { inner }
+    }
+}
+
+impl Filter for ForwardAge {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let forward_info = data.input.message().forward_info().as_ref().ok_or(())?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| ())?
+            .as_secs() as i64;
+
+        match cmp(&self.op, &(now - forward_info.date() as i64), &self.age) {
+            true => Ok(()),
+            false => Err(()),
+        }
+    }
+}
+
+pub struct ForwardAgeBuilder {
+    inner: ForwardAge,
+}
+
+impl ForwardAgeBuilder {
+    pub fn age(&mut self, age: i64) -> &mut ForwardAgeBuilder {
+        self.inner.age = age;
+        self
+    }
+
+    pub fn operator(&mut self, op: String) -> &mut ForwardAgeBuilder {
+        self.inner.op = op;
+        self
+    }
+
+    pub fn build(&self) -> ForwardAge {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::forwarded_message_example;
+    use rust_tdlib::types::{MessageForwardOrigin, MessageForwardOriginChannel};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn test_forward_age() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i32;
+        let origin = MessageForwardOrigin::Channel(
+            MessageForwardOriginChannel::builder().chat_id(5).build(),
+        );
+        // Forwarded message which was posted 2 days ago
+        let old_data = DataHub::new(forwarded_message_example(origin.clone(), now - 2 * 86400));
+        // Forwarded message which was posted a minute ago
+        let fresh_data = DataHub::new(forwarded_message_example(origin, now - 60));
+
+        // Older than a day
+        let filter = FilterType::from(FilterConf::ForwardAge {
+            age: 86400,
+            op: ">".to_string(),
+        });
+
+        assert_eq!(Ok(()), filter.filter(&old_data).await);
+        assert_eq!(Err(()), filter.filter(&fresh_data).await);
+    }
+}
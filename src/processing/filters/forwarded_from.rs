@@ -0,0 +1,102 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::find_forward_origin_id;
+use crate::processing::telegram::UsernameIds;
+use std::collections::HashSet;
+
+/// Filter forwarded messages by origin chat/user
+#[derive(Debug, Default, Clone)]
+pub struct ForwardedFrom {
+    /// Origin chat/user ids
+    chats: HashSet<i64>,
+    /// Origin chats/users by @username
+    usernames: UsernameIds,
+}
+
+impl ForwardedFrom {
+    pub fn builder() -> ForwardedFromBuilder {
+        let inner = ForwardedFrom::default();
+        ForwardedFromBuilder { inner }
+    }
+}
+
+impl Filter for ForwardedFrom {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let origin_id = find_forward_origin_id(data.input.message()).ok_or(())?;
+
+        if self.chats.contains(&origin_id) {
+            return Ok(());
+        }
+
+        self.usernames
+            .contains(origin_id)
+            .await
+            .then_some(())
+            .ok_or(())
+    }
+}
+
+pub struct ForwardedFromBuilder {
+    inner: ForwardedFrom,
+}
+
+impl ForwardedFromBuilder {
+    pub fn chats(&mut self, chats: Vec<i64>) -> &mut ForwardedFromBuilder {
+        self.inner.chats = chats.into_iter().collect();
+        self
+    }
+
+    pub fn usernames(&mut self, usernames: Vec<String>) -> &mut ForwardedFromBuilder {
+        self.inner.usernames = UsernameIds::new(usernames);
+        self
+    }
+
+    pub fn build(&self) -> ForwardedFrom {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{
+        forwarded_message_example, message_example, sender_user_example, MessageMock,
+    };
+    use rust_tdlib::types::{
+        MessageForwardOrigin, MessageForwardOriginChannel, MessageForwardOriginUser,
+    };
+
+    #[tokio::test]
+    async fn test_forwarded_from() {
+        let channel_data = DataHub::new(forwarded_message_example(
+            MessageForwardOrigin::Channel(
+                MessageForwardOriginChannel::builder().chat_id(5).build(),
+            ),
+            0,
+        ));
+        let user_data = DataHub::new(forwarded_message_example(
+            MessageForwardOrigin::User(
+                MessageForwardOriginUser::builder()
+                    .sender_user_id(6)
+                    .build(),
+            ),
+            0,
+        ));
+        let data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+
+        let filter = FilterType::from(FilterConf::ForwardedFrom {
+            chats: vec![5],
+            usernames: vec![],
+        });
+
+        assert_eq!(Ok(()), filter.filter(&channel_data).await);
+        assert_eq!(Err(()), filter.filter(&user_data).await);
+        assert_eq!(Err(()), filter.filter(&data).await);
+    }
+}
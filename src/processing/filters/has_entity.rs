@@ -0,0 +1,85 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::{entity_type_name, find_input_message_formatted_text};
+
+/// Filter messages which text/caption has any entity of provided types
+#[derive(Debug, Default, Clone)]
+pub struct HasEntity {
+    /// Entity type names (Url, TextUrl, Mention, Hashtag, Cashtag, BotCommand, etc.)
+    types: Vec<String>,
+}
+
+impl HasEntity {
+    pub fn builder() -> HasEntityBuilder {
+        let inner = HasEntity::default();
+        HasEntityBuilder { inner }
+    }
+}
+
+impl Filter for HasEntity {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let formatted_text = find_input_message_formatted_text(data.input.message()).ok_or(())?;
+
+        formatted_text
+            .entities()
+            .iter()
+            .any(|entity| self.types.contains(&entity_type_name(entity.type_())))
+            .then_some(())
+            .ok_or(())
+    }
+}
+
+pub struct HasEntityBuilder {
+    inner: HasEntity,
+}
+
+impl HasEntityBuilder {
+    pub fn types(&mut self, types: Vec<String>) -> &mut HasEntityBuilder {
+        self.inner.types = types;
+        self
+    }
+
+    pub fn build(&self) -> HasEntity {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{entities_message_example, entity_example};
+    use rust_tdlib::types::{TextEntityType, TextEntityTypeBold, TextEntityTypeTextUrl};
+
+    #[tokio::test]
+    async fn test_has_entity() {
+        let link_data = DataHub::new(entities_message_example(
+            "click here",
+            vec![entity_example(
+                0,
+                10,
+                TextEntityType::TextUrl(
+                    TextEntityTypeTextUrl::builder()
+                        .url("https://example.com")
+                        .build(),
+                ),
+            )],
+        ));
+        let bold_data = DataHub::new(entities_message_example(
+            "bold text",
+            vec![entity_example(
+                0,
+                4,
+                TextEntityType::Bold(TextEntityTypeBold::builder().build()),
+            )],
+        ));
+
+        let filter = FilterType::from(FilterConf::HasEntity {
+            types: vec!["Url".to_string(), "TextUrl".to_string()],
+        });
+
+        assert_eq!(Ok(()), filter.filter(&link_data).await);
+        assert_eq!(Err(()), filter.filter(&bold_data).await);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::{
+    find_input_message_formatted_text, find_links, link_has_domain, parse_link,
+};
+
+/// Filter by domains of links in text/caption. Subdomains match their domain
+#[derive(Debug, Default, Clone)]
+pub struct LinkDomain {
+    /// When not empty, every link must have one of these domains
+    allow: Vec<String>,
+    /// No link may have any of these domains
+    deny: Vec<String>,
+}
+
+impl LinkDomain {
+    pub fn builder() -> LinkDomainBuilder {
+        let inner = LinkDomain::default();
+        LinkDomainBuilder { inner }
+    }
+}
+
+impl Filter for LinkDomain {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let links = find_input_message_formatted_text(data.input.message())
+            .map(find_links)
+            .unwrap_or_default();
+
+        let passes = links
+            .iter()
+            .filter_map(|link| parse_link(link))
+            .all(|link| {
+                let allowed = self.allow.is_empty()
+                    || self
+                        .allow
+                        .iter()
+                        .any(|domain| link_has_domain(&link, domain));
+                let denied = self
+                    .deny
+                    .iter()
+                    .any(|domain| link_has_domain(&link, domain));
+
+                allowed && !denied
+            });
+
+        passes.then_some(()).ok_or(())
+    }
+}
+
+pub struct LinkDomainBuilder {
+    inner: LinkDomain,
+}
+
+impl LinkDomainBuilder {
+    pub fn allow(&mut self, allow: Vec<String>) -> &mut LinkDomainBuilder {
+        self.inner.allow = allow;
+        self
+    }
+
+    pub fn deny(&mut self, deny: Vec<String>) -> &mut LinkDomainBuilder {
+        self.inner.deny = deny;
+        self
+    }
+
+    pub fn build(&self) -> LinkDomain {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{entities_message_example, entity_example};
+    use rust_tdlib::types::{TextEntityType, TextEntityTypeTextUrl, TextEntityTypeUrl};
+
+    #[tokio::test]
+    async fn test_link_domain() {
+        let telegram_data = DataHub::new(entities_message_example(
+            "join t.me/joinchat/abc",
+            vec![entity_example(
+                5,
+                17,
+                TextEntityType::Url(TextEntityTypeUrl::builder().build()),
+            )],
+        ));
+        let hidden_link_data = DataHub::new(entities_message_example(
+            "read more",
+            vec![entity_example(
+                0,
+                9,
+                TextEntityType::TextUrl(
+                    TextEntityTypeTextUrl::builder()
+                        .url("https://news.example.com/article")
+                        .build(),
+                ),
+            )],
+        ));
+
+        let deny_filter = FilterType::from(FilterConf::LinkDomain {
+            allow: vec![],
+            deny: vec!["t.me".to_string(), "bit.ly".to_string()],
+        });
+        let allow_filter = FilterType::from(FilterConf::LinkDomain {
+            allow: vec!["example.com".to_string()],
+            deny: vec![],
+        });
+
+        assert_eq!(Err(()), deny_filter.filter(&telegram_data).await);
+        assert_eq!(Ok(()), deny_filter.filter(&hidden_link_data).await);
+
+        assert_eq!(Ok(()), allow_filter.filter(&hidden_link_data).await);
+        assert_eq!(Err(()), allow_filter.filter(&telegram_data).await);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::{find_input_message_formatted_text, find_links};
+
+/// Filter messages which text/caption has no more links than provided count
+#[derive(Debug, Default, Clone)]
+pub struct MaxLinks {
+    count: usize,
+}
+
+impl MaxLinks {
+    pub fn builder() -> MaxLinksBuilder {
+        let inner = MaxLinks::default();
+        MaxLinksBuilder { inner }
+    }
+}
+
+impl Filter for MaxLinks {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let links = find_input_message_formatted_text(data.input.message())
+            .map(find_links)
+            .unwrap_or_default();
+
+        (links.len() <= self.count).then_some(()).ok_or(())
+    }
+}
+
+pub struct MaxLinksBuilder {
+    inner: MaxLinks,
+}
+
+impl MaxLinksBuilder {
+    pub fn count(&mut self, count: usize) -> &mut MaxLinksBuilder {
+        self.inner.count = count;
+        self
+    }
+
+    pub fn build(&self) -> MaxLinks {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{entities_message_example, entity_example};
+    use rust_tdlib::types::{TextEntityType, TextEntityTypeTextUrl, TextEntityTypeUrl};
+
+    #[tokio::test]
+    async fn test_max_links() {
+        let one_link_data = DataHub::new(entities_message_example(
+            "see t.me/news",
+            vec![entity_example(
+                4,
+                9,
+                TextEntityType::Url(TextEntityTypeUrl::builder().build()),
+            )],
+        ));
+        let two_links_data = DataHub::new(entities_message_example(
+            "see t.me/news and this",
+            vec![
+                entity_example(
+                    4,
+                    9,
+                    TextEntityType::Url(TextEntityTypeUrl::builder().build()),
+                ),
+                entity_example(
+                    18,
+                    4,
+                    TextEntityType::TextUrl(
+                        TextEntityTypeTextUrl::builder()
+                            .url("https://example.com")
+                            .build(),
+                    ),
+                ),
+            ],
+        ));
+
+        let filter = FilterType::from(FilterConf::MaxLinks { count: 1 });
+
+        assert_eq!(Ok(()), filter.filter(&one_link_data).await);
+        assert_eq!(Err(()), filter.filter(&two_links_data).await);
+    }
+}
@@ -10,6 +10,15 @@ pub enum MessageType {
     Photo,
     Animation,
     Document,
+    Audio,
+    Voice,
+    VideoNote,
+    Sticker,
+    Poll,
+    Location,
+    Venue,
+    Contact,
+    Dice,
     AnyFile,
 }
 
@@ -43,6 +52,42 @@ impl Filter for MessageType {
                 }
                 _ => Err(()),
             },
+            MessageType::Audio => match message_content {
+                MessageContent::MessageAudio(_) => Ok(()),
+                _ => Err(()),
+            },
+            MessageType::Voice => match message_content {
+                MessageContent::MessageVoiceNote(_) => Ok(()),
+                _ => Err(()),
+            },
+            MessageType::VideoNote => match message_content {
+                MessageContent::MessageVideoNote(_) => Ok(()),
+                _ => Err(()),
+            },
+            MessageType::Sticker => match message_content {
+                MessageContent::MessageSticker(_) => Ok(()),
+                _ => Err(()),
+            },
+            MessageType::Poll => match message_content {
+                MessageContent::MessagePoll(_) => Ok(()),
+                _ => Err(()),
+            },
+            MessageType::Location => match message_content {
+                MessageContent::MessageLocation(_) => Ok(()),
+                _ => Err(()),
+            },
+            MessageType::Venue => match message_content {
+                MessageContent::MessageVenue(_) => Ok(()),
+                _ => Err(()),
+            },
+            MessageType::Contact => match message_content {
+                MessageContent::MessageContact(_) => Ok(()),
+                _ => Err(()),
+            },
+            MessageType::Dice => match message_content {
+                MessageContent::MessageDice(_) => Ok(()),
+                _ => Err(()),
+            },
             MessageType::AnyFile => {
                 if matches!(
                     message_content,
@@ -50,6 +95,9 @@ impl Filter for MessageType {
                         | MessageContent::MessagePhoto(_)
                         | MessageContent::MessageAnimation(_)
                         | MessageContent::MessageDocument(_)
+                        | MessageContent::MessageAudio(_)
+                        | MessageContent::MessageVoiceNote(_)
+                        | MessageContent::MessageVideoNote(_)
                 ) {
                     Ok(())
                 } else {
@@ -90,6 +138,11 @@ mod tests {
                 MessageMock::Animation(None, 0, 0),
                 false,
             )),
+            DataHub::new(message_example(
+                sender_user_example(),
+                MessageMock::VideoNote(0, 0),
+                false,
+            )),
         ];
         let fail_data = DataHub::new(message_example(
             sender_user_example(),
@@ -195,4 +248,166 @@ mod tests {
         assert_eq!(Ok(()), filter.filter(&success_data).await);
         assert_eq!(Err(()), filter.filter(&fail_data).await);
     }
+
+    #[tokio::test]
+    async fn test_audio() {
+        let success_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Audio(None, 0, 0),
+            false,
+        ));
+        let fail_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(FilterConf::Audio);
+
+        assert_eq!(Ok(()), filter.filter(&success_data).await);
+        assert_eq!(Err(()), filter.filter(&fail_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_voice() {
+        let success_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Voice(None, 0, 0),
+            false,
+        ));
+        let fail_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(FilterConf::Voice);
+
+        assert_eq!(Ok(()), filter.filter(&success_data).await);
+        assert_eq!(Err(()), filter.filter(&fail_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_sticker() {
+        let success_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Sticker(0),
+            false,
+        ));
+        let fail_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(FilterConf::Sticker);
+
+        assert_eq!(Ok(()), filter.filter(&success_data).await);
+        assert_eq!(Err(()), filter.filter(&fail_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_poll() {
+        let success_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Poll("Question".to_string(), vec![]),
+            false,
+        ));
+        let fail_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(FilterConf::Poll);
+
+        assert_eq!(Ok(()), filter.filter(&success_data).await);
+        assert_eq!(Err(()), filter.filter(&fail_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_location() {
+        let success_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Location(0.0, 0.0),
+            false,
+        ));
+        let fail_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(FilterConf::Location);
+
+        assert_eq!(Ok(()), filter.filter(&success_data).await);
+        assert_eq!(Err(()), filter.filter(&fail_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_video_note() {
+        let success_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::VideoNote(0, 0),
+            false,
+        ));
+        let fail_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(FilterConf::VideoNote);
+
+        assert_eq!(Ok(()), filter.filter(&success_data).await);
+        assert_eq!(Err(()), filter.filter(&fail_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_venue() {
+        let success_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Venue("Venue"),
+            false,
+        ));
+        let fail_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(FilterConf::Venue);
+
+        assert_eq!(Ok(()), filter.filter(&success_data).await);
+        assert_eq!(Err(()), filter.filter(&fail_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_contact() {
+        let success_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Contact("+100"),
+            false,
+        ));
+        let fail_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(FilterConf::Contact);
+
+        assert_eq!(Ok(()), filter.filter(&success_data).await);
+        assert_eq!(Err(()), filter.filter(&fail_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_dice() {
+        let success_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Dice("🎲"),
+            false,
+        ));
+        let fail_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(FilterConf::Dice);
+
+        assert_eq!(Ok(()), filter.filter(&success_data).await);
+        assert_eq!(Err(()), filter.filter(&fail_data).await);
+    }
 }
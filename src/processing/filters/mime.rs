@@ -0,0 +1,119 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::find_input_message_mime_type;
+
+/// Filter by MIME type of document, audio, video, animation or voice note.
+/// Type may end with wildcard, e.g. `image/*`
+#[derive(Debug, Default, Clone)]
+pub struct Mime {
+    /// When not empty, MIME type must match one of these types
+    allow: Vec<String>,
+    /// MIME type must not match any of these types
+    deny: Vec<String>,
+}
+
+impl Mime {
+    pub fn builder() -> MimeBuilder {
+        let inner = Mime::default();
+        MimeBuilder { inner }
+    }
+}
+
+/// Whether MIME type matches pattern, case-insensitive
+fn mime_matches(mime_type: &str, pattern: &str) -> bool {
+    let mime_type = mime_type.to_lowercase();
+    let pattern = pattern.to_lowercase();
+
+    match pattern.strip_suffix('*') {
+        Some(prefix) => mime_type.starts_with(prefix),
+        None => mime_type == pattern,
+    }
+}
+
+impl Filter for Mime {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let mime_type = find_input_message_mime_type(data.input.message());
+        let matches = |patterns: &Vec<String>| {
+            mime_type.is_some_and(|mime_type| {
+                patterns
+                    .iter()
+                    .any(|pattern| mime_matches(mime_type, pattern))
+            })
+        };
+
+        let allowed = self.allow.is_empty() || matches(&self.allow);
+        let denied = matches(&self.deny);
+
+        (allowed && !denied).then_some(()).ok_or(())
+    }
+}
+
+pub struct MimeBuilder {
+    inner: Mime,
+}
+
+impl MimeBuilder {
+    pub fn allow(&mut self, allow: Vec<String>) -> &mut MimeBuilder {
+        self.inner.allow = allow;
+        self
+    }
+
+    pub fn deny(&mut self, deny: Vec<String>) -> &mut MimeBuilder {
+        self.inner.deny = deny;
+        self
+    }
+
+    pub fn build(&self) -> Mime {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
+
+    #[tokio::test]
+    async fn test_mime() {
+        let pdf = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::NamedDocument("report.pdf", "application/pdf"),
+            false,
+        ));
+        let image = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::NamedDocument("cat.PNG", "Image/PNG"),
+            false,
+        ));
+        let apk = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::NamedDocument("app.apk", "application/vnd.android.package-archive"),
+            false,
+        ));
+        let text = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+
+        let allow_filter = FilterType::from(FilterConf::Mime {
+            allow: vec!["application/pdf".to_string(), "image/*".to_string()],
+            deny: vec![],
+        });
+        let deny_filter = FilterType::from(FilterConf::Mime {
+            allow: vec![],
+            deny: vec!["application/vnd.android.*".to_string()],
+        });
+
+        assert_eq!(Ok(()), allow_filter.filter(&pdf).await);
+        assert_eq!(Ok(()), allow_filter.filter(&image).await);
+        assert_eq!(Err(()), allow_filter.filter(&apk).await);
+        assert_eq!(Err(()), allow_filter.filter(&text).await);
+
+        assert_eq!(Ok(()), deny_filter.filter(&pdf).await);
+        assert_eq!(Err(()), deny_filter.filter(&apk).await);
+        assert_eq!(Ok(()), deny_filter.filter(&text).await);
+    }
+}
@@ -1,25 +1,65 @@
 pub mod counter;
 pub mod duration;
+pub mod entity_value;
+pub mod extension;
+pub mod file_name;
 pub mod file_size;
+pub mod forward;
+pub mod forward_age;
+pub mod forwarded_from;
+pub mod has_entity;
 pub mod incoming;
+pub mod link_domain;
+pub mod max_links;
 pub mod message_type;
+pub mod mime;
+#[cfg(feature = "storage")]
+pub mod near_duplicate;
 #[cfg(feature = "openai")]
 pub mod openai;
+#[cfg(feature = "storage")]
+pub mod rate_limit;
 pub mod regexp;
+#[cfg(feature = "schedule")]
+pub mod schedule;
+pub mod sender;
+#[cfg(feature = "similar_image")]
+pub mod similar_image;
 pub mod text_length;
 #[cfg(feature = "storage")]
 pub mod unique;
+pub mod variable;
 pub mod wordlist;
 
 pub(crate) use counter::Counter;
 pub(crate) use duration::Duration;
+pub(crate) use entity_value::EntityValue;
+pub(crate) use extension::Extension;
+pub(crate) use file_name::FileName;
 pub(crate) use file_size::FileSize;
+pub(crate) use forward::Forward;
+pub(crate) use forward_age::ForwardAge;
+pub(crate) use forwarded_from::ForwardedFrom;
+pub(crate) use has_entity::HasEntity;
 pub(crate) use incoming::Incoming;
+pub(crate) use link_domain::LinkDomain;
+pub(crate) use max_links::MaxLinks;
 pub(crate) use message_type::MessageType;
+pub(crate) use mime::Mime;
+#[cfg(feature = "storage")]
+pub(crate) use near_duplicate::NearDuplicate;
 #[cfg(feature = "openai")]
 pub(crate) use openai::OpenAi;
+#[cfg(feature = "storage")]
+pub(crate) use rate_limit::RateLimit;
 pub(crate) use regexp::Regexp;
+#[cfg(feature = "schedule")]
+pub(crate) use schedule::Schedule;
+pub(crate) use sender::{Sender, SenderListType};
+#[cfg(feature = "similar_image")]
+pub(crate) use similar_image::SimilarImage;
 pub(crate) use text_length::TextLength;
 #[cfg(feature = "storage")]
 pub(crate) use unique::Unique;
+pub(crate) use variable::Variable;
 pub(crate) use wordlist::{WordList, WordListType};
@@ -0,0 +1,213 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::{find_input_message_text, normalize_text};
+use crate::storage::{blocking, seen_similar};
+
+/// Texts with fewer words are too short to compare and always pass
+const MIN_WORDS: usize = 3;
+
+/// Filter texts/captions similar to recently seen ones.
+/// Text is fingerprinted with 64-bit SimHash of its words and word pairs, links are ignored
+#[derive(Debug, Clone)]
+pub struct NearDuplicate {
+    /// Namespace of seen fingerprints, pipeline name or route when not provided
+    namespace: Option<String>,
+    /// Maximum Hamming distance between fingerprints of similar texts
+    distance: u32,
+    /// Seconds for which fingerprint is kept
+    window: u64,
+}
+
+impl Default for NearDuplicate {
+    fn default() -> Self {
+        NearDuplicate {
+            namespace: None,
+            distance: 3,
+            window: 24 * 60 * 60,
+        }
+    }
+}
+
+impl NearDuplicate {
+    pub fn builder() -> NearDuplicateBuilder {
+        let inner = NearDuplicate::default();
+        NearDuplicateBuilder { inner }
+    }
+
+    fn namespace(&self, data: &DataHub) -> String {
+        let namespace = self
+            .namespace
+            .clone()
+            .unwrap_or_else(|| data.default_namespace());
+
+        format!("near_duplicate/{}", namespace)
+    }
+}
+
+/// Stable 64-bit FNV-1a hash, fingerprints are persisted
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// SimHash of normalized words and word pairs. None for too short texts
+pub(crate) fn simhash(text: &str) -> Option<u64> {
+    let text = text
+        .split_whitespace()
+        .filter(|word| !word.contains("://") && !word.starts_with("www."))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let normalized = normalize_text(&text);
+    let words: Vec<&str> = normalized.split(' ').filter(|w| !w.is_empty()).collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+
+    let features = words
+        .iter()
+        .map(|word| word.to_string())
+        .chain(words.windows(2).map(|pair| pair.join(" ")));
+
+    let mut weights = [0i32; 64];
+    for feature in features {
+        let hash = fnv1a(&feature);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            match hash >> bit & 1 {
+                1 => *weight += 1,
+                _ => *weight -= 1,
+            }
+        }
+    }
+
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, weight)| **weight > 0)
+            .fold(0, |fingerprint, (bit, _)| fingerprint | 1 << bit),
+    )
+}
+
+impl Filter for NearDuplicate {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let Some(fingerprint) =
+            find_input_message_text(data.input.message()).and_then(|text| simhash(text))
+        else {
+            return Ok(());
+        };
+
+        let namespace = self.namespace(data);
+        let (distance, window) = (self.distance, self.window);
+        let related = data.related_ids();
+
+        match blocking(move || {
+            seen_similar(&namespace, "", fingerprint, distance, window, &related)
+        })
+        .await
+        {
+            Ok(true) => Err(()),
+            Ok(false) => Ok(()),
+            Err(e) => {
+                eprintln!("NearDuplicate: {}", e);
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct NearDuplicateBuilder {
+    inner: NearDuplicate,
+}
+
+impl NearDuplicateBuilder {
+    pub fn namespace(&mut self, namespace: Option<String>) -> &mut NearDuplicateBuilder {
+        self.inner.namespace = namespace;
+        self
+    }
+
+    pub fn distance(&mut self, distance: u32) -> &mut NearDuplicateBuilder {
+        self.inner.distance = distance;
+        self
+    }
+
+    pub fn window(&mut self, window: u64) -> &mut NearDuplicateBuilder {
+        self.inner.window = window;
+        self
+    }
+
+    pub fn build(&self) -> NearDuplicate {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simhash;
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::helpers::normalize_text;
+    use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const NEWS: &str = "Central bank raised the key rate by half a percent today, \
+        the third increase this year as inflation keeps growing";
+
+    fn text_data(text: &str) -> DataHub {
+        DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(Some(text.to_string())),
+            false,
+        ))
+    }
+
+    fn distance(left: &str, right: &str) -> u32 {
+        (simhash(left).unwrap() ^ simhash(right).unwrap()).count_ones()
+    }
+
+    #[test]
+    fn test_simhash() {
+        assert_eq!("hello world 42", normalize_text(" Hello,\n WORLD... #42 "));
+        assert_eq!(None, simhash("Hello world"));
+
+        let reworded = format!("🔥 BREAKING: {}! https://example.com/news", NEWS);
+        let other = "Local football club won the championship after a dramatic \
+            penalty shootout in front of a full stadium";
+
+        assert_eq!(0, distance(NEWS, &NEWS.to_uppercase()));
+        assert!(distance(NEWS, &reworded) <= 3);
+        assert!(distance(NEWS, other) > 10);
+    }
+
+    #[tokio::test]
+    async fn test_near_duplicate() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let filter = FilterType::from(FilterConf::NearDuplicate {
+            namespace: Some(format!("test_{}", nanos)),
+            distance: 3,
+            window: 60,
+        });
+
+        assert_eq!(Ok(()), filter.filter(&text_data(NEWS)).await);
+        assert_eq!(
+            Err(()),
+            filter
+                .filter(&text_data(&format!("{} 📈 https://t.me/news", NEWS)))
+                .await
+        );
+        assert_eq!(Ok(()), filter.filter(&text_data("Short text")).await);
+        assert_eq!(Ok(()), filter.filter(&text_data("Short text")).await);
+        assert_eq!(
+            Ok(()),
+            filter
+                .filter(&text_data(
+                    "Weather service expects heavy rain and strong wind over the weekend"
+                ))
+                .await
+        );
+    }
+}
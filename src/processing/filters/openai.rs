@@ -51,6 +51,8 @@ lazy_static! {
 struct Response {
     #[serde(default)]
     pub allow: bool,
+    #[serde(default)]
+    pub analyses: String,
 }
 
 /// Filter by context using LLM
@@ -104,11 +106,13 @@ impl Filter for OpenAi {
                 .choices
                 .iter()
                 .any(|choice| {
-                    serde_json::from_str::<Response>(
+                    let response = serde_json::from_str::<Response>(
                         choice.message.content.as_ref().unwrap_or(&"{}".to_string()),
                     )
-                    .unwrap()
-                    .allow
+                    .unwrap();
+                    // LLM's analyses can be used by next filters and pipes
+                    data.variables.set("openai_analyses", &response.analyses);
+                    response.allow
                 })
                 .then_some(())
                 .ok_or(()),
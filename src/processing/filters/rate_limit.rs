@@ -0,0 +1,309 @@
+use crate::config::{RateLimitKey, RateLimitOverflow};
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::find_sender_id;
+use crate::storage::{blocking, storage, StorageResult};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Limit messages with token bucket: `max` tokens, refilled in `per_seconds`.
+/// Buckets are kept in storage per route, source chat, sender or destination chat
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    max: u32,
+    per_seconds: u64,
+    key: RateLimitKey,
+    on_exceed: RateLimitOverflow,
+    /// Longest delay of deferred message in seconds, later messages are rejected
+    max_delay: u64,
+    /// Namespace of buckets, pipeline name or route when not provided
+    namespace: Option<String>,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            max: 1,
+            per_seconds: 60,
+            key: RateLimitKey::default(),
+            on_exceed: RateLimitOverflow::default(),
+            max_delay: 60 * 60,
+            namespace: None,
+        }
+    }
+}
+
+/// Token bucket state: tokens (negative when deferred messages are owed) and time in ms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Bucket {
+    tokens: f64,
+    updated_at: u64,
+}
+
+impl Bucket {
+    fn parse(value: &str) -> Option<Self> {
+        let (tokens, updated_at) = value.split_once(':')?;
+
+        Some(Bucket {
+            tokens: tokens.parse().ok()?,
+            updated_at: updated_at.parse().ok()?,
+        })
+    }
+
+    fn value(&self) -> String {
+        format!("{}:{}", self.tokens, self.updated_at)
+    }
+
+    /// Bucket refilled by `now`, never above capacity
+    fn refilled(&self, now: u64, capacity: f64, rate: f64) -> Self {
+        let elapsed = now.saturating_sub(self.updated_at) as f64;
+
+        Bucket {
+            tokens: (self.tokens + elapsed * rate).min(capacity),
+            updated_at: now,
+        }
+    }
+}
+
+/// Current unix time in milliseconds
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+impl RateLimit {
+    pub fn builder() -> RateLimitBuilder {
+        let inner = RateLimit::default();
+        RateLimitBuilder { inner }
+    }
+
+    fn namespace(&self, data: &DataHub) -> String {
+        let namespace = self
+            .namespace
+            .clone()
+            .unwrap_or_else(|| data.default_namespace());
+
+        format!("rate_limit/{}", namespace)
+    }
+
+    fn key(&self, data: &DataHub) -> String {
+        let message = data.input.message();
+        let destination = data.destination.unwrap_or_default();
+
+        match self.key {
+            RateLimitKey::Route => format!("route:{}:{}", message.chat_id(), destination),
+            RateLimitKey::Source => format!("source:{}", message.chat_id()),
+            RateLimitKey::Sender => format!("sender:{}", find_sender_id(message)),
+            RateLimitKey::Dest => format!("dest:{}", destination),
+        }
+    }
+
+    /// Take token from bucket. Returns milliseconds to wait for it,
+    /// None when message is rejected
+    pub(crate) fn take(&self, bucket: Option<Bucket>, now: u64) -> (Option<u64>, Bucket) {
+        let capacity = self.max as f64;
+        // Tokens per millisecond
+        let rate = capacity / (self.per_seconds.max(1) * 1000) as f64;
+        let bucket = bucket
+            .unwrap_or(Bucket {
+                tokens: capacity,
+                updated_at: now,
+            })
+            .refilled(now, capacity, rate);
+
+        if bucket.tokens >= 1.0 {
+            let taken = Bucket {
+                tokens: bucket.tokens - 1.0,
+                ..bucket
+            };
+            return (Some(0), taken);
+        }
+
+        let wait = ((1.0 - bucket.tokens) / rate).ceil() as u64;
+        match self.on_exceed {
+            RateLimitOverflow::Defer if wait <= self.max_delay * 1000 => {
+                let owed = Bucket {
+                    tokens: bucket.tokens - 1.0,
+                    ..bucket
+                };
+                (Some(wait), owed)
+            }
+            _ => (None, bucket),
+        }
+    }
+
+    /// Take token from stored bucket of key. Returns milliseconds to wait for it
+    /// (None when message is rejected) and time of taking
+    fn take_stored(
+        &self,
+        namespace: &str,
+        key: &str,
+        related: &[i64],
+    ) -> StorageResult<(Option<u64>, u64)> {
+        let storage = storage();
+
+        loop {
+            let current = storage.get(namespace, key)?;
+
+            let now = now_millis();
+            let (wait, bucket) = self.take(current.as_deref().and_then(Bucket::parse), now);
+            // Bucket is full again by then, the same as missing one
+            let ttl = Some(self.per_seconds + self.max_delay);
+
+            // Changed concurrently, try again
+            if storage.compare_and_set(
+                namespace,
+                key,
+                current.as_deref(),
+                &bucket.value(),
+                ttl,
+                related,
+            )? {
+                return Ok((wait, now));
+            }
+        }
+    }
+}
+
+impl Filter for RateLimit {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let rate_limit = self.clone();
+        let namespace = self.namespace(data);
+        let key = self.key(data);
+        let related = data.related_ids();
+
+        match blocking(move || rate_limit.take_stored(&namespace, &key, &related)).await {
+            Ok((Some(0), _)) => Ok(()),
+            Ok((Some(wait), now)) => {
+                data.defer_until((now + wait).div_ceil(1000));
+                Ok(())
+            }
+            Ok((None, _)) => Err(()),
+            // Storage errors don't stop messages
+            Err(e) => {
+                eprintln!("RateLimit: {}", e);
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct RateLimitBuilder {
+    inner: RateLimit,
+}
+
+impl RateLimitBuilder {
+    pub fn max(&mut self, max: u32) -> &mut RateLimitBuilder {
+        self.inner.max = max;
+        self
+    }
+
+    pub fn per_seconds(&mut self, per_seconds: u64) -> &mut RateLimitBuilder {
+        self.inner.per_seconds = per_seconds;
+        self
+    }
+
+    pub fn key(&mut self, key: RateLimitKey) -> &mut RateLimitBuilder {
+        self.inner.key = key;
+        self
+    }
+
+    pub fn on_exceed(&mut self, on_exceed: RateLimitOverflow) -> &mut RateLimitBuilder {
+        self.inner.on_exceed = on_exceed;
+        self
+    }
+
+    pub fn max_delay(&mut self, max_delay: u64) -> &mut RateLimitBuilder {
+        self.inner.max_delay = max_delay;
+        self
+    }
+
+    pub fn namespace(&mut self, namespace: Option<String>) -> &mut RateLimitBuilder {
+        self.inner.namespace = namespace;
+        self
+    }
+
+    pub fn build(&self) -> RateLimit {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bucket, RateLimit};
+    use crate::config::{FilterConf, RateLimitKey, RateLimitOverflow};
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
+
+    fn rate_limit_conf(namespace: &str, on_exceed: RateLimitOverflow) -> FilterConf {
+        FilterConf::RateLimit {
+            max: 2,
+            per_seconds: 60,
+            key: RateLimitKey::Route,
+            on_exceed,
+            max_delay: 60,
+            namespace: Some(namespace.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let limit = RateLimit::builder()
+            .max(2)
+            .per_seconds(60)
+            .on_exceed(RateLimitOverflow::Defer)
+            .max_delay(60)
+            .build();
+
+        let (wait, bucket) = limit.take(None, 0);
+        assert_eq!(Some(0), wait);
+        let (wait, bucket) = limit.take(Some(bucket), 0);
+        assert_eq!(Some(0), wait);
+        // Token is refilled in 30 seconds
+        let (wait, bucket) = limit.take(Some(bucket), 0);
+        assert_eq!(Some(30_000), wait);
+        let (wait, bucket) = limit.take(Some(bucket), 0);
+        assert_eq!(Some(60_000), wait);
+        // Longer than max delay
+        let (wait, bucket) = limit.take(Some(bucket), 0);
+        assert_eq!(None, wait);
+
+        // Owed tokens are paid in a minute, the bucket is full in two
+        let (wait, bucket) = limit.take(Some(bucket), 120_000);
+        assert_eq!(Some(0), wait);
+        assert_eq!(Bucket::parse("1:120000"), Some(bucket));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(rate_limit_conf("test_reject", RateLimitOverflow::Reject));
+
+        assert_eq!(Ok(()), filter.filter(&data).await);
+        assert_eq!(Ok(()), filter.filter(&data).await);
+        assert_eq!(Err(()), filter.filter(&data).await);
+        assert_eq!(None, data.send_at());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_defer() {
+        let data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = FilterType::from(rate_limit_conf("test_defer", RateLimitOverflow::Defer));
+
+        assert_eq!(Ok(()), filter.filter(&data).await);
+        assert_eq!(Ok(()), filter.filter(&data).await);
+        assert_eq!(None, data.send_at());
+        assert_eq!(Ok(()), filter.filter(&data).await);
+        assert!(data.send_at().is_some());
+    }
+}
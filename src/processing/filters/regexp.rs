@@ -3,7 +3,8 @@ use crate::processing::filter::{Filter, FilterResult};
 use crate::processing::helpers::find_input_message_text;
 use regex::Regex;
 
-/// Filter by text/caption where regular expression matches
+/// Filter by text/caption where regular expression matches.
+/// Named capture groups are set as variables
 #[derive(Debug, Default, Clone)]
 pub struct Regexp {
     exp: String,
@@ -21,10 +22,16 @@ impl Filter for Regexp {
     async fn filter(&self, data: &DataHub) -> FilterResult {
         let text = find_input_message_text(data.input.message()).ok_or(())?;
 
-        match self.pattern.as_ref().ok_or(())?.is_match(text) {
-            true => Ok(()),
-            false => Err(()),
+        let pattern = self.pattern.as_ref().ok_or(())?;
+        let captures = pattern.captures(text).ok_or(())?;
+
+        for name in pattern.capture_names().flatten() {
+            if let Some(value) = captures.name(name) {
+                data.variables.set(name, value.as_str());
+            }
         }
+
+        Ok(())
     }
 }
 
@@ -77,4 +84,29 @@ mod tests {
         assert_eq!(Ok(()), number_filter.filter(&number_message_data).await);
         assert_eq!(Err(()), number_filter.filter(&example_message_data).await);
     }
+
+    #[tokio::test]
+    async fn test_regexp_caption() {
+        let filter = FilterType::from(FilterConf::Regexp {
+            exp: "^example$".to_string(),
+        });
+        // Captions of documents, audio and voice notes are matched as texts
+        let mocks = vec![
+            MessageMock::Document(Some("example".to_string()), 0),
+            MessageMock::Audio(Some("example".to_string()), 0, 0),
+            MessageMock::Voice(Some("example".to_string()), 0, 0),
+        ];
+
+        for mock in mocks {
+            let data = DataHub::new(message_example(sender_user_example(), mock, false));
+            assert_eq!(Ok(()), filter.filter(&data).await);
+        }
+
+        let sticker_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Sticker(0),
+            false,
+        ));
+        assert_eq!(Err(()), filter.filter(&sticker_data).await);
+    }
 }
@@ -0,0 +1,496 @@
+use crate::config::ScheduleTime;
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+
+/// How far Schedule pipe looks for allowed time
+const HORIZON_MINUTES: i64 = 31 * 24 * 60;
+
+/// Filter by time of message: allowed and denied windows or cron expressions, holidays
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    tz: Tz,
+    allow: Vec<Rule>,
+    deny: Vec<Rule>,
+    holidays: Vec<Holiday>,
+    time: ScheduleTime,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule {
+            tz: Tz::UTC,
+            allow: vec![],
+            deny: vec![],
+            holidays: vec![],
+            time: ScheduleTime::default(),
+        }
+    }
+}
+
+/// Time rule: "Mon-Fri 09:00-18:00" window or "*/15 9-17 * * 1-5" cron expression
+#[derive(Debug, Clone, PartialEq)]
+enum Rule {
+    Window(Window),
+    Cron(Cron),
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Result<Self, String> {
+        // Cron expressions always have five fields
+        match rule.split_whitespace().count() {
+            5 => Cron::parse(rule).map(Rule::Cron),
+            _ => Window::parse(rule).map(Rule::Window),
+        }
+    }
+
+    fn matches(&self, time: &DateTime<Tz>) -> bool {
+        match self {
+            Rule::Window(window) => window.matches(time),
+            Rule::Cron(cron) => cron.matches(time),
+        }
+    }
+}
+
+/// Days of week (bits from Monday) and minutes of day. Window ending before its start spans midnight,
+/// its part after midnight belongs to the day it started on
+#[derive(Debug, Clone, PartialEq)]
+struct Window {
+    days: u8,
+    from: u32,
+    to: u32,
+}
+
+impl Window {
+    fn parse(window: &str) -> Result<Self, String> {
+        let mut days = None;
+        let mut minutes = None;
+
+        for part in window.split_whitespace() {
+            if part.contains(':') {
+                let (from, to) = part.split_once('-').ok_or(format!("no end in {}", part))?;
+                minutes = Some((parse_minutes(from)?, parse_minutes(to)?));
+            } else {
+                days = Some(parse_days(part)?);
+            }
+        }
+
+        if days.is_none() && minutes.is_none() {
+            return Err(format!("empty window '{}'", window));
+        }
+
+        let (from, to) = minutes.unwrap_or((0, 24 * 60));
+        Ok(Window {
+            days: days.unwrap_or(0b111_1111),
+            from,
+            to,
+        })
+    }
+
+    fn matches(&self, time: &DateTime<Tz>) -> bool {
+        let minute = time.hour() * 60 + time.minute();
+        let weekday = time.weekday().num_days_from_monday();
+        let day = match self.from.cmp(&self.to) {
+            std::cmp::Ordering::Less => {
+                (self.from <= minute && minute < self.to).then_some(weekday)
+            }
+            std::cmp::Ordering::Greater if minute >= self.from => Some(weekday),
+            // After midnight window belongs to the day it started on
+            std::cmp::Ordering::Greater if minute < self.to => Some((weekday + 6) % 7),
+            std::cmp::Ordering::Greater => None,
+            std::cmp::Ordering::Equal => Some(weekday),
+        };
+
+        day.is_some_and(|day| self.days & (1 << day) != 0)
+    }
+}
+
+/// "HH:MM" as minutes of day, "24:00" is the end of day
+fn parse_minutes(time: &str) -> Result<u32, String> {
+    let (hours, minutes) = time
+        .split_once(':')
+        .ok_or(format!("invalid time {}", time))?;
+    let hours: u32 = hours
+        .parse()
+        .map_err(|_| format!("invalid hours {}", time))?;
+    let minutes: u32 = minutes
+        .parse()
+        .map_err(|_| format!("invalid minutes {}", time))?;
+
+    match hours * 60 + minutes {
+        total if minutes < 60 && total <= 24 * 60 => Ok(total),
+        _ => Err(format!("invalid time {}", time)),
+    }
+}
+
+/// "Mon-Fri", "Sat,Sun" or "Fri-Mon" as bits from Monday
+fn parse_days(days: &str) -> Result<u8, String> {
+    let parse_day = |day: &str| {
+        day.parse::<Weekday>()
+            .map(|day| day.num_days_from_monday())
+            .map_err(|_| format!("invalid day {}", day))
+    };
+
+    let mut bits = 0;
+    for part in days.split(',') {
+        let (from, to) = match part.split_once('-') {
+            Some((from, to)) => (parse_day(from)?, parse_day(to)?),
+            None => (parse_day(part)?, parse_day(part)?),
+        };
+
+        let mut day = from;
+        loop {
+            bits |= 1 << day;
+            if day == to {
+                break;
+            }
+            day = (day + 1) % 7;
+        }
+    }
+    Ok(bits)
+}
+
+/// Cron expression: minute, hour, day of month, month and day of week (0 or 7 is Sunday)
+#[derive(Debug, Clone, PartialEq)]
+struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day of month or week is "*". When both are restricted either of them matches
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "cron expression '{}' must have 5 fields",
+                expression
+            ));
+        };
+
+        let mut weekdays_bits = parse_cron_field(weekdays, 0, 7)?;
+        // Sunday is both 0 and 7
+        if weekdays_bits & (1 << 7) != 0 {
+            weekdays_bits |= 1;
+        }
+
+        Ok(Cron {
+            minutes: parse_cron_field(minutes, 0, 59)?,
+            hours: parse_cron_field(hours, 0, 23)?,
+            days: parse_cron_field(days, 1, 31)?,
+            months: parse_cron_field(months, 1, 12)?,
+            weekdays: weekdays_bits,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+
+    fn matches(&self, time: &DateTime<Tz>) -> bool {
+        let has = |bits: u64, value: u32| bits & (1 << value) != 0;
+
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+
+        day_matches
+            && has(self.minutes, time.minute())
+            && has(self.hours, time.hour())
+            && has(self.months, time.month())
+    }
+}
+
+/// Cron field ("*", "1-5", "*/15", "0,30") as bits of values
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let parse = |value: &str| match value.parse::<u32>() {
+        Ok(value) if (min..=max).contains(&value) => Ok(value),
+        _ => Err(format!("invalid value {} in cron field {}", value, field)),
+    };
+
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or(format!("invalid step in cron field {}", field))?,
+            ),
+            None => (part, 1),
+        };
+
+        let (from, to) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((from, to)) => (parse(from)?, parse(to)?),
+            // "5/10" is from 5 to the end
+            None if step > 1 => (parse(range)?, max),
+            None => (parse(range)?, parse(range)?),
+        };
+
+        for value in (from..=to).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// Holiday date: "2025-12-24" once or "12-25" every year
+#[derive(Debug, Clone, PartialEq)]
+enum Holiday {
+    Date(NaiveDate),
+    Yearly(u32, u32),
+}
+
+impl Holiday {
+    fn parse(date: &str) -> Result<Self, String> {
+        if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            return Ok(Holiday::Date(date));
+        }
+
+        // Leap year accepts any month and day
+        NaiveDate::parse_from_str(&format!("2000-{}", date), "%Y-%m-%d")
+            .map(|date| Holiday::Yearly(date.month(), date.day()))
+            .map_err(|_| format!("invalid holiday {}", date))
+    }
+
+    fn matches(&self, time: &DateTime<Tz>) -> bool {
+        match self {
+            Holiday::Date(date) => time.date_naive() == *date,
+            Holiday::Yearly(month, day) => time.month() == *month && time.day() == *day,
+        }
+    }
+}
+
+impl Schedule {
+    pub fn builder() -> ScheduleBuilder {
+        let inner = Schedule::default();
+        ScheduleBuilder { inner }
+    }
+
+    /// Message at this time passes: isn't a holiday, is within allowed and out of denied rules
+    fn matches(&self, time: DateTime<Utc>) -> bool {
+        let time = time.with_timezone(&self.tz);
+
+        !self.holidays.iter().any(|holiday| holiday.matches(&time))
+            && !self.deny.iter().any(|rule| rule.matches(&time))
+            && (self.allow.is_empty() || self.allow.iter().any(|rule| rule.matches(&time)))
+    }
+
+    /// The earliest time from `time` which passes, None when there is none within a month
+    pub(crate) fn next_allowed(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.matches(time) {
+            return Some(time);
+        }
+
+        // Rules have minute precision, so the next minutes are checked
+        let minute = time.timestamp() / 60 * 60;
+        (1..=HORIZON_MINUTES)
+            .filter_map(|n| DateTime::from_timestamp(minute + n * 60, 0))
+            .find(|time| self.matches(*time))
+    }
+}
+
+impl Filter for Schedule {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let time = match self.time {
+            ScheduleTime::Message => {
+                DateTime::from_timestamp(data.input.message().date() as i64, 0).ok_or(())?
+            }
+            ScheduleTime::Now => Utc::now(),
+        };
+
+        match self.matches(time) {
+            true => Ok(()),
+            false => Err(()),
+        }
+    }
+}
+
+pub struct ScheduleBuilder {
+    inner: Schedule,
+}
+
+impl ScheduleBuilder {
+    pub fn tz(&mut self, tz: Option<String>) -> &mut ScheduleBuilder {
+        self.inner.tz = tz
+            .map(|tz| {
+                tz.parse()
+                    .unwrap_or_else(|e| panic!("Invalid Schedule tz: {}", e))
+            })
+            .unwrap_or(Tz::UTC);
+        self
+    }
+
+    pub fn allow(&mut self, allow: Vec<String>) -> &mut ScheduleBuilder {
+        self.inner.allow = parse_rules(allow);
+        self
+    }
+
+    pub fn deny(&mut self, deny: Vec<String>) -> &mut ScheduleBuilder {
+        self.inner.deny = parse_rules(deny);
+        self
+    }
+
+    pub fn holidays(&mut self, holidays: Vec<String>) -> &mut ScheduleBuilder {
+        self.inner.holidays = holidays
+            .iter()
+            .map(|date| {
+                Holiday::parse(date).unwrap_or_else(|e| panic!("Invalid Schedule holiday: {}", e))
+            })
+            .collect();
+        self
+    }
+
+    pub fn time(&mut self, time: ScheduleTime) -> &mut ScheduleBuilder {
+        self.inner.time = time;
+        self
+    }
+
+    pub fn build(&self) -> Schedule {
+        self.inner.clone()
+    }
+}
+
+fn parse_rules(rules: Vec<String>) -> Vec<Rule> {
+    rules
+        .iter()
+        .map(|rule| Rule::parse(rule).unwrap_or_else(|e| panic!("Invalid Schedule rule: {}", e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Schedule;
+    use crate::config::{FilterConf, ScheduleTime};
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::dated_message_example;
+    use chrono::{DateTime, Utc};
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn schedule(allow: Vec<&str>, deny: Vec<&str>, holidays: Vec<&str>) -> Schedule {
+        let strings = |list: Vec<&str>| list.into_iter().map(String::from).collect();
+
+        Schedule::builder()
+            .tz(Some("Europe/Berlin".to_string()))
+            .allow(strings(allow))
+            .deny(strings(deny))
+            .holidays(strings(holidays))
+            .build()
+    }
+
+    #[test]
+    fn test_windows() {
+        let business_hours = schedule(vec!["Mon-Fri 09:00-18:00"], vec![], vec![]);
+        // Wednesday, 08:30 and 09:30 in Berlin (CEST)
+        assert!(!business_hours.matches(at("2025-06-04T06:30:00Z")));
+        assert!(business_hours.matches(at("2025-06-04T07:30:00Z")));
+        assert!(!business_hours.matches(at("2025-06-04T16:00:00Z")));
+        // Saturday
+        assert!(!business_hours.matches(at("2025-06-07T10:00:00Z")));
+
+        let quiet_hours = schedule(vec![], vec!["22:00-07:00", "Sun"], vec![]);
+        assert!(quiet_hours.matches(at("2025-06-04T10:00:00Z")));
+        assert!(!quiet_hours.matches(at("2025-06-04T21:00:00Z")));
+        assert!(!quiet_hours.matches(at("2025-06-05T04:00:00Z")));
+        assert!(!quiet_hours.matches(at("2025-06-08T10:00:00Z")));
+
+        let weekend = schedule(vec!["Fri-Mon"], vec![], vec![]);
+        assert!(weekend.matches(at("2025-06-09T10:00:00Z")));
+        assert!(!weekend.matches(at("2025-06-10T10:00:00Z")));
+    }
+
+    #[test]
+    fn test_windows_after_midnight() {
+        // Friday night goes on Saturday, 01:00 in Berlin
+        let friday_night = schedule(vec!["Fri 22:00-02:00"], vec![], vec![]);
+        assert!(friday_night.matches(at("2025-06-06T20:30:00Z")));
+        assert!(friday_night.matches(at("2025-06-06T23:00:00Z")));
+        assert!(!friday_night.matches(at("2025-06-05T23:00:00Z")));
+
+        // Monday 01:00 is the end of Sunday night, Tuesday 01:00 of Monday night
+        let weekday_nights = schedule(vec!["Mon-Fri 22:00-06:00"], vec![], vec![]);
+        assert!(!weekday_nights.matches(at("2025-06-08T23:00:00Z")));
+        assert!(weekday_nights.matches(at("2025-06-09T23:00:00Z")));
+        assert!(weekday_nights.matches(at("2025-06-07T03:00:00Z")));
+        assert!(!weekday_nights.matches(at("2025-06-07T21:00:00Z")));
+    }
+
+    #[test]
+    fn test_cron() {
+        let cron = schedule(vec!["0-29 9-17 * * 1-5"], vec![], vec![]);
+        assert!(cron.matches(at("2025-06-04T07:15:00Z")));
+        assert!(!cron.matches(at("2025-06-04T07:45:00Z")));
+        assert!(!cron.matches(at("2025-06-07T07:15:00Z")));
+
+        // First day of month or Sunday
+        let cron = schedule(vec!["*/30 * 1 * 7"], vec![], vec![]);
+        assert!(cron.matches(at("2025-06-01T10:30:00Z")));
+        assert!(cron.matches(at("2025-07-01T10:00:00Z")));
+        assert!(cron.matches(at("2025-06-08T10:00:00Z")));
+        assert!(!cron.matches(at("2025-06-08T10:15:00Z")));
+        assert!(!cron.matches(at("2025-06-09T10:00:00Z")));
+    }
+
+    #[test]
+    fn test_holidays() {
+        let schedule = schedule(vec!["Mon-Fri"], vec![], vec!["12-25", "2025-06-04"]);
+        assert!(!schedule.matches(at("2025-12-25T10:00:00Z")));
+        assert!(!schedule.matches(at("2025-06-04T10:00:00Z")));
+        assert!(schedule.matches(at("2026-06-04T10:00:00Z")));
+        // Midnight in Berlin is the next day
+        assert!(!schedule.matches(at("2025-06-03T22:30:00Z")));
+    }
+
+    #[test]
+    fn test_next_allowed() {
+        let business_hours = schedule(vec!["Mon-Fri 09:00-18:00"], vec![], vec![]);
+        let time = at("2025-06-04T10:00:00Z");
+        assert_eq!(Some(time), business_hours.next_allowed(time));
+        // Friday evening is moved to Monday morning
+        assert_eq!(
+            Some(at("2025-06-09T07:00:00Z")),
+            business_hours.next_allowed(at("2025-06-06T16:30:15Z"))
+        );
+
+        let never = schedule(vec![], vec!["Mon-Sun"], vec![]);
+        assert_eq!(None, never.next_allowed(time));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid Schedule rule")]
+    fn test_invalid_rule() {
+        schedule(vec!["Mon-Fri 09:00-25:00"], vec![], vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_message_date() {
+        let filter = FilterType::from(FilterConf::Schedule {
+            tz: None,
+            allow: vec!["Mon-Fri 09:00-18:00".to_string()],
+            deny: vec![],
+            holidays: vec![],
+            time: ScheduleTime::Message,
+        });
+
+        let date = at("2025-06-04T10:00:00Z").timestamp() as i32;
+        let data = DataHub::new(dated_message_example(date));
+        assert_eq!(Ok(()), filter.filter(&data).await);
+
+        let date = at("2025-06-04T20:00:00Z").timestamp() as i32;
+        let data = DataHub::new(dated_message_example(date));
+        assert_eq!(Err(()), filter.filter(&data).await);
+    }
+}
@@ -0,0 +1,355 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::telegram::{client, UsernameIds};
+use rust_tdlib::types::{ChatMemberStatus, GetChatMember, GetUser, MessageSender, UserType};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Administrator status is requested again after this time, so promotions and demotions are noticed
+const ADMINS_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Administrator status with time of request
+type CachedStatus = (bool, Instant);
+
+#[derive(Debug, Default, Clone)]
+pub enum SenderListType {
+    #[default]
+    Allow,
+    Deny,
+}
+
+/// This filter passes/rejects when message sender matches any rule
+#[derive(Debug, Default, Clone)]
+pub struct Sender {
+    list_type: SenderListType,
+    /// Sender user ids
+    users: HashSet<i64>,
+    /// Sender users or chats by @username
+    usernames: UsernameIds,
+    /// Sender chat ids (channel posts and anonymous admins)
+    chats: HashSet<i64>,
+    /// Channel post's or anonymous admin's author signatures
+    signatures: Vec<String>,
+    /// Match any bot
+    bots: bool,
+    /// Match any chat administrator
+    admins: bool,
+    /// Whether user is a bot, by user id
+    known_bots: Arc<Mutex<HashMap<i64, bool>>>,
+    /// Whether sender is administrator with time of request, by (chat id, sender id)
+    known_admins: Arc<Mutex<HashMap<(i64, i64), CachedStatus>>>,
+}
+
+impl Sender {
+    pub fn builder() -> SenderBuilder {
+        let inner = Sender::default();
+        SenderBuilder { inner }
+    }
+
+    /// Check cheap rules first, rules which need requests to Telegram last
+    async fn matches(&self, data: &DataHub) -> bool {
+        let message = data.input.message();
+
+        let (sender_id, is_user) = match message.sender_id() {
+            MessageSender::User(user) => (user.user_id(), true),
+            MessageSender::Chat(chat) => (chat.chat_id(), false),
+            _ => return false,
+        };
+
+        if (is_user && self.users.contains(&sender_id))
+            || (!is_user && self.chats.contains(&sender_id))
+        {
+            return true;
+        }
+
+        let signature = message.author_signature();
+        if !signature.is_empty() && self.signatures.iter().any(|s| s == signature) {
+            return true;
+        }
+
+        if !self.usernames.is_empty() && self.usernames.contains(sender_id).await {
+            return true;
+        }
+
+        if self.bots && is_user && self.is_bot(sender_id).await {
+            return true;
+        }
+
+        self.admins && self.is_admin(data).await
+    }
+
+    async fn is_bot(&self, user_id: i64) -> bool {
+        if let Some(is_bot) = self.known_bots.lock().unwrap().get(&user_id) {
+            return *is_bot;
+        }
+
+        let client = match client() {
+            Some(client) => client,
+            None => return false,
+        };
+
+        match client
+            .get_user(GetUser::builder().user_id(user_id).build())
+            .await
+        {
+            Ok(user) => {
+                let is_bot = matches!(user.type_(), UserType::Bot(_));
+                self.known_bots.lock().unwrap().insert(user_id, is_bot);
+                is_bot
+            }
+            Err(_) => false,
+        }
+    }
+
+    async fn is_admin(&self, data: &DataHub) -> bool {
+        let message = data.input.message();
+
+        // Anonymous admins and channel posts are sent on behalf of the chat itself
+        if let MessageSender::Chat(chat) = message.sender_id() {
+            if chat.chat_id() == message.chat_id() {
+                return true;
+            }
+        }
+
+        let sender_id = match message.sender_id() {
+            MessageSender::User(user) => user.user_id(),
+            MessageSender::Chat(chat) => chat.chat_id(),
+            _ => return false,
+        };
+        let key = (message.chat_id(), sender_id);
+        if let Some((is_admin, at)) = self.known_admins.lock().unwrap().get(&key) {
+            if at.elapsed() < ADMINS_TTL {
+                return *is_admin;
+            }
+        }
+
+        let client = match client() {
+            Some(client) => client,
+            None => return false,
+        };
+
+        let request = GetChatMember::builder()
+            .chat_id(message.chat_id())
+            .member_id(message.sender_id())
+            .build();
+
+        // Failed requests aren't cached, they are made again on the next message
+        match client.get_chat_member(request).await {
+            Ok(member) => {
+                let is_admin = matches!(
+                    member.status(),
+                    ChatMemberStatus::Administrator(_) | ChatMemberStatus::Creator(_)
+                );
+                self.known_admins
+                    .lock()
+                    .unwrap()
+                    .insert(key, (is_admin, Instant::now()));
+                is_admin
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+impl Filter for Sender {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let matches = self.matches(data).await;
+
+        match self.list_type {
+            SenderListType::Allow => matches.then_some(()).ok_or(()),
+            SenderListType::Deny => (!matches).then_some(()).ok_or(()),
+        }
+    }
+}
+
+pub struct SenderBuilder {
+    inner: Sender,
+}
+
+impl SenderBuilder {
+    pub fn list_type(&mut self, list_type: SenderListType) -> &mut SenderBuilder {
+        self.inner.list_type = list_type;
+        self
+    }
+
+    pub fn users(&mut self, users: Vec<i64>) -> &mut SenderBuilder {
+        self.inner.users = users.into_iter().collect();
+        self
+    }
+
+    pub fn usernames(&mut self, usernames: Vec<String>) -> &mut SenderBuilder {
+        self.inner.usernames = UsernameIds::new(usernames);
+        self
+    }
+
+    pub fn chats(&mut self, chats: Vec<i64>) -> &mut SenderBuilder {
+        self.inner.chats = chats.into_iter().collect();
+        self
+    }
+
+    pub fn signatures(&mut self, signatures: Vec<String>) -> &mut SenderBuilder {
+        self.inner.signatures = signatures;
+        self
+    }
+
+    pub fn bots(&mut self, bots: bool) -> &mut SenderBuilder {
+        self.inner.bots = bots;
+        self
+    }
+
+    pub fn admins(&mut self, admins: bool) -> &mut SenderBuilder {
+        self.inner.admins = admins;
+        self
+    }
+
+    pub fn build(&self) -> Sender {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Sender, ADMINS_TTL};
+    use crate::config::{FilterConf, SendersConf};
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{
+        message_example, sender_chat_example, sender_user_example, MessageMock,
+    };
+    use rust_tdlib::types::{Message, UpdateNewMessage};
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_allow_senders() {
+        let user_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let chat_data = DataHub::new(message_example(
+            sender_chat_example(-100),
+            MessageMock::Text(None),
+            false,
+        ));
+
+        let users_filter = FilterType::from(FilterConf::AllowSenders(SendersConf {
+            users: vec![1],
+            ..Default::default()
+        }));
+        let chats_filter = FilterType::from(FilterConf::AllowSenders(SendersConf {
+            chats: vec![-100],
+            ..Default::default()
+        }));
+
+        assert_eq!(Ok(()), users_filter.filter(&user_data).await);
+        assert_eq!(Err(()), users_filter.filter(&chat_data).await);
+
+        assert_eq!(Ok(()), chats_filter.filter(&chat_data).await);
+        assert_eq!(Err(()), chats_filter.filter(&user_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_deny_senders() {
+        let user_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let chat_data = DataHub::new(message_example(
+            sender_chat_example(2),
+            MessageMock::Text(None),
+            false,
+        ));
+
+        let filter = FilterType::from(FilterConf::DenySenders(SendersConf {
+            users: vec![1],
+            ..Default::default()
+        }));
+
+        assert_eq!(Err(()), filter.filter(&user_data).await);
+        assert_eq!(Ok(()), filter.filter(&chat_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_signatures_and_anonymous_admins() {
+        let message = message_example(sender_chat_example(1), MessageMock::Text(None), false);
+        let signed_post = DataHub::new(
+            UpdateNewMessage::builder()
+                .message(
+                    Message::builder()
+                        .chat_id(1)
+                        .sender_id(message.message().sender_id())
+                        .content(message.message().content())
+                        .author_signature("John")
+                        .build(),
+                )
+                .build(),
+        );
+        let user_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+
+        let signatures_filter = FilterType::from(FilterConf::AllowSenders(SendersConf {
+            signatures: vec!["John".to_string()],
+            ..Default::default()
+        }));
+        let admins_filter = FilterType::from(FilterConf::AllowSenders(SendersConf {
+            admins: true,
+            ..Default::default()
+        }));
+
+        assert_eq!(Ok(()), signatures_filter.filter(&signed_post).await);
+        assert_eq!(Err(()), signatures_filter.filter(&user_data).await);
+
+        // Message sent on behalf of the chat itself is anonymous admin's message
+        assert_eq!(Ok(()), admins_filter.filter(&signed_post).await);
+    }
+
+    #[tokio::test]
+    async fn test_cached_admins() {
+        let data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let chat_id = data.input.message().chat_id();
+        let filter = Sender::builder().admins(true).build();
+
+        // No client in tests, so only cached status is known
+        assert_eq!(Err(()), filter.filter(&data).await);
+
+        filter
+            .known_admins
+            .lock()
+            .unwrap()
+            .insert((chat_id, 1), (true, Instant::now()));
+        assert_eq!(Ok(()), filter.filter(&data).await);
+
+        // Expired status is requested again
+        filter
+            .known_admins
+            .lock()
+            .unwrap()
+            .insert((chat_id, 1), (true, Instant::now() - ADMINS_TTL));
+        assert_eq!(Err(()), filter.filter(&data).await);
+    }
+
+    #[tokio::test]
+    async fn test_unresolved_usernames() {
+        let data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+        let filter = Sender::builder()
+            .usernames(vec!["@user".to_string()])
+            .build();
+
+        // Without client usernames aren't resolved, and aren't cached as resolved
+        assert_eq!(Err(()), filter.filter(&data).await);
+        assert_eq!(Err(()), filter.filter(&data).await);
+    }
+}
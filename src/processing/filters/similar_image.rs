@@ -0,0 +1,218 @@
+use crate::config::PhotoSizeConf;
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+use crate::processing::helpers::find_photo_size;
+use crate::processing::telegram::download_file;
+use crate::storage::{blocking, seen_similar};
+use image::imageops::FilterType;
+use image::DynamicImage;
+use rust_tdlib::types::MessageContent;
+
+/// Photo size which is hashed. Thumbnail is enough and fast to download
+const HASHED_PHOTO_SIZE: PhotoSizeConf = PhotoSizeConf::MaxDimension(320);
+
+/// Filter photos similar to recently seen ones, by default ones seen by the same destination chat.
+/// Photo is fingerprinted with 64-bit difference hash (dHash)
+#[derive(Debug, Clone)]
+pub struct SimilarImage {
+    /// Namespace of seen hashes, pipeline name or route when not provided
+    namespace: Option<String>,
+    /// Maximum Hamming distance between hashes of similar images
+    distance: u32,
+    /// Seconds for which hash is kept
+    window: u64,
+    /// Hashes are compared with ones of the same destination chat only
+    per_destination: bool,
+}
+
+impl Default for SimilarImage {
+    fn default() -> Self {
+        SimilarImage {
+            namespace: None,
+            distance: 5,
+            window: 24 * 60 * 60,
+            per_destination: true,
+        }
+    }
+}
+
+impl SimilarImage {
+    pub fn builder() -> SimilarImageBuilder {
+        let inner = SimilarImage::default();
+        SimilarImageBuilder { inner }
+    }
+
+    fn namespace(&self, data: &DataHub) -> String {
+        let namespace = self
+            .namespace
+            .clone()
+            .unwrap_or_else(|| data.default_namespace());
+
+        format!("similar_image/{}", namespace)
+    }
+
+    /// Scope of hashes within namespace: destination chat (when per destination) or whole namespace
+    fn scope(&self, data: &DataHub) -> String {
+        match (self.per_destination, data.destination) {
+            (true, Some(destination)) => format!("{}:", destination),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Difference hash: every bit tells whether pixel is brighter than its right neighbour
+/// in 9x8 grayscale image
+pub(crate) fn dhash(image: &DynamicImage) -> u64 {
+    let pixels = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    (0..8)
+        .flat_map(|y| (0..8).map(move |x| (x, y)))
+        .fold(0, |hash, (x, y)| {
+            let brighter = pixels.get_pixel(x, y)[0] > pixels.get_pixel(x + 1, y)[0];
+            hash << 1 | brighter as u64
+        })
+}
+
+impl Filter for SimilarImage {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let MessageContent::MessagePhoto(message) = data.input.message().content() else {
+            return Ok(());
+        };
+        let Some(photo_size) = find_photo_size(message.photo(), &Some(HASHED_PHOTO_SIZE)) else {
+            return Ok(());
+        };
+
+        // Images which can't be downloaded or decoded pass
+        let Some(path) = download_file(photo_size.photo()).await else {
+            return Ok(());
+        };
+        let hash = match image::open(&path) {
+            Ok(image) => dhash(&image),
+            Err(e) => {
+                eprintln!("Image {} not decoded: {}", path, e);
+                return Ok(());
+            }
+        };
+
+        let namespace = self.namespace(data);
+        let scope = self.scope(data);
+        let (distance, window) = (self.distance, self.window);
+        let related = data.related_ids();
+
+        match blocking(move || seen_similar(&namespace, &scope, hash, distance, window, &related))
+            .await
+        {
+            Ok(true) => Err(()),
+            Ok(false) => Ok(()),
+            Err(e) => {
+                eprintln!("SimilarImage: {}", e);
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct SimilarImageBuilder {
+    inner: SimilarImage,
+}
+
+impl SimilarImageBuilder {
+    pub fn namespace(&mut self, namespace: Option<String>) -> &mut SimilarImageBuilder {
+        self.inner.namespace = namespace;
+        self
+    }
+
+    pub fn distance(&mut self, distance: u32) -> &mut SimilarImageBuilder {
+        self.inner.distance = distance;
+        self
+    }
+
+    pub fn window(&mut self, window: u64) -> &mut SimilarImageBuilder {
+        self.inner.window = window;
+        self
+    }
+
+    pub fn per_destination(&mut self, per_destination: bool) -> &mut SimilarImageBuilder {
+        self.inner.per_destination = per_destination;
+        self
+    }
+
+    pub fn build(&self) -> SimilarImage {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dhash;
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
+    use crate::storage::seen_similar;
+    use image::{imageops, DynamicImage, Rgb, RgbImage};
+
+    fn image_example(brightness: u8, flip: bool) -> DynamicImage {
+        let image = RgbImage::from_fn(90, 60, |x, y| {
+            let value = ((x * 2 + y) % 200) as u8;
+            Rgb([value.saturating_add(brightness), value, (y * 4) as u8])
+        });
+
+        match flip {
+            true => DynamicImage::ImageRgb8(image).fliph(),
+            false => DynamicImage::ImageRgb8(image),
+        }
+    }
+
+    #[test]
+    fn test_dhash() {
+        let original = dhash(&image_example(0, false));
+        let brighter = dhash(&image_example(20, false));
+        let resized =
+            dhash(&image_example(0, false).resize_exact(45, 30, imageops::FilterType::Triangle));
+        let flipped = dhash(&image_example(0, true));
+
+        assert!((original ^ brighter).count_ones() <= 5);
+        assert!((original ^ resized).count_ones() <= 5);
+        assert!((original ^ flipped).count_ones() > 5);
+    }
+
+    #[tokio::test]
+    async fn test_similar_image_without_client() {
+        let filter = FilterType::from(FilterConf::SimilarImage {
+            namespace: None,
+            distance: 5,
+            window: 60,
+            per_destination: true,
+        });
+        let photo = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Photo(None, 100),
+            false,
+        ));
+        let text = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(None),
+            false,
+        ));
+
+        // Photo which can't be downloaded passes
+        assert_eq!(Ok(()), filter.filter(&photo).await);
+        assert_eq!(Ok(()), filter.filter(&text).await);
+    }
+
+    #[test]
+    fn test_seen_similar() {
+        let namespace = "similar_image/test_seen";
+        let hash = dhash(&image_example(0, false));
+        let close = dhash(&image_example(20, false));
+        let other = dhash(&image_example(0, true));
+
+        assert!(!seen_similar(namespace, "-5001:", hash, 5, 60, &[]).unwrap());
+        // Recompressed copy is rejected, other image passes
+        assert!(seen_similar(namespace, "-5001:", close, 5, 60, &[]).unwrap());
+        assert!(!seen_similar(namespace, "-5001:", other, 5, 60, &[]).unwrap());
+        // Another destination hasn't seen it
+        assert!(!seen_similar(namespace, "-5002:", close, 5, 60, &[]).unwrap());
+    }
+}
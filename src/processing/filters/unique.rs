@@ -1,64 +1,308 @@
+use crate::config::UniqueKey;
 use crate::processing::data::DataHub;
 use crate::processing::filter::{Filter, FilterResult};
-use crate::processing::helpers::find_input_message_text;
-use pickledb::{PickleDb, PickleDbDumpPolicy};
-use std::path::Path;
-use std::sync::Mutex;
-
-lazy_static::lazy_static! {
-    static ref STORE: Mutex<PickleDb> = {
-        let path = Path::new("storage/key-value.db");
-
-        Mutex::new(if path.exists() {
-            PickleDb::load_json(path, PickleDbDumpPolicy::AutoDump).expect("DB error")
-        } else {
-            PickleDb::new_json(path, PickleDbDumpPolicy::AutoDump)
-        })
-    };
+use crate::processing::helpers::{
+    find_input_message_file, find_input_message_text, normalize_text,
+};
+use crate::storage::{blocking, now, storage, LEGACY_UNIQUE_NAMESPACE};
+
+/// Filter duplicates, pass unique messages.
+/// Messages are seen within namespace (pipeline name or route by default) and optionally per destination chat
+#[derive(Debug, Clone)]
+pub struct Unique {
+    /// Namespace of seen messages, pipeline name or route when not provided
+    namespace: Option<String>,
+    /// Seconds after which seen message is unique again
+    ttl: Option<u64>,
+    /// Parts of message which make its identity
+    keys: Vec<UniqueKey>,
+    /// Same message may be sent once to every destination
+    per_destination: bool,
+}
+
+impl Default for Unique {
+    fn default() -> Self {
+        Unique {
+            namespace: None,
+            ttl: None,
+            keys: vec![UniqueKey::Text, UniqueKey::File],
+            per_destination: false,
+        }
+    }
 }
 
-/// Filter duplicates, pass unique messages
-#[derive(Debug, Default, Clone)]
-pub struct Unique;
+impl Unique {
+    pub fn builder() -> UniqueBuilder {
+        let inner = Unique::default();
+        UniqueBuilder { inner }
+    }
+
+    /// Digest of message parts chosen by keys. None when message has none of them
+    fn digest(&self, data: &DataHub) -> Option<String> {
+        let message = data.input.message();
+        let parts: Vec<String> = self
+            .keys
+            .iter()
+            .filter_map(|key| match key {
+                UniqueKey::Text => find_input_message_text(message)
+                    .filter(|text| !text.is_empty())
+                    .cloned(),
+                UniqueKey::NormalizedText => find_input_message_text(message)
+                    .map(|text| normalize_text(text))
+                    .filter(|text| !text.is_empty())
+                    .map(|text| format!("normalized_text:{}", text)),
+                UniqueKey::File => find_input_message_file(message, &data.photo_size)
+                    .map(|file| file.remote().unique_id())
+                    .filter(|unique_id| !unique_id.is_empty())
+                    .map(|unique_id| format!("file:{}", unique_id)),
+            })
+            .collect();
+
+        if parts.is_empty() {
+            return None;
+        }
+
+        Some(format!("{:x}", md5::compute(parts.join("\n"))))
+    }
+
+    /// Digest of message text as stored by previous versions: md5 of the text only
+    fn legacy_digest(data: &DataHub) -> Option<String> {
+        find_input_message_text(data.input.message())
+            .filter(|text| !text.is_empty())
+            .map(|text| format!("{:x}", md5::compute(text)))
+    }
+
+    /// Storage namespace of seen messages
+    fn namespace(&self, data: &DataHub) -> String {
+        let namespace = self
+            .namespace
+            .clone()
+            .unwrap_or_else(|| data.default_namespace());
+
+        format!("unique/{}", namespace)
+    }
+
+    /// Storage key of message: destination (when per destination) and digest
+    fn key(&self, data: &DataHub, digest: &str) -> String {
+        match (self.per_destination, data.destination) {
+            (true, Some(destination)) => format!("{}:{}", destination, digest),
+            _ => digest.to_string(),
+        }
+    }
+}
 
 impl Filter for Unique {
     async fn filter(&self, data: &DataHub) -> FilterResult {
-        // TODO: For other messages then text, check uniqueness by file_id
-        // TODO: Multiple unique filters for different pipelines with different PREFIX_KEY
+        let legacy_digest = Self::legacy_digest(data);
+        let digest = self.digest(data);
+        let key = digest.as_ref().map(|digest| self.key(data, digest));
+        let namespace = self.namespace(data);
+        let ttl = self.ttl;
+        let related = data.related_ids();
+
+        let unique = blocking(move || {
+            // Messages seen by previous versions, their digests are migrated as they were
+            if let Some(legacy_digest) = legacy_digest {
+                if let Ok(Some(_)) = storage().get(LEGACY_UNIQUE_NAMESPACE, &legacy_digest) {
+                    return Ok(false);
+                }
+            }
+
+            let Some(key) = key else {
+                return Ok(true);
+            };
 
-        let mut db = STORE.lock().unwrap();
-        if let Some(text) = find_input_message_text(data.input.message()) {
-            let digest = format!("{:x}", md5::compute(text));
+            // Seen message is kept for ttl, then it's unique again
+            storage().compare_and_set(&namespace, &key, None, &now().to_string(), ttl, &related)
+        });
 
-            if db.get::<bool>(&digest).is_some() {
-                return Err(());
+        match unique.await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(()),
+            Err(e) => {
+                eprintln!("Unique: {}", e);
+                Ok(())
             }
-            db.set(&digest, &true).unwrap();
         }
+    }
+}
+
+pub struct UniqueBuilder {
+    inner: Unique,
+}
 
-        Ok(())
+impl UniqueBuilder {
+    pub fn namespace(&mut self, namespace: Option<String>) -> &mut UniqueBuilder {
+        self.inner.namespace = namespace;
+        self
+    }
+
+    pub fn ttl(&mut self, ttl: Option<u64>) -> &mut UniqueBuilder {
+        self.inner.ttl = ttl;
+        self
+    }
+
+    pub fn keys(&mut self, keys: Vec<UniqueKey>) -> &mut UniqueBuilder {
+        self.inner.keys = keys;
+        self
+    }
+
+    pub fn per_destination(&mut self, per_destination: bool) -> &mut UniqueBuilder {
+        self.inner.per_destination = per_destination;
+        self
+    }
+
+    pub fn build(&self) -> Unique {
+        self.inner.clone()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::FilterConf;
+    use crate::config::{FilterConf, UniqueKey};
     use crate::processing::data::DataHub;
     use crate::processing::filter::{Filter, FilterType};
     use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
+    use crate::storage::{storage, LEGACY_UNIQUE_NAMESPACE};
+
+    fn unique_conf(namespace: &str, keys: Vec<UniqueKey>, per_destination: bool) -> FilterConf {
+        FilterConf::Unique {
+            namespace: Some(namespace.to_string()),
+            ttl: None,
+            keys,
+            per_destination,
+        }
+    }
+
+    fn text_data(text: &str) -> DataHub {
+        DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(Some(text.to_string())),
+            false,
+        ))
+    }
 
     #[tokio::test]
     async fn test_unique() {
+        let message_data = text_data("some message");
+
+        let unique_filter = FilterType::from(FilterConf::Unique {
+            namespace: None,
+            ttl: None,
+            keys: vec![UniqueKey::Text, UniqueKey::File],
+            per_destination: false,
+        });
+
+        assert_eq!(Ok(()), unique_filter.filter(&message_data).await);
+        // Second time should not pass
+        assert_eq!(Err(()), unique_filter.filter(&message_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_unique_namespaces() {
+        let message_data = text_data("namespaced message");
+
+        let first = FilterType::from(unique_conf("test_first", vec![UniqueKey::Text], false));
+        let second = FilterType::from(unique_conf("test_second", vec![UniqueKey::Text], false));
+
+        assert_eq!(Ok(()), first.filter(&message_data).await);
+        assert_eq!(Err(()), first.filter(&message_data).await);
+        assert_eq!(Ok(()), second.filter(&message_data).await);
+        assert_eq!(Err(()), second.filter(&message_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_unique_normalized_text() {
+        let filter = FilterType::from(unique_conf(
+            "test_normalized",
+            vec![UniqueKey::NormalizedText],
+            false,
+        ));
+
+        assert_eq!(Ok(()), filter.filter(&text_data("Breaking: news!")).await);
+        assert_eq!(Err(()), filter.filter(&text_data("breaking   NEWS")).await);
+        assert_eq!(Ok(()), filter.filter(&text_data("other news")).await);
+    }
+
+    #[tokio::test]
+    async fn test_unique_per_destination() {
+        let filter = FilterType::from(unique_conf(
+            "test_per_destination",
+            vec![UniqueKey::Text],
+            true,
+        ));
+
+        let mut message_data = text_data("same post");
+        message_data.destination = Some(1);
+        assert_eq!(Ok(()), filter.filter(&message_data).await);
+        assert_eq!(Err(()), filter.filter(&message_data).await);
+
+        message_data.destination = Some(2);
+        assert_eq!(Ok(()), filter.filter(&message_data).await);
+        assert_eq!(Err(()), filter.filter(&message_data).await);
+    }
+
+    #[tokio::test]
+    async fn test_unique_legacy() {
+        let filter = FilterType::from(unique_conf("test_legacy", vec![UniqueKey::Text], false));
+        let digest = format!("{:x}", md5::compute("message seen before migration"));
+        storage()
+            .set(LEGACY_UNIQUE_NAMESPACE, &digest, "1", None, &[])
+            .unwrap();
+
+        assert_eq!(
+            Err(()),
+            filter
+                .filter(&text_data("message seen before migration"))
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unique_legacy_caption() {
+        // Legacy digest is made of caption only, whatever keys are configured
+        let filter = FilterType::from(unique_conf(
+            "test_legacy_caption",
+            vec![UniqueKey::Text, UniqueKey::File],
+            false,
+        ));
+        let digest = format!("{:x}", md5::compute("photo seen before migration"));
+        storage()
+            .set(LEGACY_UNIQUE_NAMESPACE, &digest, "1", None, &[])
+            .unwrap();
+
         let message_data = DataHub::new(message_example(
             sender_user_example(),
-            MessageMock::Text(Some("some message".to_string())),
+            MessageMock::Photo(Some("photo seen before migration".to_string()), 1024),
             false,
         ));
+        assert_eq!(Err(()), filter.filter(&message_data).await);
 
-        let unique_filter = FilterType::from(FilterConf::Unique);
+        let message_data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Photo(Some("photo after migration".to_string()), 1024),
+            false,
+        ));
+        assert_eq!(Ok(()), filter.filter(&message_data).await);
+    }
 
-        let _ = unique_filter.filter(&message_data).await;
-        // Second time should not pass
-        assert_eq!(Err(()), unique_filter.filter(&message_data).await);
+    #[tokio::test]
+    async fn test_unique_unnamed_pipelines() {
+        let filter = FilterType::from(FilterConf::Unique {
+            namespace: None,
+            ttl: None,
+            keys: vec![UniqueKey::Text],
+            per_destination: false,
+        });
+
+        // Pipelines without name have own seen messages per route
+        let mut message_data = text_data("unnamed pipeline message");
+        message_data.pipeline = Some(String::new());
+        message_data.destination = Some(-4001);
+        assert_eq!(Ok(()), filter.filter(&message_data).await);
+        assert_eq!(Err(()), filter.filter(&message_data).await);
+
+        message_data.destination = Some(-4002);
+        assert_eq!(Ok(()), filter.filter(&message_data).await);
     }
 }
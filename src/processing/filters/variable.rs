@@ -0,0 +1,90 @@
+use crate::processing::data::DataHub;
+use crate::processing::filter::{Filter, FilterResult};
+
+/// Filter by variable set by previous filters (e.g. Regexp named captures)
+#[derive(Debug, Default, Clone)]
+pub struct Variable {
+    name: String,
+    /// Expected value. Any value passes when not provided
+    value: Option<String>,
+}
+
+impl Variable {
+    pub fn builder() -> VariableBuilder {
+        let inner = Variable::default();
+        VariableBuilder { inner }
+    }
+}
+
+impl Filter for Variable {
+    async fn filter(&self, data: &DataHub) -> FilterResult {
+        let value = data.variables.get(&self.name).ok_or(())?;
+
+        match &self.value {
+            Some(expected) if !expected.eq(&value) => Err(()),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub struct VariableBuilder {
+    inner: Variable,
+}
+
+impl VariableBuilder {
+    pub fn name(&mut self, name: String) -> &mut VariableBuilder {
+        self.inner.name = name;
+        self
+    }
+
+    pub fn value(&mut self, value: Option<String>) -> &mut VariableBuilder {
+        self.inner.value = value;
+        self
+    }
+
+    pub fn build(&self) -> Variable {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FilterConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::filter::{Filter, FilterType};
+    use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
+
+    #[tokio::test]
+    async fn test_variable() {
+        let data = DataHub::new(message_example(
+            sender_user_example(),
+            MessageMock::Text(Some("Order 42".to_string())),
+            false,
+        ));
+
+        let regexp_filter = FilterType::from(FilterConf::Regexp {
+            exp: r"Order (?P<order>\d+)".to_string(),
+        });
+        let any_filter = FilterType::from(FilterConf::Variable {
+            name: "order".to_string(),
+            value: None,
+        });
+        let value_filter = FilterType::from(FilterConf::Variable {
+            name: "order".to_string(),
+            value: Some("42".to_string()),
+        });
+        let other_value_filter = FilterType::from(FilterConf::Variable {
+            name: "order".to_string(),
+            value: Some("7".to_string()),
+        });
+
+        // Variable isn't set yet
+        assert_eq!(Err(()), any_filter.filter(&data).await);
+
+        assert_eq!(Ok(()), regexp_filter.filter(&data).await);
+        assert_eq!(Some("42".to_string()), data.variables.get("order"));
+        assert_eq!(Ok(()), any_filter.filter(&data).await);
+        assert_eq!(Ok(()), value_filter.filter(&data).await);
+        assert_eq!(Err(()), other_value_filter.filter(&data).await);
+    }
+}
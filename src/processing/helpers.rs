@@ -1,37 +1,296 @@
+use crate::config::PhotoSizeConf;
 use rust_tdlib::types::{
     File, FormattedText, InputFile, InputFileId, InputFileLocal, InputMessageAnimation,
-    InputMessageContent, InputMessageDocument, InputMessagePhoto, InputMessageText,
-    InputMessageVideo, Message, MessageContent,
+    InputMessageAudio, InputMessageContact, InputMessageContent, InputMessageDice,
+    InputMessageDocument, InputMessageLocation, InputMessagePhoto, InputMessagePoll,
+    InputMessageSticker, InputMessageText, InputMessageVenue, InputMessageVideo,
+    InputMessageVideoNote, InputMessageVoiceNote, Message, MessageContent, MessageForwardOrigin,
+    MessageSender, Photo, PhotoSize, PollType, PollTypeRegular, TextEntity, TextEntityType,
 };
+use url::Url;
 
-/// Find file in message content (Video, Animation, Document, Photo).
-/// For photo's this will return first photo size
-pub(crate) fn find_input_message_file(message: &Message) -> Option<&File> {
+/// Find file in message content (Video, Animation, Document, Photo, Audio, VoiceNote, VideoNote, Sticker).
+/// For photo's this will return chosen photo size, the one which is sent
+pub(crate) fn find_input_message_file<'a>(
+    message: &'a Message,
+    photo_size: &Option<PhotoSizeConf>,
+) -> Option<&'a File> {
     match message.content() {
         MessageContent::MessageVideo(m) => Some(m.video().video()),
-        MessageContent::MessagePhoto(m) => Some(m.photo().sizes().first()?.photo()),
+        MessageContent::MessagePhoto(m) => Some(find_photo_size(m.photo(), photo_size)?.photo()),
         MessageContent::MessageAnimation(m) => Some(m.animation().animation()),
         MessageContent::MessageDocument(m) => Some(m.document().document()),
+        MessageContent::MessageAudio(m) => Some(m.audio().audio()),
+        MessageContent::MessageVoiceNote(m) => Some(m.voice_note().voice()),
+        MessageContent::MessageVideoNote(m) => Some(m.video_note().video()),
+        MessageContent::MessageSticker(m) => Some(m.sticker().sticker()),
         _ => None,
     }
 }
 
+/// Choose photo size. The largest one by default, or when chosen type is not found.
+/// When no size fits max dimension, the smallest one is chosen
+pub(crate) fn find_photo_size<'a>(
+    photo: &'a Photo,
+    photo_size: &Option<PhotoSizeConf>,
+) -> Option<&'a PhotoSize> {
+    let sizes = photo.sizes();
+    let largest = || sizes.iter().max_by_key(|size| size.width() * size.height());
+
+    match photo_size {
+        None => largest(),
+        Some(PhotoSizeConf::Type(type_)) => sizes
+            .iter()
+            .find(|size| size.type_().eq(type_))
+            .or_else(largest),
+        Some(PhotoSizeConf::MaxDimension(dimension)) => sizes
+            .iter()
+            .filter(|size| size.width().max(size.height()) <= *dimension)
+            .max_by_key(|size| size.width() * size.height())
+            .or_else(|| sizes.iter().min_by_key(|size| size.width() * size.height())),
+    }
+}
+
 /// Find Text message in message content, for media's return caption
 pub(crate) fn find_input_message_text(message: &Message) -> Option<&String> {
+    find_input_message_formatted_text(message).map(|formatted_text| formatted_text.text())
+}
+
+/// Find Text message with entities in message content, for media's return caption
+pub(crate) fn find_input_message_formatted_text(message: &Message) -> Option<&FormattedText> {
     match message.content() {
-        MessageContent::MessageText(m) => Some(m.text().text()),
-        MessageContent::MessagePhoto(m) => Some(m.caption().text()),
-        MessageContent::MessageAnimation(m) => Some(m.caption().text()),
-        MessageContent::MessageVideo(m) => Some(m.caption().text()),
+        MessageContent::MessageText(m) => Some(m.text()),
+        MessageContent::MessagePhoto(m) => Some(m.caption()),
+        MessageContent::MessageAnimation(m) => Some(m.caption()),
+        MessageContent::MessageVideo(m) => Some(m.caption()),
+        MessageContent::MessageDocument(m) => Some(m.caption()),
+        MessageContent::MessageAudio(m) => Some(m.caption()),
+        MessageContent::MessageVoiceNote(m) => Some(m.caption()),
         _ => None,
     }
 }
 
-/// Find message duration from video/animation medias.
+/// Entity type name without prefix, as in config. (textEntityTypeTextUrl -> TextUrl)
+pub(crate) fn entity_type_name(entity_type: &TextEntityType) -> String {
+    serde_json::to_value(entity_type)
+        .ok()
+        .and_then(|value| value["@type"].as_str().map(str::to_string))
+        .map(|name| name.trim_start_matches("textEntityType").to_string())
+        .unwrap_or_default()
+}
+
+/// Part of the text covered by entity. Entity offsets are in UTF-16 code units
+pub(crate) fn entity_text(text: &str, entity: &TextEntity) -> String {
+    let units: Vec<u16> = text
+        .encode_utf16()
+        .skip(entity.offset().max(0) as usize)
+        .take(entity.length().max(0) as usize)
+        .collect();
+
+    String::from_utf16_lossy(&units)
+}
+
+/// Links of Url and TextUrl entities
+pub(crate) fn find_links(formatted_text: &FormattedText) -> Vec<String> {
+    formatted_text
+        .entities()
+        .iter()
+        .filter_map(|entity| match entity.type_() {
+            TextEntityType::Url(_) => Some(entity_text(formatted_text.text(), entity)),
+            TextEntityType::TextUrl(t) => Some(t.url().clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parse link from text. Links in texts may have no scheme (t.me/joinchat)
+pub(crate) fn parse_link(link: &str) -> Option<Url> {
+    match Url::parse(link) {
+        Ok(url) if url.has_host() => Some(url),
+        _ => Url::parse(&format!("http://{}", link)).ok(),
+    }
+}
+
+/// Whether link's host is the domain or its subdomain
+pub(crate) fn link_has_domain(link: &Url, domain: &str) -> bool {
+    let domain = domain.trim().trim_start_matches("www.").to_lowercase();
+
+    match link.host_str() {
+        Some(host) => {
+            let host = host.to_lowercase();
+            host == domain || host.ends_with(&format!(".{}", domain))
+        }
+        None => false,
+    }
+}
+
+/// Lowercase words of letters and digits, separated with single space
+#[cfg(feature = "storage")]
+pub(crate) fn normalize_text(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Find message duration from video/animation/audio/voice note/video note medias.
 pub(crate) fn find_input_message_duration(message: &Message) -> Option<i32> {
     match message.content() {
         MessageContent::MessageVideo(m) => Some(m.video().duration()),
         MessageContent::MessageAnimation(m) => Some(m.animation().duration()),
+        MessageContent::MessageAudio(m) => Some(m.audio().duration()),
+        MessageContent::MessageVoiceNote(m) => Some(m.voice_note().duration()),
+        MessageContent::MessageVideoNote(m) => Some(m.video_note().duration()),
+        _ => None,
+    }
+}
+
+/// Find MIME type of document/audio/video/animation/voice note.
+pub(crate) fn find_input_message_mime_type(message: &Message) -> Option<&str> {
+    let mime_type = match message.content() {
+        MessageContent::MessageDocument(m) => m.document().mime_type(),
+        MessageContent::MessageAudio(m) => m.audio().mime_type(),
+        MessageContent::MessageVideo(m) => m.video().mime_type(),
+        MessageContent::MessageAnimation(m) => m.animation().mime_type(),
+        MessageContent::MessageVoiceNote(m) => m.voice_note().mime_type(),
+        _ => return None,
+    };
+
+    Some(mime_type.as_str()).filter(|mime_type| !mime_type.is_empty())
+}
+
+/// Find original file name of document/audio/video/animation.
+pub(crate) fn find_input_message_file_name(message: &Message) -> Option<&str> {
+    let file_name = match message.content() {
+        MessageContent::MessageDocument(m) => m.document().file_name(),
+        MessageContent::MessageAudio(m) => m.audio().file_name(),
+        MessageContent::MessageVideo(m) => m.video().file_name(),
+        MessageContent::MessageAnimation(m) => m.animation().file_name(),
+        _ => return None,
+    };
+
+    Some(file_name.as_str()).filter(|file_name| !file_name.is_empty())
+}
+
+/// Id of message sender, user or chat (channel posts, anonymous admins)
+pub(crate) fn find_sender_id(message: &Message) -> i64 {
+    match message.sender_id() {
+        MessageSender::User(user) => user.user_id(),
+        MessageSender::Chat(chat) => chat.chat_id(),
+        _ => message.chat_id(),
+    }
+}
+
+/// Name of message content type, e.g. "photo", "video_note"
+#[cfg(feature = "templating")]
+pub(crate) fn message_type_name(message: &Message) -> String {
+    let name = match message.content() {
+        MessageContent::MessageText(_) => "text",
+        MessageContent::MessagePhoto(_) => "photo",
+        MessageContent::MessageVideo(_) => "video",
+        MessageContent::MessageAnimation(_) => "animation",
+        MessageContent::MessageDocument(_) => "document",
+        MessageContent::MessageAudio(_) => "audio",
+        MessageContent::MessageVoiceNote(_) => "voice_note",
+        MessageContent::MessageVideoNote(_) => "video_note",
+        MessageContent::MessageSticker(_) => "sticker",
+        MessageContent::MessagePoll(_) => "poll",
+        MessageContent::MessageLocation(_) => "location",
+        MessageContent::MessageVenue(_) => "venue",
+        MessageContent::MessageContact(_) => "contact",
+        MessageContent::MessageDice(_) => "dice",
+        _ => "other",
+    };
+
+    name.to_string()
+}
+
+/// Find forwarded message's origin chat/user id. Hidden users and imported messages have no id
+pub(crate) fn find_forward_origin_id(message: &Message) -> Option<i64> {
+    match message.forward_info().as_ref()?.origin() {
+        MessageForwardOrigin::Channel(o) => Some(o.chat_id()),
+        MessageForwardOrigin::Chat(o) => Some(o.sender_chat_id()),
+        MessageForwardOrigin::User(o) => Some(o.sender_user_id()),
+        _ => None,
+    }
+}
+
+/// Same message content with another text/caption. None for content without text
+pub(crate) fn with_message_text(
+    message: &InputMessageContent,
+    text: FormattedText,
+) -> Option<InputMessageContent> {
+    match message {
+        InputMessageContent::InputMessageVideo(m) => Some(InputMessageContent::InputMessageVideo(
+            InputMessageVideo::builder()
+                .video(m.video())
+                .thumbnail(m.thumbnail())
+                .added_sticker_file_ids(m.added_sticker_file_ids().clone())
+                .duration(m.duration())
+                .width(m.width())
+                .height(m.height())
+                .supports_streaming(m.supports_streaming())
+                .caption(text)
+                .ttl(m.ttl())
+                .build(),
+        )),
+        InputMessageContent::InputMessagePhoto(m) => Some(InputMessageContent::InputMessagePhoto(
+            InputMessagePhoto::builder()
+                .photo(m.photo())
+                .thumbnail(m.thumbnail())
+                .added_sticker_file_ids(m.added_sticker_file_ids().clone())
+                .width(m.width())
+                .height(m.height())
+                .caption(text)
+                .ttl(m.ttl())
+                .build(),
+        )),
+        InputMessageContent::InputMessageAnimation(m) => {
+            Some(InputMessageContent::InputMessageAnimation(
+                InputMessageAnimation::builder()
+                    .animation(m.animation())
+                    .thumbnail(m.thumbnail())
+                    .added_sticker_file_ids(m.added_sticker_file_ids().clone())
+                    .duration(m.duration())
+                    .width(m.width())
+                    .height(m.height())
+                    .caption(text)
+                    .build(),
+            ))
+        }
+        InputMessageContent::InputMessageDocument(m) => {
+            Some(InputMessageContent::InputMessageDocument(
+                InputMessageDocument::builder()
+                    .document(m.document())
+                    .thumbnail(m.thumbnail())
+                    .disable_content_type_detection(m.disable_content_type_detection())
+                    .caption(text)
+                    .build(),
+            ))
+        }
+        InputMessageContent::InputMessageAudio(m) => Some(InputMessageContent::InputMessageAudio(
+            InputMessageAudio::builder()
+                .audio(m.audio())
+                .duration(m.duration())
+                .title(m.title())
+                .performer(m.performer())
+                .caption(text)
+                .build(),
+        )),
+        InputMessageContent::InputMessageVoiceNote(m) => {
+            Some(InputMessageContent::InputMessageVoiceNote(
+                InputMessageVoiceNote::builder()
+                    .voice_note(m.voice_note())
+                    .duration(m.duration())
+                    .waveform(m.waveform())
+                    .caption(text)
+                    .build(),
+            ))
+        }
+        InputMessageContent::InputMessageText(_) => Some(InputMessageContent::InputMessageText(
+            InputMessageText::builder().text(text).build(),
+        )),
         _ => None,
     }
 }
@@ -43,6 +302,9 @@ pub(crate) fn find_output_message_text(message: &InputMessageContent) -> Option<
         InputMessageContent::InputMessageVideo(m) => Some(m.caption()),
         InputMessageContent::InputMessagePhoto(m) => Some(m.caption()),
         InputMessageContent::InputMessageAnimation(m) => Some(m.caption()),
+        InputMessageContent::InputMessageDocument(m) => Some(m.caption()),
+        InputMessageContent::InputMessageAudio(m) => Some(m.caption()),
+        InputMessageContent::InputMessageVoiceNote(m) => Some(m.caption()),
         _ => None,
     }
 }
@@ -61,7 +323,10 @@ pub(crate) fn cmp<T: PartialOrd>(operator: &str, left: &T, right: &T) -> bool {
 }
 
 /// Transform input message into output message
-pub(crate) fn transform(input: &Message) -> Result<InputMessageContent, ()> {
+pub(crate) fn transform(
+    input: &Message,
+    photo_size: &Option<PhotoSizeConf>,
+) -> Result<InputMessageContent, ()> {
     match input.content() {
         MessageContent::MessageText(received_message) => Ok(InputMessageContent::InputMessageText(
             InputMessageText::builder()
@@ -114,13 +379,124 @@ pub(crate) fn transform(input: &Message) -> Result<InputMessageContent, ()> {
             ))
         }
 
+        MessageContent::MessageAudio(received_message) => {
+            let audio = received_message.audio();
+
+            Ok(InputMessageContent::InputMessageAudio(
+                InputMessageAudio::builder()
+                    .audio(input_file(audio.audio()))
+                    .duration(audio.duration())
+                    .title(audio.title())
+                    .performer(audio.performer())
+                    .caption(received_message.caption())
+                    .build(),
+            ))
+        }
+
+        MessageContent::MessageVoiceNote(received_message) => {
+            let voice_note = received_message.voice_note();
+
+            Ok(InputMessageContent::InputMessageVoiceNote(
+                InputMessageVoiceNote::builder()
+                    .voice_note(input_file(voice_note.voice()))
+                    .duration(voice_note.duration())
+                    .waveform(voice_note.waveform())
+                    .caption(received_message.caption())
+                    .build(),
+            ))
+        }
+
+        MessageContent::MessageVideoNote(received_message) => {
+            let video_note = received_message.video_note();
+
+            Ok(InputMessageContent::InputMessageVideoNote(
+                InputMessageVideoNote::builder()
+                    .video_note(input_file(video_note.video()))
+                    .duration(video_note.duration())
+                    .length(video_note.length())
+                    .build(),
+            ))
+        }
+
+        MessageContent::MessageSticker(received_message) => {
+            let sticker = received_message.sticker();
+
+            Ok(InputMessageContent::InputMessageSticker(
+                InputMessageSticker::builder()
+                    .sticker(input_file(sticker.sticker()))
+                    .width(sticker.width())
+                    .height(sticker.height())
+                    .emoji(sticker.emoji())
+                    .build(),
+            ))
+        }
+
+        MessageContent::MessagePoll(received_message) => {
+            let poll = received_message.poll();
+            // Correct answer of quiz is unknown until it's answered, such quiz is copied as regular poll
+            let poll_type = match poll.type_() {
+                PollType::Quiz(quiz) if quiz.correct_option_id() < 0 => {
+                    PollType::Regular(PollTypeRegular::builder().build())
+                }
+                poll_type => poll_type.clone(),
+            };
+
+            Ok(InputMessageContent::InputMessagePoll(
+                InputMessagePoll::builder()
+                    .question(poll.question())
+                    .options(
+                        poll.options()
+                            .iter()
+                            .map(|option| option.text().clone())
+                            .collect(),
+                    )
+                    .is_anonymous(poll.is_anonymous())
+                    .type_(poll_type)
+                    .is_closed(poll.is_closed())
+                    .build(),
+            ))
+        }
+
+        // Live location can't be updated by mapper, so it's sent as static location
+        MessageContent::MessageLocation(received_message) => {
+            Ok(InputMessageContent::InputMessageLocation(
+                InputMessageLocation::builder()
+                    .location(received_message.location())
+                    .build(),
+            ))
+        }
+
+        MessageContent::MessageVenue(received_message) => {
+            Ok(InputMessageContent::InputMessageVenue(
+                InputMessageVenue::builder()
+                    .venue(received_message.venue())
+                    .build(),
+            ))
+        }
+
+        MessageContent::MessageContact(received_message) => {
+            Ok(InputMessageContent::InputMessageContact(
+                InputMessageContact::builder()
+                    .contact(received_message.contact())
+                    .build(),
+            ))
+        }
+
+        // Dice value is random, the same emoji is rolled again
+        MessageContent::MessageDice(received_message) => Ok(InputMessageContent::InputMessageDice(
+            InputMessageDice::builder()
+                .emoji(received_message.emoji())
+                .build(),
+        )),
+
         MessageContent::MessagePhoto(received_message) => {
-            let photo_size = received_message.photo().sizes().first().unwrap();
-            let photo = InputFile::Id(InputFileId::builder().id(photo_size.photo().id()).build());
+            let photo_size = find_photo_size(received_message.photo(), photo_size).ok_or(())?;
 
             Ok(InputMessageContent::InputMessagePhoto(
                 InputMessagePhoto::builder()
-                    .photo(photo)
+                    .photo(input_file(photo_size.photo()))
+                    .width(photo_size.width())
+                    .height(photo_size.height())
                     .caption(received_message.caption())
                     .build(),
             ))
@@ -130,6 +506,11 @@ pub(crate) fn transform(input: &Message) -> Result<InputMessageContent, ()> {
     }
 }
 
+/// Already uploaded file, to send it again
+fn input_file(file: &File) -> InputFile {
+    InputFile::Id(InputFileId::builder().id(file.id()).build())
+}
+
 pub(crate) fn transform_output_to_photo_message(
     message: &InputMessageContent,
     path: &str,
@@ -1,12 +1,18 @@
 mod data;
+pub(crate) mod digest;
 mod filter;
 mod filters;
 mod helpers;
+mod overflow;
 mod pipe;
 pub mod pipeline;
 mod pipes;
+pub mod telegram;
+#[cfg(feature = "templating")]
+mod templating;
 #[cfg(test)]
 mod test_helpers;
+mod text_editor;
 
 pub(crate) use self::helpers::transform;
 pub use self::pipeline::Pipeline;
@@ -0,0 +1,267 @@
+use crate::config::OverflowConf;
+use crate::processing::helpers::{find_output_message_text, with_message_text};
+use crate::processing::text_editor::TextEditor;
+use rust_tdlib::types::{FormattedText, InputMessageContent, InputMessageText};
+
+/// Telegram limit of message text length, in UTF-16 code units
+pub const MAX_TEXT_LENGTH: usize = 4096;
+/// Telegram limit of media caption length, in UTF-16 code units
+pub const MAX_CAPTION_LENGTH: usize = 1024;
+
+const ELLIPSIS: &str = "…";
+
+/// Make output messages which fit Telegram limits. Messages after the first one are follow-up text
+/// messages. When the first message is media, they should be sent as replies to it
+pub(crate) fn split_output(
+    output: InputMessageContent,
+    overflow: &OverflowConf,
+) -> Vec<InputMessageContent> {
+    let formatted_text = match find_output_message_text(&output) {
+        Some(formatted_text) => formatted_text.clone(),
+        None => return vec![output],
+    };
+    let is_text = matches!(output, InputMessageContent::InputMessageText(_));
+    let limit = match is_text {
+        true => MAX_TEXT_LENGTH,
+        false => MAX_CAPTION_LENGTH,
+    };
+    let editor = TextEditor::new(&formatted_text);
+    if editor.len() <= limit {
+        return vec![output];
+    }
+
+    let (first, rest) = match overflow {
+        OverflowConf::None => return vec![output],
+        OverflowConf::Truncate => (truncate(&editor, limit), vec![]),
+        OverflowConf::Split => {
+            let first_end = split_position(&editor, 0, limit);
+            let rest = TextEditor::new(&editor.formatted_slice(first_end, editor.len()));
+            (
+                editor.formatted_slice(0, trim_end(&editor, 0, first_end)),
+                split(&rest, MAX_TEXT_LENGTH),
+            )
+        }
+        // Caption is moved to follow-up message. Text messages have nowhere to move, so they are split
+        OverflowConf::Reply if !is_text => {
+            (FormattedText::default(), split(&editor, MAX_TEXT_LENGTH))
+        }
+        OverflowConf::Reply => {
+            let mut chunks = split(&editor, MAX_TEXT_LENGTH);
+            let first = chunks.remove(0);
+            (first, chunks)
+        }
+    };
+
+    std::iter::once(with_message_text(&output, first).unwrap_or(output))
+        .chain(rest.into_iter().map(|text| {
+            InputMessageContent::InputMessageText(InputMessageText::builder().text(text).build())
+        }))
+        .collect()
+}
+
+/// Split text into chunks not longer than limit
+fn split(editor: &TextEditor, limit: usize) -> Vec<FormattedText> {
+    let mut chunks = vec![];
+    let mut start = skip_whitespace(editor, 0);
+
+    while start < editor.len() {
+        let end = split_position(editor, start, limit);
+        chunks.push(editor.formatted_slice(start, trim_end(editor, start, end)));
+        start = skip_whitespace(editor, end);
+    }
+
+    chunks
+}
+
+/// Cut text to limit, including ellipsis
+fn truncate(editor: &TextEditor, limit: usize) -> FormattedText {
+    let ellipsis_length = ELLIPSIS.encode_utf16().count();
+    let end = safe_position(editor, limit - ellipsis_length);
+    let mut truncated = TextEditor::new(&editor.formatted_slice(0, end));
+    truncated.insert(truncated.len(), ELLIPSIS);
+    truncated.build()
+}
+
+/// End of chunk starting at start. Text is split on paragraph, line, sentence or word boundary,
+/// the last one found in the second half of the chunk
+fn split_position(editor: &TextEditor, start: usize, limit: usize) -> usize {
+    let end = start + limit;
+    if end >= editor.len() {
+        return editor.len();
+    }
+
+    let chunk = editor.slice(start, end);
+    let chunk_utf16: Vec<u16> = chunk.encode_utf16().collect();
+    let half = chunk_utf16.len() / 2;
+
+    for separator in ["\n\n", "\n", ". ", "! ", "? ", " "] {
+        let separator: Vec<u16> = separator.encode_utf16().collect();
+        if let Some(position) = chunk_utf16
+            .windows(separator.len())
+            .rposition(|window| window == separator.as_slice())
+            .filter(|position| *position >= half)
+        {
+            // Sentence end stays in the chunk
+            let keep = separator
+                .iter()
+                .take_while(|c| **c != ' ' as u16 && **c != '\n' as u16)
+                .count();
+            return start + position + keep;
+        }
+    }
+
+    safe_position(editor, end)
+}
+
+/// Move position back when it's in the middle of surrogate pair
+fn safe_position(editor: &TextEditor, position: usize) -> usize {
+    let slice = editor.slice(position.saturating_sub(1), position + 1);
+    match slice.encode_utf16().count() == 2 && slice.chars().count() == 1 {
+        true => position - 1,
+        false => position,
+    }
+}
+
+/// Chunk end without trailing whitespaces
+fn trim_end(editor: &TextEditor, start: usize, end: usize) -> usize {
+    let chunk = editor.slice(start, end);
+    end - (chunk.encode_utf16().count() - chunk.trim_end().encode_utf16().count())
+}
+
+/// First position after whitespaces
+fn skip_whitespace(editor: &TextEditor, position: usize) -> usize {
+    let rest = editor.slice(position, editor.len());
+    let trimmed = rest.trim_start();
+    position + rest.encode_utf16().count() - trimmed.encode_utf16().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::OverflowConf;
+    use crate::processing::helpers::find_output_message_text;
+    use crate::processing::overflow::{split_output, MAX_CAPTION_LENGTH, MAX_TEXT_LENGTH};
+    use crate::processing::test_helpers::entity_example;
+    use rust_tdlib::types::{
+        FormattedText, InputFile, InputFileLocal, InputMessageContent, InputMessagePhoto,
+        InputMessageText, TextEntityType, TextEntityTypeBold,
+    };
+
+    fn text_output(text: &str) -> InputMessageContent {
+        // Whole text is bold
+        let length = text.encode_utf16().count() as i32;
+        InputMessageContent::InputMessageText(
+            InputMessageText::builder()
+                .text(
+                    FormattedText::builder()
+                        .text(text)
+                        .entities(vec![entity_example(
+                            0,
+                            length,
+                            TextEntityType::Bold(TextEntityTypeBold::builder().build()),
+                        )])
+                        .build(),
+                )
+                .build(),
+        )
+    }
+
+    fn photo_output(caption: &str) -> InputMessageContent {
+        InputMessageContent::InputMessagePhoto(
+            InputMessagePhoto::builder()
+                .photo(InputFile::Local(
+                    InputFileLocal::builder().path("photo.jpg").build(),
+                ))
+                .width(1280)
+                .height(720)
+                .caption(FormattedText::builder().text(caption).build())
+                .build(),
+        )
+    }
+
+    fn texts(messages: &[InputMessageContent]) -> Vec<FormattedText> {
+        messages
+            .iter()
+            .map(|m| find_output_message_text(m).unwrap().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_split_text() {
+        let paragraph = "word ".repeat(500);
+        let text = format!(
+            "{}\n\n{}\n\n{}",
+            paragraph.trim(),
+            paragraph.trim(),
+            paragraph.trim()
+        );
+
+        let messages = split_output(text_output(&text), &OverflowConf::Split);
+        let texts = texts(&messages);
+
+        // Split on paragraph boundaries
+        assert_eq!(3, texts.len());
+        assert_eq!(paragraph.trim(), texts[0].text());
+        assert_eq!(paragraph.trim(), texts[2].text());
+        // Entity is split across the chunks
+        for text in texts {
+            assert!(text.text().encode_utf16().count() <= MAX_TEXT_LENGTH);
+            assert_eq!(0, text.entities()[0].offset());
+            assert_eq!(
+                text.text().encode_utf16().count() as i32,
+                text.entities()[0].length()
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_caption() {
+        let caption = "Sentence one. ".repeat(100);
+
+        let messages = split_output(photo_output(&caption), &OverflowConf::Split);
+        let texts = texts(&messages);
+
+        match &messages[0] {
+            InputMessageContent::InputMessagePhoto(photo) => {
+                assert_eq!(photo.width(), 1280);
+                assert_eq!(photo.height(), 720);
+            }
+            _ => panic!("expected photo"),
+        }
+        assert!(matches!(
+            messages[1],
+            InputMessageContent::InputMessageText(_)
+        ));
+        assert!(texts[0].text().encode_utf16().count() <= MAX_CAPTION_LENGTH);
+        assert!(texts[0].text().ends_with("one."));
+        assert_eq!(
+            caption.trim(),
+            format!("{} {}", texts[0].text(), texts[1].text())
+        );
+    }
+
+    #[test]
+    fn test_reply_caption() {
+        let caption = "a".repeat(2000);
+
+        let messages = split_output(photo_output(&caption), &OverflowConf::Reply);
+        let texts = texts(&messages);
+
+        assert_eq!(2, messages.len());
+        assert!(texts[0].text().is_empty());
+        assert_eq!(caption, *texts[1].text());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let text = "🔥".repeat(3000);
+
+        let messages = split_output(text_output(&text), &OverflowConf::Truncate);
+        let texts = texts(&messages);
+
+        assert_eq!(1, messages.len());
+        assert_eq!(MAX_TEXT_LENGTH - 1, texts[0].text().encode_utf16().count());
+        assert!(texts[0].text().ends_with("🔥…"));
+        // Ellipsis isn't bold
+        assert_eq!(MAX_TEXT_LENGTH as i32 - 2, texts[0].entities()[0].length());
+    }
+}
@@ -1,7 +1,13 @@
 use crate::config::PipeConf;
 use crate::processing::data::DataHub;
+#[cfg(feature = "schedule")]
+use crate::processing::filters::Schedule as ScheduleRules;
+#[cfg(feature = "templating")]
+use crate::processing::pipes::Format;
+#[cfg(feature = "schedule")]
+use crate::processing::pipes::Schedule;
 use crate::processing::pipes::{
-    Format, Replace, ReplaceRegexp, StaticPhoto, StaticText, Transform,
+    ForwardOrigin, Links, Replace, ReplaceRegexp, StaticPhoto, StaticText, Transform,
 };
 
 /// Pipe trait handles received messages and makes output builder (SendMessageBuilder)
@@ -22,9 +28,16 @@ pub enum PipeType {
     Replace(Replace),
     /// Search and replace texts with regular expression
     ReplaceRegexp(ReplaceRegexp),
+    /// Append origin of forwarded message to text/caption
+    ForwardOrigin(ForwardOrigin),
+    /// Remove and rewrite links of text/caption
+    Links(Links),
     /// Format send message by provided template
     #[cfg(feature = "templating")]
     Format(Format),
+    /// Delay output until time which rules allow
+    #[cfg(feature = "schedule")]
+    Schedule(Schedule),
 }
 
 /// Forward trait calls
@@ -36,8 +49,12 @@ impl Pipe for PipeType {
             Self::StaticPhoto(p) => p.handle(data).await,
             Self::Replace(p) => p.handle(data).await,
             Self::ReplaceRegexp(p) => p.handle(data).await,
+            Self::ForwardOrigin(p) => p.handle(data).await,
+            Self::Links(p) => p.handle(data).await,
             #[cfg(feature = "templating")]
             Self::Format(p) => p.handle(data).await,
+            #[cfg(feature = "schedule")]
+            Self::Schedule(p) => p.handle(data).await,
         }
     }
 }
@@ -48,9 +65,15 @@ impl From<PipeConf> for PipeType {
         match pipe_conf {
             PipeConf::Transform => PipeType::Transform(Transform),
 
-            PipeConf::StaticText { formatted_text } => {
-                PipeType::StaticText(StaticText::builder().text(formatted_text).build())
-            }
+            PipeConf::StaticText {
+                formatted_text,
+                parse_mode,
+            } => PipeType::StaticText(
+                StaticText::builder()
+                    .text(formatted_text)
+                    .parse_mode(parse_mode)
+                    .build(),
+            ),
 
             PipeConf::StaticPhoto { path } => {
                 PipeType::StaticPhoto(StaticPhoto::builder().path(path).build())
@@ -61,9 +84,23 @@ impl From<PipeConf> for PipeType {
             }
 
             #[cfg(feature = "templating")]
-            PipeConf::Format { template } => {
-                PipeType::Format(Format::builder().template(template).build())
-            }
+            PipeConf::Format {
+                template,
+                parse_mode,
+                timezone,
+                date_format,
+                default,
+                syntax,
+            } => PipeType::Format(
+                Format::builder()
+                    .template(template)
+                    .parse_mode(parse_mode)
+                    .timezone(timezone)
+                    .date_format(date_format)
+                    .default(default)
+                    .syntax(syntax)
+                    .build(),
+            ),
 
             PipeConf::ReplaceRegexp {
                 search,
@@ -76,6 +113,45 @@ impl From<PipeConf> for PipeType {
                     .all(all)
                     .build(),
             ),
+
+            PipeConf::ForwardOrigin { template } => {
+                PipeType::ForwardOrigin(ForwardOrigin::builder().template(template).build())
+            }
+
+            PipeConf::Links {
+                remove,
+                replace_domain,
+                add_query,
+                strip_query,
+                unwrap_text_urls,
+            } => PipeType::Links(
+                Links::builder()
+                    .remove(remove)
+                    .replace_domain(replace_domain)
+                    .add_query(add_query)
+                    .strip_query(strip_query)
+                    .unwrap_text_urls(unwrap_text_urls)
+                    .build(),
+            ),
+
+            #[cfg(feature = "schedule")]
+            PipeConf::Schedule {
+                tz,
+                allow,
+                deny,
+                holidays,
+            } => PipeType::Schedule(
+                Schedule::builder()
+                    .rules(
+                        ScheduleRules::builder()
+                            .tz(tz)
+                            .allow(allow)
+                            .deny(deny)
+                            .holidays(holidays)
+                            .build(),
+                    )
+                    .build(),
+            ),
         }
     }
 }
@@ -1,7 +1,12 @@
-use crate::config::PipelineConf;
+#[cfg(feature = "storage")]
+use crate::config::SchedulingConf;
+use crate::config::{OverflowConf, PhotoSizeConf, PipelineConf, ThreadConf};
 use crate::processing::data::DataHub;
+#[cfg(feature = "templating")]
+use crate::processing::digest::Digest;
 use crate::processing::filter::{Filter, FilterType};
 use crate::processing::filters::Incoming;
+use crate::processing::overflow::split_output;
 use crate::processing::pipe::{Pipe, PipeType};
 use crate::processing::pipes::Transform;
 use rust_tdlib::types::{InputMessageContent, UpdateNewMessage};
@@ -9,7 +14,16 @@ use std::error::Error;
 use std::fmt;
 
 /// Return type of pipeline
-pub type PipelineResult = Result<InputMessageContent, PipelineError>;
+pub type PipelineResult = Result<PipelineOutput, PipelineError>;
+
+/// Output of pipeline
+#[derive(Debug)]
+pub struct PipelineOutput {
+    /// Messages after the first one are follow-ups of too long text/caption
+    pub messages: Vec<InputMessageContent>,
+    /// Unix time to send messages at, None to send at once
+    pub send_at: Option<u64>,
+}
 
 #[derive(Debug)]
 pub enum PipelineError {
@@ -33,18 +47,43 @@ impl Error for PipelineError {}
 pub struct Pipeline {
     /// Pipeline name
     pub name: String,
+    /// Source forum topic. Messages from other topics are not handled by this pipeline
+    pub source_thread: Option<ThreadConf>,
+    /// Destination forum topic where messages are sent to
+    pub destination_thread: Option<ThreadConf>,
     /// Filter messages
     pub filters: Vec<FilterType>,
     /// Make send message builder
     pub pipes: Vec<PipeType>,
+    /// Handling of too long text/caption
+    pub overflow: OverflowConf,
+    /// Size of copied photos
+    pub photo_size: Option<PhotoSizeConf>,
+    /// Seconds after source message when output is sent
+    pub delay: Option<u64>,
+    /// Where delayed messages wait for their time
+    #[cfg(feature = "storage")]
+    pub scheduling: SchedulingConf,
+    /// Output is collected into digest instead of being sent
+    #[cfg(feature = "templating")]
+    pub digest: Option<Digest>,
 }
 
 impl Default for Pipeline {
     fn default() -> Self {
         Pipeline {
             name: String::default(),
+            source_thread: None,
+            destination_thread: None,
             filters: vec![FilterType::Incoming(Incoming)],
             pipes: vec![PipeType::Transform(Transform)],
+            overflow: OverflowConf::default(),
+            photo_size: None,
+            delay: None,
+            #[cfg(feature = "storage")]
+            scheduling: SchedulingConf::default(),
+            #[cfg(feature = "templating")]
+            digest: None,
         }
     }
 }
@@ -53,6 +92,15 @@ impl From<PipelineConf> for Pipeline {
     fn from(pipeline_conf: PipelineConf) -> Self {
         let mut pipeline = Self {
             name: pipeline_conf.name,
+            source_thread: pipeline_conf.route.src_thread,
+            destination_thread: pipeline_conf.route.dest_thread,
+            overflow: pipeline_conf.overflow,
+            photo_size: pipeline_conf.photo_size,
+            delay: pipeline_conf.delay,
+            #[cfg(feature = "storage")]
+            scheduling: pipeline_conf.scheduling,
+            #[cfg(feature = "templating")]
+            digest: pipeline_conf.digest.map(Digest::from),
             ..Default::default()
         };
 
@@ -71,8 +119,11 @@ impl From<PipelineConf> for Pipeline {
 }
 
 impl Pipeline {
-    pub async fn handle(&self, input: UpdateNewMessage) -> PipelineResult {
+    pub async fn handle(&self, input: UpdateNewMessage, destination: i64) -> PipelineResult {
         let mut data = DataHub::new(input);
+        data.destination = Some(destination);
+        data.pipeline = Some(self.name.clone());
+        data.photo_size = self.photo_size.clone();
 
         // First filter data
         for filter in &self.filters {
@@ -82,20 +133,37 @@ impl Pipeline {
                 .map_err(|_| PipelineError::FilterError(format!("{:?}", filter)))?;
         }
 
+        if let Some(delay) = self.delay {
+            let date = data.input.message().date() as u64;
+            data.defer_until(date + delay);
+        }
+
         // Then make output (run pipes)
         for pipe in &self.pipes {
             pipe.handle(&mut data).await;
         }
 
-        data.output.ok_or(PipelineError::OutputError(format!(
-            "No output generated in pipeline {}",
-            self.name
-        )))
+        let send_at = data.send_at();
+        data.output
+            .map(|output| PipelineOutput {
+                messages: split_output(output, &self.overflow),
+                send_at,
+            })
+            .ok_or(PipelineError::OutputError(format!(
+                "No output generated in pipeline {}",
+                self.name
+            )))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "schedule")]
+    use crate::config::PipeConf;
+    #[cfg(feature = "schedule")]
+    use crate::processing::pipe::PipeType;
+    #[cfg(feature = "schedule")]
+    use crate::processing::pipes::Transform;
     use crate::processing::test_helpers::{message_example, sender_user_example, MessageMock};
     use crate::processing::Pipeline;
 
@@ -106,7 +174,44 @@ mod tests {
         let success_message =
             message_example(sender_user_example(), MessageMock::Text(None), false);
         let fail_message = message_example(sender_user_example(), MessageMock::Text(None), true);
-        assert!(pipeline.handle(success_message).await.is_ok());
-        assert!(pipeline.handle(fail_message).await.is_err());
+        assert!(pipeline.handle(success_message, 2).await.is_ok());
+        assert!(pipeline.handle(fail_message, 2).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delay() {
+        let pipeline = Pipeline {
+            delay: Some(600),
+            ..Default::default()
+        };
+        let message = message_example(sender_user_example(), MessageMock::Text(None), false);
+
+        let output = pipeline.handle(message, 2).await.unwrap();
+        // Message of example is sent at unix epoch
+        assert_eq!(Some(600), output.send_at);
+    }
+
+    #[cfg(feature = "schedule")]
+    #[tokio::test]
+    async fn test_delay_with_schedule() {
+        // Delay ends on Saturday 2100-01-02 10:00 UTC, schedule allows weekdays only
+        let pipeline = Pipeline {
+            delay: Some(4102567200),
+            pipes: vec![
+                PipeType::Transform(Transform),
+                PipeType::from(PipeConf::Schedule {
+                    tz: None,
+                    allow: vec!["Mon-Fri".to_string()],
+                    deny: vec![],
+                    holidays: vec![],
+                }),
+            ],
+            ..Default::default()
+        };
+        let message = message_example(sender_user_example(), MessageMock::Text(None), false);
+
+        let output = pipeline.handle(message, 2).await.unwrap();
+        // Monday 2100-01-04 00:00 UTC
+        assert_eq!(Some(4102704000), output.send_at);
     }
 }
@@ -1,14 +1,53 @@
+use crate::config::{ParseMode, TemplateSyntax};
 use crate::processing::data::DataHub;
-use crate::processing::helpers::find_output_message_text;
+use crate::processing::helpers::{
+    entity_text, find_input_message_duration, find_input_message_file, find_output_message_text,
+    message_type_name,
+};
 use crate::processing::pipe::Pipe;
-use rust_tdlib::types::FormattedText;
-use std::collections::HashMap;
-use strfmt::strfmt;
+use crate::processing::telegram::{chat_title, message_link, parse_text, sender_name, username};
+use crate::processing::templating::{environment, validate};
+use crate::processing::text_editor::{utf16_offset, TextEditor};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use minijinja::{escape_formatter, Environment, Error, Output, State, Value};
+use rust_tdlib::types::{FormattedText, MessageSender, TextEntityType};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use strfmt::strfmt_map;
+
+/// Placeholder of message in rendered template, replaced with message text and entities after rendering
+const MESSAGE_MARKER: &str = "\u{E000}message\u{E000}";
+
+/// Variables passed to jinja templates as numbers
+const NUMERIC_VARS: [&str; 6] = [
+    "message_id",
+    "source_chat_id",
+    "destination_chat_id",
+    "sender_id",
+    "duration",
+    "file_size",
+];
 
 /// Format send message by provided template
 #[derive(Debug, Default, Clone)]
 pub struct Format {
     template: String,
+    /// Parse template's markdown/HTML into entities. Message is inserted after parsing, so it isn't parsed.
+    /// Jinja template can change message (e.g. by filters), changed message is parsed with the template
+    parse_mode: Option<ParseMode>,
+    /// Timezone of `date` variable, UTC by default
+    timezone: Option<Tz>,
+    /// strftime format of `date` variable
+    date_format: String,
+    /// Value of unknown or unavailable variables
+    default: String,
+    /// Template syntax
+    syntax: TemplateSyntax,
+    /// Jinja templates environment
+    env: Option<Arc<Environment<'static>>>,
+    /// Variables used by Jinja template
+    jinja_vars: HashSet<String>,
 }
 
 impl Format {
@@ -16,21 +55,194 @@ impl Format {
         let inner = Format::default();
         FormatBuilder { inner }
     }
+
+    /// Whether template uses variable, so requests to Telegram are made only when needed
+    fn uses(&self, var: &str) -> bool {
+        match self.syntax {
+            TemplateSyntax::Simple => {
+                self.template
+                    .match_indices(&format!("{{{}", var))
+                    .any(|(start, found)| {
+                        matches!(
+                            self.template[start + found.len()..].chars().next(),
+                            Some('}' | ':')
+                        )
+                    })
+            }
+            TemplateSyntax::Jinja => self.jinja_vars.contains(var),
+        }
+    }
+
+    /// Render simple template. Message is rendered as marker
+    fn render_simple(&self, context_vars: &HashMap<String, String>) -> Result<String, String> {
+        strfmt_map(&self.template, |mut fmt| {
+            let value = match fmt.key {
+                "message" => MESSAGE_MARKER,
+                key => context_vars.get(key).unwrap_or(&self.default),
+            };
+            fmt.str(value)
+        })
+        .map_err(|e| e.to_string())
+    }
+
+    /// Render jinja template. Message is passed as text, so it can be used in conditions and filters,
+    /// message output unchanged is rendered as marker
+    fn render_jinja(
+        &self,
+        context_vars: HashMap<String, String>,
+        formatted_text: &FormattedText,
+    ) -> Result<String, String> {
+        let mut context: HashMap<String, Value> = context_vars
+            .into_iter()
+            .map(|(key, value)| match value.parse::<i64>() {
+                Ok(number) if NUMERIC_VARS.contains(&key.as_str()) => (key, Value::from(number)),
+                _ => (key, Value::from(value)),
+            })
+            .collect();
+
+        let hashtags: Vec<String> = formatted_text
+            .entities()
+            .iter()
+            .filter(|entity| matches!(entity.type_(), TextEntityType::Hashtag(_)))
+            .map(|entity| entity_text(formatted_text.text(), entity))
+            .collect();
+        context.insert("hashtags".to_string(), Value::from(hashtags));
+
+        self.env
+            .as_ref()
+            .unwrap()
+            .render_str(&self.template, context)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Template variables. Unavailable ones (e.g. message link of private chat) are not set
+    async fn context_vars(&self, data: &DataHub) -> HashMap<String, String> {
+        let message = data.input.message();
+        // Variables set by filters and pipes, built-in variables take precedence
+        let mut context_vars: HashMap<String, String> = data.variables.all().into_iter().collect();
+        if let Some(formatted_text) = data.output.as_ref().and_then(find_output_message_text) {
+            context_vars.insert("message".to_string(), formatted_text.text().clone());
+        }
+        context_vars.insert("message_id".to_string(), message.id().to_string());
+        context_vars.insert("source_chat_id".to_string(), message.chat_id().to_string());
+        context_vars.insert("media_type".to_string(), message_type_name(message));
+
+        if self.uses("source_chat") {
+            if let Some(title) = chat_title(message.chat_id()).await {
+                context_vars.insert("source_chat".to_string(), title);
+            }
+        }
+
+        if let Some(destination) = data.destination {
+            context_vars.insert("destination_chat_id".to_string(), destination.to_string());
+            if self.uses("destination_chat") {
+                if let Some(title) = chat_title(destination).await {
+                    context_vars.insert("destination_chat".to_string(), title);
+                }
+            }
+        }
+
+        let sender_id = match message.sender_id() {
+            MessageSender::User(user) => Some(user.user_id()),
+            MessageSender::Chat(chat) => Some(chat.chat_id()),
+            _ => None,
+        };
+        if let Some(sender_id) = sender_id {
+            context_vars.insert("sender_id".to_string(), sender_id.to_string());
+        }
+        if self.uses("sender_name") {
+            if let Some(name) = sender_name(message.sender_id()).await {
+                context_vars.insert("sender_name".to_string(), name);
+            }
+        }
+        if self.uses("sender_username") {
+            if let Some(username) = username(message.sender_id()).await {
+                context_vars.insert("sender_username".to_string(), username);
+            }
+        }
+
+        if self.uses("message_link") {
+            if let Some(link) = message_link(message.chat_id(), message.id()).await {
+                context_vars.insert("message_link".to_string(), link);
+            }
+        }
+
+        if let Some(date) = DateTime::<Utc>::from_timestamp(message.date() as i64, 0) {
+            let date = date.with_timezone(&self.timezone.unwrap_or(Tz::UTC));
+            context_vars.insert(
+                "date".to_string(),
+                date.format(&self.date_format).to_string(),
+            );
+        }
+
+        if let Some(duration) = find_input_message_duration(message) {
+            context_vars.insert("duration".to_string(), duration.to_string());
+        }
+        if let Some(file) = find_input_message_file(message, &data.photo_size) {
+            context_vars.insert("file_size".to_string(), file.size().to_string());
+        }
+
+        context_vars
+    }
+}
+
+/// Output message unchanged by Jinja template as marker, so it's placed with its entities after parsing
+fn message_formatter(out: &mut Output, state: &State, value: &Value) -> Result<(), Error> {
+    let is_message = value.as_str().is_some_and(|text| !text.is_empty())
+        && state.lookup("message").as_ref() == Some(value);
+    if is_message {
+        out.write_str(MESSAGE_MARKER)?;
+        return Ok(());
+    }
+    escape_formatter(out, state, value)
+}
+
+/// Replace markers of rendered template with message and its entities. Entities of template
+/// containing marker (e.g. bold `*{message}*`) are resized to the message
+fn place_message(rendered: &FormattedText, message: &FormattedText) -> FormattedText {
+    let mut editor = TextEditor::new(rendered);
+    let text = editor.text();
+    let positions: Vec<(usize, usize)> = text
+        .match_indices(MESSAGE_MARKER)
+        .map(|(start, found)| {
+            (
+                utf16_offset(&text, start),
+                utf16_offset(&text, start + found.len()),
+            )
+        })
+        .collect();
+
+    // From the end to keep marker offsets
+    for (start, end) in positions.into_iter().rev() {
+        editor.replace(start, end, message.text());
+        editor.add_entities(start, message.entities());
+    }
+
+    editor.build()
 }
 
 impl Pipe for Format {
     async fn handle(&self, data: &mut DataHub) {
         if let Some(m) = &data.output {
             if let Some(formatted_text) = find_output_message_text(m) {
-                // Available context variables: message, todo:source, todo:source_link
-                let mut context_vars: HashMap<String, String> = HashMap::new();
-                context_vars.insert("message".to_string(), formatted_text.text().to_string());
-
-                data.set_output_text(
-                    FormattedText::builder()
-                        .text(strfmt(&self.template, &context_vars).unwrap())
-                        .build(),
-                );
+                let context_vars = self.context_vars(data).await;
+
+                let rendered = match self.syntax {
+                    TemplateSyntax::Simple => self.render_simple(&context_vars),
+                    TemplateSyntax::Jinja => self.render_jinja(context_vars, formatted_text),
+                };
+                let rendered = match rendered {
+                    Ok(rendered) => rendered,
+                    Err(e) => {
+                        eprintln!("Template not formatted: {}", e);
+                        return;
+                    }
+                };
+                let rendered = match &self.parse_mode {
+                    Some(parse_mode) => parse_text(&rendered, parse_mode).await,
+                    None => FormattedText::builder().text(rendered).build(),
+                };
+                data.set_output_text(place_message(&rendered, formatted_text));
             }
         }
     }
@@ -46,17 +258,69 @@ impl FormatBuilder {
         self
     }
 
+    pub fn parse_mode(&mut self, parse_mode: Option<ParseMode>) -> &mut FormatBuilder {
+        self.inner.parse_mode = parse_mode;
+        self
+    }
+
+    pub fn timezone(&mut self, timezone: Option<String>) -> &mut FormatBuilder {
+        self.inner.timezone = timezone.map(|timezone| {
+            timezone
+                .parse()
+                .unwrap_or_else(|e| panic!("Invalid Format timezone: {}", e))
+        });
+        self
+    }
+
+    pub fn date_format(&mut self, date_format: String) -> &mut FormatBuilder {
+        self.inner.date_format = date_format;
+        self
+    }
+
+    pub fn default(&mut self, default: String) -> &mut FormatBuilder {
+        self.inner.default = default;
+        self
+    }
+
+    pub fn syntax(&mut self, syntax: TemplateSyntax) -> &mut FormatBuilder {
+        self.inner.syntax = syntax;
+        self
+    }
+
     pub fn build(&self) -> Format {
-        self.inner.clone()
+        let mut format = self.inner.clone();
+
+        // Jinja template is validated on config load
+        if format.syntax == TemplateSyntax::Jinja {
+            let mut env = environment(format.parse_mode == Some(ParseMode::Html));
+            if let Err(e) = validate(&env, &format.template) {
+                panic!("Invalid Format template: {}", e);
+            }
+            env.set_formatter(message_formatter);
+            format.jinja_vars = env
+                .template_from_str(&format.template)
+                .map(|template| template.undeclared_variables(false))
+                .unwrap_or_default();
+            format.env = Some(Arc::new(env));
+        }
+
+        format
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::PipeConf;
+    use crate::config::{PipeConf, TemplateSyntax};
+    use crate::processing::data::DataHub;
     use crate::processing::pipe::{Pipe, PipeType};
-    use crate::processing::test_helpers::{formatted_text_example, transformed_data_example};
-    use rust_tdlib::types::InputMessageContent;
+    use crate::processing::pipes::format::{place_message, Format, MESSAGE_MARKER};
+    use crate::processing::test_helpers::{
+        entities_message_example, entity_example, formatted_text_example, transformed_data_example,
+    };
+    use rust_tdlib::types::{
+        FormattedText, InputMessageContent, TextEntityType, TextEntityTypeBold,
+        TextEntityTypeHashtag, TextEntityTypeItalic,
+    };
 
     #[tokio::test]
     async fn test_format() {
@@ -64,6 +328,11 @@ mod tests {
         let success_text = formatted_text_example(Some("Start `Original message` End".to_string()));
         let pipe = PipeType::from(PipeConf::Format {
             template: "Start `{message}` End".to_string(),
+            parse_mode: None,
+            timezone: None,
+            date_format: "%Y-%m-%d %H:%M".to_string(),
+            default: String::new(),
+            syntax: TemplateSyntax::Simple,
         });
 
         pipe.handle(&mut data).await;
@@ -75,4 +344,247 @@ mod tests {
 
         assert_eq!(data_text.text(), success_text.text());
     }
+
+    #[tokio::test]
+    async fn test_format_keeps_entities() {
+        // "message" is bold
+        let mut data = DataHub::new(entities_message_example(
+            "Bold message",
+            vec![entity_example(
+                5,
+                7,
+                TextEntityType::Bold(TextEntityTypeBold::builder().build()),
+            )],
+        ));
+        PipeType::from(PipeConf::Transform).handle(&mut data).await;
+        let pipe = PipeType::from(PipeConf::Format {
+            template: "🔥 {message} | {message}".to_string(),
+            parse_mode: None,
+            timezone: None,
+            date_format: "%Y-%m-%d %H:%M".to_string(),
+            default: String::new(),
+            syntax: TemplateSyntax::Simple,
+        });
+
+        pipe.handle(&mut data).await;
+
+        let data_text = match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => formatted_text_example(None),
+        };
+
+        assert_eq!("🔥 Bold message | Bold message", data_text.text());
+        let positions: Vec<(i32, i32)> = data_text
+            .entities()
+            .iter()
+            .map(|entity| (entity.offset(), entity.length()))
+            .collect();
+        assert_eq!(vec![(8, 7), (23, 7)], positions);
+    }
+
+    #[tokio::test]
+    async fn test_format_variables() {
+        let mut data = transformed_data_example(Some("Text".to_string())).await;
+        data.destination = Some(2);
+        let pipe = PipeType::from(PipeConf::Format {
+            template: "{message} #{message_id} {media_type} {source_chat_id}->{destination_chat_id} at {date} {unknown}".to_string(),
+            parse_mode: None,
+            timezone: Some("Asia/Tokyo".to_string()),
+            date_format: "%d.%m %H:%M".to_string(),
+            default: "-".to_string(),
+            syntax: TemplateSyntax::Simple,
+        });
+
+        pipe.handle(&mut data).await;
+
+        let data_text = match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => formatted_text_example(None),
+        };
+
+        // Message date is 0 (1970-01-01 00:00 UTC)
+        assert_eq!("Text #1 text 1->2 at 01.01 09:00 -", data_text.text());
+    }
+
+    #[tokio::test]
+    async fn test_format_jinja() {
+        // "#news" is hashtag, "Big" is bold
+        let mut data = DataHub::new(entities_message_example(
+            "Big #news",
+            vec![
+                entity_example(
+                    0,
+                    3,
+                    TextEntityType::Bold(TextEntityTypeBold::builder().build()),
+                ),
+                entity_example(
+                    4,
+                    5,
+                    TextEntityType::Hashtag(TextEntityTypeHashtag::builder().build()),
+                ),
+            ],
+        ));
+        PipeType::from(PipeConf::Transform).handle(&mut data).await;
+        let pipe = PipeType::from(PipeConf::Format {
+            template: "{% if sender_username %}by @{{ sender_username }} {% endif %}{{ message }} | {{ message | truncate(2) }} | {{ hashtags | join(', ') }} | {{ message_id + 1 }}".to_string(),
+            parse_mode: None,
+            timezone: None,
+            date_format: "%Y-%m-%d %H:%M".to_string(),
+            default: String::new(),
+            syntax: TemplateSyntax::Jinja,
+        });
+
+        pipe.handle(&mut data).await;
+
+        let data_text = match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => formatted_text_example(None),
+        };
+
+        assert_eq!("Big #news | Bi… | #news | 2", data_text.text());
+        // Entities of message rendered unchanged are kept
+        assert_eq!(2, data_text.entities().len());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid Format template")]
+    fn test_format_jinja_invalid() {
+        let _ = PipeType::from(PipeConf::Format {
+            template: "{% if message %}".to_string(),
+            parse_mode: None,
+            timezone: None,
+            date_format: "%Y-%m-%d %H:%M".to_string(),
+            default: String::new(),
+            syntax: TemplateSyntax::Jinja,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_format_pipeline_variables() {
+        let mut data = transformed_data_example(Some("Text".to_string())).await;
+        data.variables.set("order", "42");
+        data.variables.set("message_id", "overridden");
+        let pipe = PipeType::from(PipeConf::Format {
+            template: "{message} #{order} {message_id}".to_string(),
+            parse_mode: None,
+            timezone: None,
+            date_format: "%Y-%m-%d %H:%M".to_string(),
+            default: String::new(),
+            syntax: TemplateSyntax::Simple,
+        });
+
+        pipe.handle(&mut data).await;
+
+        let data_text = match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => formatted_text_example(None),
+        };
+
+        // Built-in variables take precedence
+        assert_eq!("Text #42 1", data_text.text());
+    }
+
+    #[test]
+    fn test_place_message_in_parsed_entity() {
+        // "Hi *{message}*!" parsed as markdown, marker is bold
+        let rendered = FormattedText::builder()
+            .text(format!("Hi {}!", MESSAGE_MARKER))
+            .entities(vec![entity_example(
+                3,
+                MESSAGE_MARKER.encode_utf16().count() as i32,
+                TextEntityType::Bold(TextEntityTypeBold::builder().build()),
+            )])
+            .build();
+        let message = FormattedText::builder()
+            .text("Big news")
+            .entities(vec![entity_example(
+                0,
+                3,
+                TextEntityType::Italic(TextEntityTypeItalic::builder().build()),
+            )])
+            .build();
+
+        let placed = place_message(&rendered, &message);
+
+        assert_eq!("Hi Big news!", placed.text());
+        let entities: Vec<(i32, i32, bool)> = placed
+            .entities()
+            .iter()
+            .map(|e| {
+                let bold = matches!(e.type_(), TextEntityType::Bold(_));
+                (e.offset(), e.length(), bold)
+            })
+            .collect();
+        assert_eq!(vec![(3, 8, true), (3, 3, false)], entities);
+    }
+
+    #[tokio::test]
+    async fn test_format_jinja_uses() {
+        let format = Format::builder()
+            .template("{{ sender_name }}|{% if message_link %}{{ message_link }}{% else %}no link{% endif %}".to_string())
+            .syntax(TemplateSyntax::Jinja)
+            .build();
+        assert!(format.uses("sender_name"));
+        assert!(format.uses("message_link"));
+        assert!(!format.uses("source_chat"));
+
+        let simple = Format::builder()
+            .template("{sender_name:>10} {source_chat_id}".to_string())
+            .build();
+        assert!(simple.uses("sender_name"));
+        assert!(!simple.uses("source_chat"));
+
+        // No client in tests, so sender name and link are unavailable
+        let mut data = transformed_data_example(Some("Text".to_string())).await;
+        format.handle(&mut data).await;
+        let data_text = match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => formatted_text_example(None),
+        };
+        assert_eq!("|no link", data_text.text());
+    }
+
+    #[tokio::test]
+    async fn test_format_jinja_short_message() {
+        let mut data = DataHub::new(entities_message_example(
+            "ok",
+            vec![entity_example(
+                0,
+                2,
+                TextEntityType::Bold(TextEntityTypeBold::builder().build()),
+            )],
+        ));
+        PipeType::from(PipeConf::Transform).handle(&mut data).await;
+        let pipe = PipeType::from(PipeConf::Format {
+            template: "ok: {{ message }}".to_string(),
+            parse_mode: None,
+            timezone: None,
+            date_format: "%Y-%m-%d %H:%M".to_string(),
+            default: String::new(),
+            syntax: TemplateSyntax::Jinja,
+        });
+
+        pipe.handle(&mut data).await;
+
+        let data_text = match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => formatted_text_example(None),
+        };
+        assert_eq!("ok: ok", data_text.text());
+        // Entities are placed at the message only, not at the same text of template
+        let positions: Vec<(i32, i32)> = data_text
+            .entities()
+            .iter()
+            .map(|entity| (entity.offset(), entity.length()))
+            .collect();
+        assert_eq!(vec![(4, 2)], positions);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid Format timezone")]
+    fn test_format_invalid_timezone() {
+        let _ = Format::builder()
+            .timezone(Some("Mars/Olympus".to_string()))
+            .build();
+    }
 }
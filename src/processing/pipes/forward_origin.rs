@@ -0,0 +1,116 @@
+use crate::processing::data::DataHub;
+use crate::processing::helpers::find_output_message_text;
+use crate::processing::pipe::Pipe;
+use crate::processing::telegram::{chat_title, user_name};
+use crate::processing::text_editor::TextEditor;
+use rust_tdlib::types::MessageForwardOrigin;
+
+/// Appends line with the origin of forwarded message to text/caption
+#[derive(Debug, Default, Clone)]
+pub struct ForwardOrigin {
+    /// Line template, {origin} is replaced with origin chat/user name
+    template: String,
+}
+
+impl ForwardOrigin {
+    pub fn builder() -> ForwardOriginBuilder {
+        let inner = ForwardOrigin::default();
+        ForwardOriginBuilder { inner }
+    }
+}
+
+/// Name of forwarded message's origin chat/user. Falls back to id when chat is unknown
+async fn origin_name(origin: &MessageForwardOrigin) -> Option<String> {
+    let (name, id) = match origin {
+        MessageForwardOrigin::Channel(o) => (chat_title(o.chat_id()).await, o.chat_id()),
+        MessageForwardOrigin::Chat(o) => (chat_title(o.sender_chat_id()).await, o.sender_chat_id()),
+        MessageForwardOrigin::User(o) => (user_name(o.sender_user_id()).await, o.sender_user_id()),
+        MessageForwardOrigin::HiddenUser(o) => return Some(o.sender_name().clone()),
+        MessageForwardOrigin::MessageImport(o) => return Some(o.sender_name().clone()),
+        _ => return None,
+    };
+
+    Some(name.unwrap_or_else(|| id.to_string()))
+}
+
+impl Pipe for ForwardOrigin {
+    async fn handle(&self, data: &mut DataHub) {
+        let forward_info = match data.input.message().forward_info() {
+            Some(forward_info) => forward_info,
+            None => return,
+        };
+
+        let origin = match origin_name(forward_info.origin()).await {
+            Some(origin) => origin,
+            None => return,
+        };
+
+        if let Some(m) = &data.output {
+            if let Some(formatted_text) = find_output_message_text(m) {
+                let line = self.template.replace("{origin}", &origin);
+                let mut editor = TextEditor::new(formatted_text);
+                let line = match editor.len() {
+                    0 => line,
+                    _ => format!("\n\n{}", line),
+                };
+                editor.insert(editor.len(), &line);
+
+                data.set_output_text(editor.build());
+            }
+        }
+    }
+}
+
+pub struct ForwardOriginBuilder {
+    inner: ForwardOrigin,
+}
+
+impl ForwardOriginBuilder {
+    pub fn template(&mut self, template: String) -> &mut ForwardOriginBuilder {
+        self.inner.template = template;
+        self
+    }
+
+    pub fn build(&self) -> ForwardOrigin {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::PipeConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::pipe::{Pipe, PipeType};
+    use crate::processing::test_helpers::{formatted_text_example, forwarded_message_example};
+    use rust_tdlib::types::{
+        InputMessageContent, MessageForwardOrigin, MessageForwardOriginHiddenUser,
+    };
+
+    #[tokio::test]
+    async fn test_forward_origin() {
+        let mut data = DataHub::new(forwarded_message_example(
+            MessageForwardOrigin::HiddenUser(
+                MessageForwardOriginHiddenUser::builder()
+                    .sender_name("Someone")
+                    .build(),
+            ),
+            0,
+        ));
+        PipeType::from(PipeConf::Transform).handle(&mut data).await;
+
+        let success_text =
+            formatted_text_example(Some("example\n\nOriginally from Someone".to_string()));
+        let pipe = PipeType::from(PipeConf::ForwardOrigin {
+            template: "Originally from {origin}".to_string(),
+        });
+
+        pipe.handle(&mut data).await;
+
+        let data_text = match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => formatted_text_example(None),
+        };
+
+        assert_eq!(data_text.text(), success_text.text());
+    }
+}
@@ -0,0 +1,377 @@
+use crate::processing::data::DataHub;
+use crate::processing::helpers::{find_output_message_text, link_has_domain, parse_link};
+use crate::processing::pipe::Pipe;
+use crate::processing::text_editor::TextEditor;
+use rust_tdlib::types::{TextEntity, TextEntityType, TextEntityTypeTextUrl, TextEntityTypeUrl};
+use std::collections::BTreeMap;
+
+/// Remove and rewrite links (Url and TextUrl entities) of text/caption
+#[derive(Debug, Default, Clone)]
+pub struct Links {
+    /// Remove links with these domains. Url links are removed from text, TextUrl links become plain text
+    remove: Vec<String>,
+    /// Replace domain (key) with another one (value)
+    replace_domain: BTreeMap<String, String>,
+    /// Add or overwrite query parameters
+    add_query: BTreeMap<String, String>,
+    /// Remove query parameters, "*" removes all
+    strip_query: Vec<String>,
+    /// Show hidden TextUrl links in text, after the link's text
+    unwrap_text_urls: bool,
+}
+
+impl Links {
+    pub fn builder() -> LinksBuilder {
+        let inner = Links::default();
+        LinksBuilder { inner }
+    }
+
+    fn should_remove(&self, link: &str) -> bool {
+        parse_link(link).is_some_and(|url| {
+            self.remove
+                .iter()
+                .any(|domain| link_has_domain(&url, domain))
+        })
+    }
+
+    /// Apply domain and query rules. Unchanged links are returned as they are
+    fn rewrite(&self, link: &str) -> String {
+        let mut url = match parse_link(link) {
+            Some(url) => url,
+            None => return link.to_string(),
+        };
+        let mut changed = false;
+
+        if let Some(to) = self
+            .replace_domain
+            .iter()
+            .find_map(|(from, to)| link_has_domain(&url, from).then_some(to))
+        {
+            changed |= url.set_host(Some(to)).is_ok();
+        }
+
+        if !self.strip_query.is_empty() || !self.add_query.is_empty() {
+            let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+            let mut new_query: Vec<(String, String)> = query
+                .iter()
+                .filter(|(key, _)| {
+                    !self.strip_query.iter().any(|s| s == "*" || s == key)
+                        && !self.add_query.contains_key(key)
+                })
+                .cloned()
+                .collect();
+            new_query.extend(
+                self.add_query
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone())),
+            );
+
+            if new_query != query {
+                changed = true;
+                if new_query.is_empty() {
+                    url.set_query(None);
+                } else {
+                    url.query_pairs_mut().clear().extend_pairs(new_query);
+                }
+            }
+        }
+
+        if !changed {
+            return link.to_string();
+        }
+
+        let mut new_link = url.to_string();
+        // Keep links without scheme and trailing slash as they were written
+        if !link.contains("://") {
+            new_link = new_link.trim_start_matches("http://").to_string();
+        }
+        if url.path() == "/" && url.query().is_none() && !link.ends_with('/') {
+            new_link = new_link.trim_end_matches('/').to_string();
+        }
+
+        new_link
+    }
+}
+
+/// Range of removed link with spaces around it, so words around the link stay separated by one space
+fn removal_range(editor: &TextEditor, start: usize, end: usize) -> (usize, usize) {
+    let is_space = |position: usize| editor.slice(position, position + 1) == " ";
+
+    let mut before = start;
+    while before > 0 && is_space(before - 1) {
+        before -= 1;
+    }
+    let mut after = end;
+    while after < editor.len() && is_space(after) {
+        after += 1;
+    }
+
+    let line_start = before == 0 || editor.slice(before - 1, before) == "\n";
+    match (before < start, after > end) {
+        // Between words or at line start, spaces after the link go with it
+        (true, true) => (start, after),
+        (false, true) if line_start => (start, after),
+        // Before punctuation or at line end, spaces before the link go with it
+        (true, false) => (before, end),
+        _ => (start, end),
+    }
+}
+
+impl Pipe for Links {
+    async fn handle(&self, data: &mut DataHub) {
+        if let Some(m) = &data.output {
+            if let Some(formatted_text) = find_output_message_text(m) {
+                let mut editor = TextEditor::new(formatted_text);
+                let mut changed = false;
+
+                // From the end of the text, so editing a link doesn't move links which are not processed yet
+                let mut links: Vec<TextEntity> = formatted_text
+                    .entities()
+                    .iter()
+                    .filter(|e| {
+                        matches!(
+                            e.type_(),
+                            TextEntityType::Url(_) | TextEntityType::TextUrl(_)
+                        )
+                    })
+                    .cloned()
+                    .collect();
+                links.sort_by_key(|entity| -entity.offset());
+
+                for link in links {
+                    let start = link.offset() as usize;
+                    let end = start + link.length() as usize;
+                    let index = match editor.entities().iter().position(|e| {
+                        e.offset() == link.offset()
+                            && e.length() == link.length()
+                            && matches!(
+                                e.type_(),
+                                TextEntityType::Url(_) | TextEntityType::TextUrl(_)
+                            )
+                    }) {
+                        Some(index) => index,
+                        None => continue,
+                    };
+
+                    match link.type_() {
+                        TextEntityType::Url(_) => {
+                            let url = editor.slice(start, end);
+                            if self.should_remove(&url) {
+                                let (start, end) = removal_range(&editor, start, end);
+                                editor.replace(start, end, "");
+                                changed = true;
+                            } else {
+                                let new_url = self.rewrite(&url);
+                                if new_url != url {
+                                    editor.replace(start, end, &new_url);
+                                    changed = true;
+                                }
+                            }
+                        }
+                        TextEntityType::TextUrl(text_url) => {
+                            if self.should_remove(text_url.url()) {
+                                editor.remove_entity(index);
+                                changed = true;
+                                continue;
+                            }
+
+                            let new_url = self.rewrite(text_url.url());
+                            if self.unwrap_text_urls {
+                                changed = true;
+                                editor.remove_entity(index);
+                                editor.insert(end, &format!(" ({})", new_url));
+                                editor.push_entity(
+                                    TextEntity::builder()
+                                        .offset(end as i32 + 2)
+                                        .length(new_url.encode_utf16().count() as i32)
+                                        .type_(TextEntityType::Url(
+                                            TextEntityTypeUrl::builder().build(),
+                                        ))
+                                        .build(),
+                                );
+                            } else if new_url != *text_url.url() {
+                                changed = true;
+                                editor.set_entity(
+                                    index,
+                                    TextEntity::builder()
+                                        .offset(link.offset())
+                                        .length(link.length())
+                                        .type_(TextEntityType::TextUrl(
+                                            TextEntityTypeTextUrl::builder().url(new_url).build(),
+                                        ))
+                                        .build(),
+                                );
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+
+                if changed {
+                    data.set_output_text(editor.build());
+                }
+            }
+        }
+    }
+}
+
+pub struct LinksBuilder {
+    inner: Links,
+}
+
+impl LinksBuilder {
+    pub fn remove(&mut self, remove: Vec<String>) -> &mut LinksBuilder {
+        self.inner.remove = remove;
+        self
+    }
+
+    pub fn replace_domain(
+        &mut self,
+        replace_domain: BTreeMap<String, String>,
+    ) -> &mut LinksBuilder {
+        self.inner.replace_domain = replace_domain;
+        self
+    }
+
+    pub fn add_query(&mut self, add_query: BTreeMap<String, String>) -> &mut LinksBuilder {
+        self.inner.add_query = add_query;
+        self
+    }
+
+    pub fn strip_query(&mut self, strip_query: Vec<String>) -> &mut LinksBuilder {
+        self.inner.strip_query = strip_query;
+        self
+    }
+
+    pub fn unwrap_text_urls(&mut self, unwrap_text_urls: bool) -> &mut LinksBuilder {
+        self.inner.unwrap_text_urls = unwrap_text_urls;
+        self
+    }
+
+    pub fn build(&self) -> Links {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::PipeConf;
+    use crate::processing::data::DataHub;
+    use crate::processing::pipe::{Pipe, PipeType};
+    use crate::processing::test_helpers::{entities_message_example, entity_example};
+    use rust_tdlib::types::{
+        FormattedText, InputMessageContent, TextEntityType, TextEntityTypeBold,
+        TextEntityTypeTextUrl, TextEntityTypeUrl,
+    };
+    use std::collections::BTreeMap;
+
+    fn url_entity(offset: i32, length: i32) -> rust_tdlib::types::TextEntity {
+        entity_example(
+            offset,
+            length,
+            TextEntityType::Url(TextEntityTypeUrl::builder().build()),
+        )
+    }
+
+    async fn handle(
+        pipe: PipeConf,
+        text: &str,
+        entities: Vec<rust_tdlib::types::TextEntity>,
+    ) -> FormattedText {
+        let mut data = DataHub::new(entities_message_example(text, entities));
+        PipeType::from(PipeConf::Transform).handle(&mut data).await;
+        PipeType::from(pipe).handle(&mut data).await;
+
+        match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => FormattedText::default(),
+        }
+    }
+
+    fn links_conf() -> PipeConf {
+        PipeConf::Links {
+            remove: vec!["t.me".to_string()],
+            replace_domain: BTreeMap::from([("example.com".to_string(), "mirror.org".to_string())]),
+            add_query: BTreeMap::from([("utm_source".to_string(), "relay".to_string())]),
+            strip_query: vec!["ref".to_string()],
+            unwrap_text_urls: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_links_url() {
+        // "Join t.me/chat or https://example.com/a?ref=1 now", last word is bold
+        let text = handle(
+            links_conf(),
+            "Join t.me/chat or https://example.com/a?ref=1 now",
+            vec![
+                url_entity(5, 9),
+                url_entity(18, 27),
+                entity_example(
+                    46,
+                    3,
+                    TextEntityType::Bold(TextEntityTypeBold::builder().build()),
+                ),
+            ],
+        )
+        .await;
+
+        let new_link = "https://mirror.org/a?utm_source=relay";
+        assert_eq!(format!("Join or {} now", new_link), *text.text());
+        assert_eq!(2, text.entities().len());
+        assert_eq!(
+            (8, new_link.len() as i32),
+            (text.entities()[0].offset(), text.entities()[0].length())
+        );
+        // Bold is moved with the text
+        assert_eq!(
+            (9 + new_link.len() as i32, 3),
+            (text.entities()[1].offset(), text.entities()[1].length())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_links_remove_spaces() {
+        let text = handle(
+            links_conf(),
+            "t.me/a first\nsee t.me/b. Then t.me/c",
+            vec![url_entity(0, 6), url_entity(17, 6), url_entity(30, 6)],
+        )
+        .await;
+
+        assert_eq!("first\nsee. Then", text.text());
+    }
+
+    #[tokio::test]
+    async fn test_links_unwrap_text_url() {
+        let pipe = PipeConf::Links {
+            remove: vec![],
+            replace_domain: BTreeMap::new(),
+            add_query: BTreeMap::new(),
+            strip_query: vec![],
+            unwrap_text_urls: true,
+        };
+        let text = handle(
+            pipe,
+            "read this",
+            vec![entity_example(
+                5,
+                4,
+                TextEntityType::TextUrl(
+                    TextEntityTypeTextUrl::builder()
+                        .url("https://example.com")
+                        .build(),
+                ),
+            )],
+        )
+        .await;
+
+        assert_eq!("read this (https://example.com)", text.text());
+        assert_eq!(1, text.entities().len());
+        assert_eq!(
+            (11, 19),
+            (text.entities()[0].offset(), text.entities()[0].length())
+        );
+        assert!(matches!(text.entities()[0].type_(), TextEntityType::Url(_)));
+    }
+}
@@ -1,13 +1,21 @@
 #[cfg(feature = "templating")]
 pub mod format;
+pub mod forward_origin;
+pub mod links;
 pub mod replace;
 pub mod replace_regexp;
+#[cfg(feature = "schedule")]
+pub mod schedule;
 pub mod statics;
 pub mod transform;
 
 #[cfg(feature = "templating")]
 pub(crate) use format::Format;
+pub(crate) use forward_origin::ForwardOrigin;
+pub(crate) use links::Links;
 pub(crate) use replace::Replace;
 pub(crate) use replace_regexp::ReplaceRegexp;
+#[cfg(feature = "schedule")]
+pub(crate) use schedule::Schedule;
 pub(crate) use statics::{StaticPhoto, StaticText};
 pub(crate) use transform::Transform;
@@ -1,7 +1,7 @@
 use crate::processing::data::DataHub;
 use crate::processing::helpers::find_output_message_text;
 use crate::processing::pipe::Pipe;
-use rust_tdlib::types::FormattedText;
+use crate::processing::text_editor::TextEditor;
 
 /// Search and replace texts
 #[derive(Debug, Default, Clone)]
@@ -21,20 +21,21 @@ impl Pipe for Replace {
     async fn handle(&self, data: &mut DataHub) {
         if let Some(m) = &data.output {
             if let Some(formatted_text) = find_output_message_text(m) {
-                let mut replaced_text = formatted_text.text().to_owned();
-                for search in &self.search {
-                    replaced_text = replaced_text.replace(search, &self.replace);
+                let mut editor = TextEditor::new(formatted_text);
+                for search in self.search.iter().filter(|search| !search.is_empty()) {
+                    let matches = editor
+                        .text()
+                        .match_indices(search.as_str())
+                        .map(|(start, found)| (start..start + found.len(), self.replace.clone()))
+                        .collect();
+                    editor.replace_matches(matches);
                 }
 
-                if formatted_text.text().eq(&replaced_text) {
+                let new_formatted_text = editor.build();
+                if formatted_text.text().eq(new_formatted_text.text()) {
                     return;
                 }
 
-                let new_formatted_text = FormattedText::builder()
-                    .text(replaced_text)
-                    .entities(formatted_text.entities().clone())
-                    .build();
-
                 data.set_output_text(new_formatted_text);
             }
         }
@@ -64,9 +65,12 @@ impl ReplaceBuilder {
 #[cfg(test)]
 mod tests {
     use crate::config::PipeConf;
+    use crate::processing::data::DataHub;
     use crate::processing::pipe::{Pipe, PipeType};
-    use crate::processing::test_helpers::{formatted_text_example, transformed_data_example};
-    use rust_tdlib::types::InputMessageContent;
+    use crate::processing::test_helpers::{
+        entities_message_example, entity_example, formatted_text_example, transformed_data_example,
+    };
+    use rust_tdlib::types::{InputMessageContent, TextEntityType, TextEntityTypeBold};
 
     #[tokio::test]
     async fn test_replace() {
@@ -105,4 +109,33 @@ mod tests {
 
         assert_eq!(data_text.text(), success_text.text());
     }
+
+    #[tokio::test]
+    async fn test_replace_keeps_entities() {
+        // "world" is bold
+        let mut data = DataHub::new(entities_message_example(
+            "Hi big world",
+            vec![entity_example(
+                7,
+                5,
+                TextEntityType::Bold(TextEntityTypeBold::builder().build()),
+            )],
+        ));
+        PipeType::from(PipeConf::Transform).handle(&mut data).await;
+        let pipe = PipeType::from(PipeConf::Replace {
+            search: vec!["big".to_string()],
+            replace: "🌍".to_string(),
+        });
+
+        pipe.handle(&mut data).await;
+
+        let data_text = match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => formatted_text_example(None),
+        };
+
+        assert_eq!("Hi 🌍 world", data_text.text());
+        assert_eq!(6, data_text.entities()[0].offset());
+        assert_eq!(5, data_text.entities()[0].length());
+    }
 }
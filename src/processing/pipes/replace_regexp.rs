@@ -1,8 +1,8 @@
 use crate::processing::data::DataHub;
 use crate::processing::helpers::find_output_message_text;
 use crate::processing::pipe::Pipe;
+use crate::processing::text_editor::TextEditor;
 use regex::Regex;
-use rust_tdlib::types::FormattedText;
 
 /// Search and replace texts with regular expression
 #[derive(Debug, Default, Clone)]
@@ -24,33 +24,29 @@ impl Pipe for ReplaceRegexp {
     async fn handle(&self, data: &mut DataHub) {
         if let Some(m) = &data.output {
             if let Some(formatted_text) = find_output_message_text(m) {
-                let mut replaced_text = formatted_text.text().to_owned();
-
-                if self.all {
-                    replaced_text = self
-                        .search_pattern
-                        .as_ref()
-                        .unwrap()
-                        .replace_all(&replaced_text, &self.replace)
-                        .to_string();
-                } else {
-                    replaced_text = self
-                        .search_pattern
-                        .as_ref()
-                        .unwrap()
-                        .replace(&replaced_text, &self.replace)
-                        .to_string();
-                }
-
-                if formatted_text.text().eq(&replaced_text) {
+                let mut editor = TextEditor::new(formatted_text);
+                let text = editor.text();
+
+                // Same as Regex::replace/replace_all, but replaced one by one to keep entities
+                let matches = self
+                    .search_pattern
+                    .as_ref()
+                    .unwrap()
+                    .captures_iter(&text)
+                    .take(if self.all { usize::MAX } else { 1 })
+                    .map(|captures| {
+                        let mut replacement = String::new();
+                        captures.expand(&self.replace, &mut replacement);
+                        (captures.get(0).unwrap().range(), replacement)
+                    })
+                    .collect();
+                editor.replace_matches(matches);
+
+                let new_formatted_text = editor.build();
+                if formatted_text.text().eq(new_formatted_text.text()) {
                     return;
                 }
 
-                let new_formatted_text = FormattedText::builder()
-                    .text(replaced_text)
-                    .entities(formatted_text.entities().clone())
-                    .build();
-
                 data.set_output_text(new_formatted_text);
             }
         }
@@ -86,9 +82,12 @@ impl ReplaceRegexpBuilder {
 #[cfg(test)]
 mod tests {
     use crate::config::PipeConf;
+    use crate::processing::data::DataHub;
     use crate::processing::pipe::{Pipe, PipeType};
-    use crate::processing::test_helpers::{formatted_text_example, transformed_data_example};
-    use rust_tdlib::types::InputMessageContent;
+    use crate::processing::test_helpers::{
+        entities_message_example, entity_example, formatted_text_example, transformed_data_example,
+    };
+    use rust_tdlib::types::{InputMessageContent, TextEntityType, TextEntityTypeBold};
 
     #[tokio::test]
     async fn test_replace_regexp_all() {
@@ -130,4 +129,34 @@ mod tests {
 
         assert_eq!(data_text.text(), success_text.text());
     }
+
+    #[tokio::test]
+    async fn test_replace_regexp_keeps_entities() {
+        // "2024" is bold
+        let mut data = DataHub::new(entities_message_example(
+            "Date 2024-01-31 in 2024",
+            vec![entity_example(
+                19,
+                4,
+                TextEntityType::Bold(TextEntityTypeBold::builder().build()),
+            )],
+        ));
+        PipeType::from(PipeConf::Transform).handle(&mut data).await;
+        let pipe = PipeType::from(PipeConf::ReplaceRegexp {
+            search: r"(\d{4})-(\d{2})-(\d{2})".to_string(),
+            replace: "$3.$2".to_string(),
+            all: true,
+        });
+
+        pipe.handle(&mut data).await;
+
+        let data_text = match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => formatted_text_example(None),
+        };
+
+        assert_eq!("Date 31.01 in 2024", data_text.text());
+        assert_eq!(14, data_text.entities()[0].offset());
+        assert_eq!(4, data_text.entities()[0].length());
+    }
 }
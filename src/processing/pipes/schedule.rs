@@ -0,0 +1,118 @@
+use crate::processing::data::DataHub;
+use crate::processing::filters::Schedule as ScheduleRules;
+use crate::processing::pipe::Pipe;
+use chrono::{DateTime, Utc};
+
+/// Delay output until the earliest time which rules allow, e.g. the end of quiet hours.
+/// Output deferred by pipeline delay or filters is sent at the first allowed time after it
+#[derive(Debug, Default, Clone)]
+pub struct Schedule {
+    rules: ScheduleRules,
+}
+
+impl Schedule {
+    pub fn builder() -> ScheduleBuilder {
+        let inner = Schedule::default();
+        ScheduleBuilder { inner }
+    }
+}
+
+impl Pipe for Schedule {
+    async fn handle(&self, data: &mut DataHub) {
+        let now = Utc::now();
+        let start = data
+            .send_at()
+            .and_then(|send_at| DateTime::from_timestamp(send_at as i64, 0))
+            .map_or(now, |send_at| send_at.max(now));
+
+        match self.rules.next_allowed(start) {
+            Some(time) if time > start => data.defer_until(time.timestamp() as u64),
+            Some(_) => {}
+            None => println!("Schedule: no allowed time within a month, message is sent at once"),
+        }
+    }
+}
+
+pub struct ScheduleBuilder {
+    inner: Schedule,
+}
+
+impl ScheduleBuilder {
+    pub fn rules(&mut self, rules: ScheduleRules) -> &mut ScheduleBuilder {
+        self.inner.rules = rules;
+        self
+    }
+
+    pub fn build(&self) -> Schedule {
+        self.inner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::PipeConf;
+    use crate::processing::pipe::{Pipe, PipeType};
+    use crate::processing::test_helpers::transformed_data_example;
+    use chrono::{Days, Utc};
+
+    fn schedule_conf(holidays: Vec<String>) -> PipeConf {
+        PipeConf::Schedule {
+            tz: None,
+            allow: vec!["Mon-Sun".to_string()],
+            deny: vec![],
+            holidays,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_allowed_now() {
+        let mut data = transformed_data_example(None).await;
+        PipeType::from(schedule_conf(vec![]))
+            .handle(&mut data)
+            .await;
+
+        assert_eq!(None, data.send_at());
+    }
+
+    #[tokio::test]
+    async fn test_delayed() {
+        let today = Utc::now().date_naive();
+        let mut data = transformed_data_example(None).await;
+        PipeType::from(schedule_conf(vec![today.format("%Y-%m-%d").to_string()]))
+            .handle(&mut data)
+            .await;
+
+        // Holiday is over at midnight
+        let tomorrow = today.checked_add_days(Days::new(1)).unwrap();
+        let midnight = tomorrow.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as u64;
+        assert_eq!(Some(midnight), data.send_at());
+    }
+
+    #[tokio::test]
+    async fn test_delayed_into_holiday() {
+        let tomorrow = Utc::now()
+            .date_naive()
+            .checked_add_days(Days::new(1))
+            .unwrap();
+        let mut data = transformed_data_example(None).await;
+        // Already deferred into the holiday, e.g. by pipeline delay
+        data.defer_until(
+            tomorrow
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_utc()
+                .timestamp() as u64,
+        );
+        PipeType::from(schedule_conf(vec![tomorrow.format("%Y-%m-%d").to_string()]))
+            .handle(&mut data)
+            .await;
+
+        let after_holiday = tomorrow.checked_add_days(Days::new(1)).unwrap();
+        let midnight = after_holiday
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp() as u64;
+        assert_eq!(Some(midnight), data.send_at());
+    }
+}
@@ -1,12 +1,16 @@
+use crate::config::ParseMode;
 use crate::processing::data::DataHub;
 use crate::processing::helpers::transform_output_to_photo_message;
 use crate::processing::pipe::Pipe;
+use crate::processing::telegram::parse_text;
 use rust_tdlib::types::FormattedText;
 
 /// Sets static text on send message. On media content this will set "caption", otherwise "text"
 #[derive(Debug, Default, Clone)]
 pub struct StaticText {
     formatted_text: FormattedText,
+    /// Parse text's markdown/HTML into entities
+    parse_mode: Option<ParseMode>,
 }
 
 impl StaticText {
@@ -18,7 +22,12 @@ impl StaticText {
 
 impl Pipe for StaticText {
     async fn handle(&self, data: &mut DataHub) {
-        data.set_output_text(self.formatted_text.clone());
+        let formatted_text = match &self.parse_mode {
+            Some(parse_mode) => parse_text(self.formatted_text.text(), parse_mode).await,
+            None => self.formatted_text.clone(),
+        };
+
+        data.set_output_text(formatted_text);
     }
 }
 
@@ -32,6 +41,11 @@ impl StaticTextBuilder {
         self
     }
 
+    pub fn parse_mode(&mut self, parse_mode: Option<ParseMode>) -> &mut StaticTextBuilder {
+        self.inner.parse_mode = parse_mode;
+        self
+    }
+
     pub fn build(&self) -> StaticText {
         self.inner.clone()
     }
@@ -76,7 +90,7 @@ impl StaticPhotoBuilder {
 
 #[cfg(test)]
 mod tests {
-    use crate::config::PipeConf;
+    use crate::config::{ParseMode, PipeConf};
     use crate::processing::pipe::{Pipe, PipeType};
     use crate::processing::test_helpers::{formatted_text_example, transformed_data_example};
     use rust_tdlib::types::InputMessageContent;
@@ -87,6 +101,7 @@ mod tests {
         let success_formatted_text = formatted_text_example(Some("Test Text".to_string()));
         let pipe = PipeType::from(PipeConf::StaticText {
             formatted_text: success_formatted_text.clone(),
+            parse_mode: None,
         });
         pipe.handle(&mut data).await;
 
@@ -98,6 +113,25 @@ mod tests {
         assert_eq!(success_formatted_text.text(), data_text.text());
     }
 
+    #[tokio::test]
+    async fn test_static_text_parse_mode_without_client() {
+        let mut data = transformed_data_example(None).await;
+        let pipe = PipeType::from(PipeConf::StaticText {
+            formatted_text: formatted_text_example(Some("*Test* Text".to_string())),
+            parse_mode: Some(ParseMode::Markdown),
+        });
+        pipe.handle(&mut data).await;
+
+        let data_text = match data.output {
+            Some(InputMessageContent::InputMessageText(m)) => m.text().clone(),
+            _ => formatted_text_example(None),
+        };
+
+        // Text can't be parsed without Telegram client, it's kept as it is
+        assert_eq!("*Test* Text", data_text.text());
+        assert!(data_text.entities().is_empty());
+    }
+
     #[tokio::test]
     async fn test_static_photo() {
         let mut data = transformed_data_example(Some("Something".to_string())).await;
@@ -9,7 +9,7 @@ pub struct Transform;
 impl Pipe for Transform {
     async fn handle(&self, data: &mut DataHub) {
         // All type of messages (text, video, photo, animation, etc...)
-        if let Ok(new_message) = transform(data.input.message()) {
+        if let Ok(new_message) = transform(data.input.message(), &data.photo_size) {
             data.output = Some(new_message);
         }
     }
@@ -17,7 +17,14 @@ impl Pipe for Transform {
 
 #[cfg(test)]
 mod tests {
-    use crate::processing::test_helpers::transformed_data_example;
+    use crate::config::{PhotoSizeConf, PipeConf};
+    use crate::processing::data::DataHub;
+    use crate::processing::helpers::find_output_message_text;
+    use crate::processing::pipe::{Pipe, PipeType};
+    use crate::processing::test_helpers::{
+        message_example, sender_user_example, transformed_data_example, MessageMock,
+    };
+    use rust_tdlib::types::InputMessageContent;
 
     #[tokio::test]
     async fn test_transform() {
@@ -25,4 +32,95 @@ mod tests {
 
         assert!(data.output.is_some());
     }
+
+    #[tokio::test]
+    async fn test_transform_photo_size() {
+        let cases = vec![
+            (None, 800),
+            (Some(PhotoSizeConf::Type("m".to_string())), 320),
+            (Some(PhotoSizeConf::Type("w".to_string())), 800),
+            (Some(PhotoSizeConf::MaxDimension(500)), 320),
+            (Some(PhotoSizeConf::MaxDimension(50)), 90),
+        ];
+
+        for (photo_size, width) in cases {
+            let mut data = DataHub::new(message_example(
+                sender_user_example(),
+                MessageMock::PhotoSizes(vec![("s", 90, 10), ("x", 800, 1000), ("m", 320, 100)]),
+                false,
+            ));
+            data.photo_size = photo_size;
+            PipeType::from(PipeConf::Transform).handle(&mut data).await;
+
+            match data.output.as_ref().unwrap() {
+                InputMessageContent::InputMessagePhoto(m) => assert_eq!(width, m.width()),
+                _ => panic!("Photo expected"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transform_content_types() {
+        let mocks = vec![
+            MessageMock::Audio(Some("Audio".to_string()), 10, 100),
+            MessageMock::Voice(Some("Voice".to_string()), 10, 100),
+            MessageMock::Sticker(100),
+            MessageMock::Poll(
+                "Question".to_string(),
+                vec!["Yes".to_string(), "No".to_string()],
+            ),
+            MessageMock::Location(1.0, 2.0),
+            MessageMock::Document(Some("Document".to_string()), 100),
+            MessageMock::VideoNote(10, 100),
+            MessageMock::Venue("Venue"),
+            MessageMock::Contact("+100"),
+            MessageMock::Dice("🎲"),
+        ];
+
+        for mock in mocks {
+            let mut data = DataHub::new(message_example(sender_user_example(), mock, false));
+            PipeType::from(PipeConf::Transform).handle(&mut data).await;
+
+            match data.output.as_ref().unwrap() {
+                InputMessageContent::InputMessageAudio(m) => {
+                    assert_eq!("Audio", m.caption().text())
+                }
+                InputMessageContent::InputMessageVoiceNote(m) => {
+                    assert_eq!("Voice", m.caption().text())
+                }
+                InputMessageContent::InputMessagePoll(m) => {
+                    assert_eq!("Question", m.question());
+                    assert_eq!(2, m.options().len());
+                }
+                InputMessageContent::InputMessageLocation(m) => {
+                    assert_eq!(2.0, m.location().longitude())
+                }
+                InputMessageContent::InputMessageSticker(_) => (),
+                InputMessageContent::InputMessageDocument(m) => {
+                    assert_eq!("Document", m.caption().text())
+                }
+                InputMessageContent::InputMessageVideoNote(m) => assert_eq!(10, m.duration()),
+                InputMessageContent::InputMessageVenue(m) => {
+                    assert_eq!("Venue", m.venue().title())
+                }
+                InputMessageContent::InputMessageContact(m) => {
+                    assert_eq!("+100", m.contact().phone_number())
+                }
+                InputMessageContent::InputMessageDice(m) => assert_eq!("🎲", m.emoji()),
+                output => panic!("Unexpected output {:?}", output),
+            }
+
+            // Captions can be changed by pipes
+            if find_output_message_text(data.output.as_ref().unwrap()).is_some() {
+                PipeType::from(PipeConf::Replace {
+                    search: vec!["o".to_string()],
+                    replace: "0".to_string(),
+                })
+                .handle(&mut data)
+                .await;
+                let text = find_output_message_text(data.output.as_ref().unwrap()).unwrap();
+                assert!(!text.text().contains('o'));
+            }
+        }
+    }
 }
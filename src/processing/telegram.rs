@@ -0,0 +1,255 @@
+use crate::config::ParseMode;
+use colored::Colorize;
+use rust_tdlib::client::tdlib_client::TdJson;
+use rust_tdlib::client::Client;
+#[cfg(feature = "templating")]
+use rust_tdlib::types::{ChatType, GetMessageLink, GetSupergroup, MessageSender};
+#[cfg(feature = "similar_image")]
+use rust_tdlib::types::{DownloadFile, File};
+use rust_tdlib::types::{
+    FormattedText, GetChat, GetUser, ParseTextEntities, SearchPublicChat, TextParseMode,
+    TextParseModeHTML, TextParseModeMarkdown,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+lazy_static::lazy_static! {
+    static ref CLIENT: RwLock<Option<Client<TdJson>>> = RwLock::new(None);
+    static ref CHAT_TITLES: RwLock<HashMap<i64, String>> = RwLock::new(HashMap::new());
+}
+
+/// Share authorized client with filters and pipes which need to make requests to Telegram
+pub fn set_client(client: Client<TdJson>) {
+    *CLIENT.write().unwrap() = Some(client);
+}
+
+/// Share titles of loaded (source and destination) chats
+pub fn set_chat_titles(chat_titles: HashMap<i64, String>) {
+    *CHAT_TITLES.write().unwrap() = chat_titles;
+}
+
+/// Authorized client. None until application is authorized (and in tests)
+pub(crate) fn client() -> Option<Client<TdJson>> {
+    CLIENT.read().unwrap().clone()
+}
+
+/// Usernames which failed to resolve (e.g. before client is ready) are resolved again after this time
+const USERNAMES_RETRY: Duration = Duration::from_secs(60);
+
+/// Resolved ids, whether all usernames were resolved and time of resolution
+type Resolved = (HashSet<i64>, bool, Instant);
+
+/// Ids of @usernames, resolved on first use and again while some of them fail
+#[derive(Debug, Default, Clone)]
+pub(crate) struct UsernameIds {
+    usernames: Vec<String>,
+    resolved: Arc<tokio::sync::Mutex<Option<Resolved>>>,
+}
+
+impl UsernameIds {
+    pub fn new(usernames: Vec<String>) -> Self {
+        UsernameIds {
+            usernames,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.usernames.is_empty()
+    }
+
+    /// Whether id belongs to any of usernames
+    pub async fn contains(&self, id: i64) -> bool {
+        if self.usernames.is_empty() {
+            return false;
+        }
+        let mut resolved = self.resolved.lock().await;
+
+        let resolve = match resolved.as_ref() {
+            Some((_, complete, at)) => !complete && at.elapsed() >= USERNAMES_RETRY,
+            None => true,
+        };
+        if resolve {
+            let (ids, complete) = resolve_usernames(&self.usernames).await;
+            *resolved = Some((ids, complete, Instant::now()));
+        }
+
+        resolved
+            .as_ref()
+            .is_some_and(|(ids, _, _)| ids.contains(&id))
+    }
+}
+
+/// Resolve @usernames into user/chat ids. Returns ids and whether all usernames were resolved,
+/// usernames which failed (or all of them when there is no client yet) are skipped
+async fn resolve_usernames(usernames: &[String]) -> (HashSet<i64>, bool) {
+    let mut ids = HashSet::new();
+
+    let Some(client) = client() else {
+        return (ids, false);
+    };
+
+    let mut complete = true;
+    for username in usernames {
+        let request = SearchPublicChat::builder()
+            .username(username.trim_start_matches('@'))
+            .build();
+
+        match client.search_public_chat(request).await {
+            Ok(chat) => {
+                ids.insert(chat.id());
+            }
+            Err(e) => {
+                println!("{} {} {}", "Username not resolved :".red(), username, e);
+                complete = false;
+            }
+        }
+    }
+
+    (ids, complete)
+}
+
+/// Chat title by id. Loaded chats are taken from index
+pub(crate) async fn chat_title(chat_id: i64) -> Option<String> {
+    if let Some(title) = CHAT_TITLES.read().unwrap().get(&chat_id) {
+        return Some(title.clone());
+    }
+
+    let chat = client()?
+        .get_chat(GetChat::builder().chat_id(chat_id).build())
+        .await
+        .ok()?;
+
+    Some(chat.title().to_string())
+}
+
+/// Local path of file, downloads it when needed
+#[cfg(feature = "similar_image")]
+pub(crate) async fn download_file(file: &File) -> Option<String> {
+    if file.local().is_downloading_completed() {
+        return Some(file.local().path().to_string());
+    }
+
+    let request = DownloadFile::builder()
+        .file_id(file.id())
+        .priority(1)
+        .synchronous(true)
+        .build();
+
+    match client()?.download_file(request).await {
+        Ok(file) if file.local().is_downloading_completed() => {
+            Some(file.local().path().to_string())
+        }
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("File {} not downloaded: {}", file.id(), e);
+            None
+        }
+    }
+}
+
+/// User's full name by id
+pub(crate) async fn user_name(user_id: i64) -> Option<String> {
+    let user = client()?
+        .get_user(GetUser::builder().user_id(user_id).build())
+        .await
+        .ok()?;
+
+    Some(
+        format!("{} {}", user.first_name(), user.last_name())
+            .trim()
+            .to_string(),
+    )
+}
+
+/// Parse markdown/HTML formatted text into text with entities.
+/// Text is kept as it is when it can't be parsed (or there is no client)
+pub(crate) async fn parse_text(text: &str, parse_mode: &ParseMode) -> FormattedText {
+    let parse_mode = match parse_mode {
+        ParseMode::Markdown => {
+            TextParseMode::Markdown(TextParseModeMarkdown::builder().version(1).build())
+        }
+        ParseMode::MarkdownV2 => {
+            TextParseMode::Markdown(TextParseModeMarkdown::builder().version(2).build())
+        }
+        ParseMode::Html => TextParseMode::HTML(TextParseModeHTML::builder().build()),
+    };
+    let request = ParseTextEntities::builder()
+        .text(text)
+        .parse_mode(parse_mode)
+        .build();
+
+    match client() {
+        Some(client) => match client.parse_text_entities(request).await {
+            Ok(formatted_text) => formatted_text,
+            Err(e) => {
+                eprintln!("Text not parsed: {}", e);
+                FormattedText::builder().text(text).build()
+            }
+        },
+        None => FormattedText::builder().text(text).build(),
+    }
+}
+
+/// Public @username (without @) of user or supergroup/channel
+#[cfg(feature = "templating")]
+pub(crate) async fn username(sender: &MessageSender) -> Option<String> {
+    let client = client()?;
+    let username = match sender {
+        MessageSender::User(user) => client
+            .get_user(GetUser::builder().user_id(user.user_id()).build())
+            .await
+            .ok()?
+            .username()
+            .clone(),
+        MessageSender::Chat(chat) => {
+            let chat = client
+                .get_chat(GetChat::builder().chat_id(chat.chat_id()).build())
+                .await
+                .ok()?;
+            match chat.type_() {
+                ChatType::Supergroup(supergroup) => client
+                    .get_supergroup(
+                        GetSupergroup::builder()
+                            .supergroup_id(supergroup.supergroup_id())
+                            .build(),
+                    )
+                    .await
+                    .ok()?
+                    .username()
+                    .clone(),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    (!username.is_empty()).then_some(username)
+}
+
+/// Display name of user or chat
+#[cfg(feature = "templating")]
+pub(crate) async fn sender_name(sender: &MessageSender) -> Option<String> {
+    match sender {
+        MessageSender::User(user) => user_name(user.user_id()).await,
+        MessageSender::Chat(chat) => chat_title(chat.chat_id()).await,
+        _ => None,
+    }
+}
+
+/// Link to the message. Available for messages of supergroups and channels
+#[cfg(feature = "templating")]
+pub(crate) async fn message_link(chat_id: i64, message_id: i64) -> Option<String> {
+    let link = client()?
+        .get_message_link(
+            GetMessageLink::builder()
+                .chat_id(chat_id)
+                .message_id(message_id)
+                .build(),
+        )
+        .await
+        .ok()?;
+
+    Some(link.link().clone())
+}
@@ -0,0 +1,77 @@
+use minijinja::{AutoEscape, Environment, Error};
+
+/// Limit of template instructions per render, so broken template can't hang message handling
+const TEMPLATE_FUEL: u64 = 100_000;
+
+/// Characters escaped in Telegram "MarkdownV2"
+const MARKDOWN_SPECIAL_CHARS: &str = "_*[]()~`>#+-=|{}.!\\";
+
+/// Jinja-like templates environment. There is no loader, so templates can't include files
+pub(crate) fn environment(escape_html: bool) -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_fuel(Some(TEMPLATE_FUEL));
+    if escape_html {
+        env.set_auto_escape_callback(|_| AutoEscape::Html);
+    }
+    env.add_filter("truncate", truncate);
+    env.add_filter("escape_markdown", escape_markdown);
+    env
+}
+
+/// Check template syntax. Used on config load
+pub(crate) fn validate(env: &Environment<'static>, template: &str) -> Result<(), Error> {
+    env.template_from_str(template).map(|_| ())
+}
+
+/// Cut text to length (in characters) and append `end` ("…" by default)
+fn truncate(value: String, length: usize, end: Option<String>) -> String {
+    if value.chars().count() <= length {
+        return value;
+    }
+
+    let mut truncated: String = value.chars().take(length).collect();
+    truncated.push_str(&end.unwrap_or_else(|| "…".to_string()));
+    truncated
+}
+
+/// Escape "MarkdownV2" special characters
+fn escape_markdown(value: String) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if MARKDOWN_SPECIAL_CHARS.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::processing::templating::{environment, validate};
+    use minijinja::context;
+
+    #[test]
+    fn test_filters() {
+        let env = environment(false);
+        let rendered = env
+            .render_str(
+                "{{ text | truncate(5) }} {{ tags | join(', ') }} {{ price | escape_markdown }}",
+                context!(text => "Hello world", tags => vec!["#a", "#b"], price => "1.5$"),
+            )
+            .unwrap();
+
+        assert_eq!("Hello… #a, #b 1\\.5$", rendered);
+    }
+
+    #[test]
+    fn test_validate() {
+        let env = environment(true);
+        assert!(validate(&env, "{% if user %}{{ user }}{% endif %}").is_ok());
+        assert!(validate(&env, "{% if user %}{{ user }}").is_err());
+        // No loader, templates can't include files
+        assert!(env
+            .render_str("{% include '/etc/passwd' %}", context!())
+            .is_err());
+    }
+}
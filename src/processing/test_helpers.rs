@@ -2,17 +2,38 @@ use crate::config::PipeConf;
 use crate::processing::data::DataHub;
 use crate::processing::pipe::{Pipe, PipeType};
 use rust_tdlib::types::{
-    Animation, Document, File, FormattedText, LocalFile, Message, MessageAnimation, MessageContent,
-    MessageDocument, MessagePhoto, MessageSender, MessageSenderUser, MessageText, MessageVideo,
-    Photo, PhotoSize, RemoteFile, UpdateNewMessage, Video,
+    Animation, Audio, Contact, Document, File, FormattedText, LocalFile, Location, Message,
+    MessageAnimation, MessageAudio, MessageContact, MessageContent, MessageDice, MessageDocument,
+    MessageForwardInfo, MessageForwardOrigin, MessageLocation, MessagePhoto, MessagePoll,
+    MessageSender, MessageSenderChat, MessageSenderUser, MessageSticker, MessageText, MessageVenue,
+    MessageVideo, MessageVideoNote, MessageVoiceNote, Photo, PhotoSize, Poll, PollOption,
+    RemoteFile, Sticker, TextEntity, TextEntityType, UpdateNewMessage, Venue, Video, VideoNote,
+    VoiceNote,
 };
 
 /// Mock message with all types of message contents.
 pub(crate) enum MessageMock {
     Photo(Option<String>, i32),
+    /// Photo with several sizes - type, width/height and file size
+    PhotoSizes(Vec<(&'static str, i32, i32)>),
     Document(Option<String>, i32),
+    /// Document with file name and MIME type
+    NamedDocument(&'static str, &'static str),
     Video(Option<String>, i32, i32),
     Animation(Option<String>, i32, i32),
+    Audio(Option<String>, i32, i32),
+    Voice(Option<String>, i32, i32),
+    /// Video note with duration and file size
+    VideoNote(i32, i32),
+    Sticker(i32),
+    Poll(String, Vec<String>),
+    Location(f32, f32),
+    /// Venue with title
+    Venue(&'static str),
+    /// Contact with phone number
+    Contact(&'static str),
+    /// Dice with emoji
+    Dice(&'static str),
     Text(Option<String>),
 }
 
@@ -32,6 +53,28 @@ impl From<MessageMock> for MessageContent {
                     .caption(formatted_text_example(text))
                     .build(),
             ),
+            MessageMock::PhotoSizes(sizes) => MessageContent::MessagePhoto(
+                MessagePhoto::builder()
+                    .photo(
+                        Photo::builder()
+                            .sizes(
+                                sizes
+                                    .into_iter()
+                                    .map(|(type_, dimension, filesize)| {
+                                        PhotoSize::builder()
+                                            .type_(type_)
+                                            .width(dimension)
+                                            .height(dimension)
+                                            .photo(file_example(filesize))
+                                            .build()
+                                    })
+                                    .collect(),
+                            )
+                            .build(),
+                    )
+                    .caption(formatted_text_example(None))
+                    .build(),
+            ),
             MessageMock::Video(text, duration, filesize) => MessageContent::MessageVideo(
                 MessageVideo::builder()
                     .video(
@@ -60,6 +103,93 @@ impl From<MessageMock> for MessageContent {
                     .caption(formatted_text_example(text))
                     .build(),
             ),
+            MessageMock::NamedDocument(file_name, mime_type) => MessageContent::MessageDocument(
+                MessageDocument::builder()
+                    .document(
+                        Document::builder()
+                            .file_name(file_name)
+                            .mime_type(mime_type)
+                            .document(file_example(100))
+                            .build(),
+                    )
+                    .caption(formatted_text_example(None))
+                    .build(),
+            ),
+            MessageMock::Audio(text, duration, filesize) => MessageContent::MessageAudio(
+                MessageAudio::builder()
+                    .audio(
+                        Audio::builder()
+                            .audio(file_example(filesize))
+                            .duration(duration)
+                            .build(),
+                    )
+                    .caption(formatted_text_example(text))
+                    .build(),
+            ),
+            MessageMock::Voice(text, duration, filesize) => MessageContent::MessageVoiceNote(
+                MessageVoiceNote::builder()
+                    .voice_note(
+                        VoiceNote::builder()
+                            .voice(file_example(filesize))
+                            .duration(duration)
+                            .build(),
+                    )
+                    .caption(formatted_text_example(text))
+                    .build(),
+            ),
+            MessageMock::VideoNote(duration, filesize) => MessageContent::MessageVideoNote(
+                MessageVideoNote::builder()
+                    .video_note(
+                        VideoNote::builder()
+                            .video(file_example(filesize))
+                            .duration(duration)
+                            .build(),
+                    )
+                    .build(),
+            ),
+            MessageMock::Sticker(filesize) => MessageContent::MessageSticker(
+                MessageSticker::builder()
+                    .sticker(Sticker::builder().sticker(file_example(filesize)).build())
+                    .build(),
+            ),
+            MessageMock::Poll(question, options) => MessageContent::MessagePoll(
+                MessagePoll::builder()
+                    .poll(
+                        Poll::builder()
+                            .question(question)
+                            .options(
+                                options
+                                    .iter()
+                                    .map(|option| PollOption::builder().text(option).build())
+                                    .collect(),
+                            )
+                            .build(),
+                    )
+                    .build(),
+            ),
+            MessageMock::Location(latitude, longitude) => MessageContent::MessageLocation(
+                MessageLocation::builder()
+                    .location(
+                        Location::builder()
+                            .latitude(latitude)
+                            .longitude(longitude)
+                            .build(),
+                    )
+                    .build(),
+            ),
+            MessageMock::Venue(title) => MessageContent::MessageVenue(
+                MessageVenue::builder()
+                    .venue(Venue::builder().title(title).build())
+                    .build(),
+            ),
+            MessageMock::Contact(phone_number) => MessageContent::MessageContact(
+                MessageContact::builder()
+                    .contact(Contact::builder().phone_number(phone_number).build())
+                    .build(),
+            ),
+            MessageMock::Dice(emoji) => MessageContent::MessageDice(Box::new(
+                MessageDice::builder().emoji(emoji).value(6).build(),
+            )),
             MessageMock::Text(text) => MessageContent::MessageText(Box::new(
                 MessageText::builder()
                     .text(formatted_text_example(text))
@@ -73,6 +203,10 @@ pub(crate) fn sender_user_example() -> MessageSender {
     MessageSender::User(MessageSenderUser::builder().user_id(1).build())
 }
 
+pub(crate) fn sender_chat_example(chat_id: i64) -> MessageSender {
+    MessageSender::Chat(MessageSenderChat::builder().chat_id(chat_id).build())
+}
+
 pub(crate) fn file_example(size: i32) -> File {
     File::builder()
         .size(size)
@@ -88,6 +222,37 @@ pub(crate) fn formatted_text_example(text: Option<String>) -> FormattedText {
         .build()
 }
 
+pub(crate) fn entity_example(offset: i32, length: i32, entity_type: TextEntityType) -> TextEntity {
+    TextEntity::builder()
+        .offset(offset)
+        .length(length)
+        .type_(entity_type)
+        .build()
+}
+
+/// Text message with entities
+pub(crate) fn entities_message_example(text: &str, entities: Vec<TextEntity>) -> UpdateNewMessage {
+    UpdateNewMessage::builder()
+        .message(
+            Message::builder()
+                .id(1)
+                .chat_id(1)
+                .sender_id(sender_user_example())
+                .content(MessageContent::MessageText(Box::new(
+                    MessageText::builder()
+                        .text(
+                            FormattedText::builder()
+                                .text(text)
+                                .entities(entities)
+                                .build(),
+                        )
+                        .build(),
+                )))
+                .build(),
+        )
+        .build()
+}
+
 pub(crate) fn message_example(
     sender: MessageSender,
     content: MessageMock,
@@ -106,6 +271,43 @@ pub(crate) fn message_example(
         .build()
 }
 
+#[cfg(feature = "schedule")]
+pub(crate) fn dated_message_example(date: i32) -> UpdateNewMessage {
+    UpdateNewMessage::builder()
+        .message(
+            Message::builder()
+                .id(1)
+                .chat_id(1)
+                .date(date)
+                .sender_id(sender_user_example())
+                .content(MessageContent::from(MessageMock::Text(None)))
+                .build(),
+        )
+        .build()
+}
+
+pub(crate) fn forwarded_message_example(
+    origin: MessageForwardOrigin,
+    date: i32,
+) -> UpdateNewMessage {
+    UpdateNewMessage::builder()
+        .message(
+            Message::builder()
+                .id(1)
+                .chat_id(1)
+                .sender_id(sender_user_example())
+                .forward_info(
+                    MessageForwardInfo::builder()
+                        .origin(origin)
+                        .date(date)
+                        .build(),
+                )
+                .content(MessageContent::from(MessageMock::Text(None)))
+                .build(),
+        )
+        .build()
+}
+
 pub(crate) async fn transformed_data_example(message: Option<String>) -> DataHub {
     let mut data = DataHub::new(message_example(
         sender_user_example(),
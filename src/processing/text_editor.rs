@@ -0,0 +1,323 @@
+use rust_tdlib::types::{FormattedText, TextEntity};
+use std::ops::Range;
+
+/// Edits text and keeps entities consistent. Positions are in UTF-16 code units, as TDLib entity offsets.
+///
+/// Rules for an entity when range of text is replaced:
+/// 1. entity before or after the range is kept (shifted when after)
+/// 2. entity containing the range is resized, replacement takes entity's formatting
+/// 3. entity partially overlapping the range is clipped to its part outside the range
+/// 4. entity inside the range (or empty after the edit) is dropped
+///
+/// Inserted text isn't covered by entities starting or ending at insert position,
+/// only by entities containing it.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct TextEditor {
+    text: Vec<u16>,
+    entities: Vec<TextEntity>,
+}
+
+impl TextEditor {
+    pub fn new(formatted_text: &FormattedText) -> Self {
+        TextEditor {
+            text: formatted_text.text().encode_utf16().collect(),
+            entities: formatted_text.entities().clone(),
+        }
+    }
+
+    /// Text length in UTF-16 code units
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    pub fn entities(&self) -> &Vec<TextEntity> {
+        &self.entities
+    }
+
+    /// Current text
+    pub fn text(&self) -> String {
+        String::from_utf16_lossy(&self.text)
+    }
+
+    /// Text of the range
+    pub fn slice(&self, start: usize, end: usize) -> String {
+        String::from_utf16_lossy(&self.text[start.min(self.len())..end.min(self.len())])
+    }
+
+    /// Formatted text of the range, entities are clipped to it
+    pub fn formatted_slice(&self, start: usize, end: usize) -> FormattedText {
+        let end = end.min(self.len());
+        let start = start.min(end);
+        let (range_start, range_end) = (start as i32, end as i32);
+
+        let entities = self
+            .entities
+            .iter()
+            .filter_map(|entity| {
+                let entity_start = entity.offset().max(range_start);
+                let entity_end = (entity.offset() + entity.length()).min(range_end);
+
+                (entity_end > entity_start).then(|| {
+                    TextEntity::builder()
+                        .offset(entity_start - range_start)
+                        .length(entity_end - entity_start)
+                        .type_(entity.type_())
+                        .build()
+                })
+            })
+            .collect();
+
+        FormattedText::builder()
+            .text(String::from_utf16_lossy(&self.text[start..end]))
+            .entities(entities)
+            .build()
+    }
+
+    /// Replace range [start, end) with replacement
+    pub fn replace(&mut self, start: usize, end: usize, replacement: &str) {
+        let end = end.min(self.len());
+        let start = start.min(end);
+        if start == end {
+            return self.insert(start, replacement);
+        }
+
+        let replacement: Vec<u16> = replacement.encode_utf16().collect();
+        let replacement_end = (start + replacement.len()) as i32;
+        let delta = replacement.len() as i32 - (end - start) as i32;
+        let (start, end) = (start as i32, end as i32);
+
+        self.entities = self
+            .entities
+            .iter()
+            .filter_map(|entity| {
+                let entity_start = entity.offset();
+                let entity_end = entity.offset() + entity.length();
+
+                let (new_start, new_end) = if entity_end <= start {
+                    (entity_start, entity_end)
+                } else if entity_start >= end {
+                    (entity_start + delta, entity_end + delta)
+                } else if entity_start <= start && end <= entity_end {
+                    (entity_start, entity_end + delta)
+                } else if start <= entity_start && entity_end <= end {
+                    return None;
+                } else if entity_start < start {
+                    (entity_start, start)
+                } else {
+                    (replacement_end, entity_end + delta)
+                };
+
+                (new_end > new_start).then(|| {
+                    TextEntity::builder()
+                        .offset(new_start)
+                        .length(new_end - new_start)
+                        .type_(entity.type_())
+                        .build()
+                })
+            })
+            .collect();
+
+        self.text.splice(start as usize..end as usize, replacement);
+    }
+
+    /// Insert text at position. Entities ending or starting at position don't cover inserted text
+    pub fn insert(&mut self, position: usize, text: &str) {
+        let position = position.min(self.len()) as i32;
+        let delta = text.encode_utf16().count() as i32;
+
+        for entity in self.entities.iter_mut() {
+            if entity.offset() >= position {
+                *entity = TextEntity::builder()
+                    .offset(entity.offset() + delta)
+                    .length(entity.length())
+                    .type_(entity.type_())
+                    .build();
+            } else if entity.offset() + entity.length() > position {
+                *entity = TextEntity::builder()
+                    .offset(entity.offset())
+                    .length(entity.length() + delta)
+                    .type_(entity.type_())
+                    .build();
+            }
+        }
+
+        self.text
+            .splice(position as usize..position as usize, text.encode_utf16());
+    }
+
+    /// Insert formatted text at position, its entities are moved to the position
+    #[cfg(feature = "storage")]
+    pub fn insert_formatted(&mut self, position: usize, formatted_text: &FormattedText) {
+        let position = position.min(self.len());
+        self.insert(position, formatted_text.text());
+        self.add_entities(position, formatted_text.entities());
+    }
+
+    /// Add entities of text which is placed at position
+    #[cfg(any(feature = "storage", feature = "templating"))]
+    pub fn add_entities(&mut self, position: usize, entities: &[TextEntity]) {
+        for entity in entities {
+            self.entities.push(
+                TextEntity::builder()
+                    .offset(entity.offset() + position as i32)
+                    .length(entity.length())
+                    .type_(entity.type_())
+                    .build(),
+            );
+        }
+    }
+
+    /// Replace matches found in current text (e.g. by `str::match_indices` or `Regex::captures_iter`).
+    /// Ranges are byte offsets of `self.text()` and should be ordered and not overlapping
+    pub fn replace_matches(&mut self, matches: Vec<(Range<usize>, String)>) {
+        let text = self.text();
+
+        // From the end, so offsets of matches which are not replaced yet stay the same
+        for (range, replacement) in matches.into_iter().rev() {
+            self.replace(
+                utf16_offset(&text, range.start),
+                utf16_offset(&text, range.end),
+                &replacement,
+            );
+        }
+    }
+
+    /// Add entity, e.g. for inserted link
+    pub fn push_entity(&mut self, entity: TextEntity) {
+        self.entities.push(entity);
+    }
+
+    /// Replace entity at index, e.g. with changed url
+    pub fn set_entity(&mut self, index: usize, entity: TextEntity) {
+        self.entities[index] = entity;
+    }
+
+    pub fn remove_entity(&mut self, index: usize) {
+        self.entities.remove(index);
+    }
+
+    pub fn build(&self) -> FormattedText {
+        let mut entities = self.entities.clone();
+        entities.sort_by_key(|entity| entity.offset());
+
+        FormattedText::builder()
+            .text(String::from_utf16_lossy(&self.text))
+            .entities(entities)
+            .build()
+    }
+}
+
+/// Convert byte offset of the text to UTF-16 code units offset
+pub(crate) fn utf16_offset(text: &str, byte_offset: usize) -> usize {
+    text[..byte_offset].encode_utf16().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::processing::test_helpers::entity_example;
+    use crate::processing::text_editor::TextEditor;
+    use rust_tdlib::types::{FormattedText, TextEntity, TextEntityType, TextEntityTypeBold};
+
+    fn bold(offset: i32, length: i32) -> TextEntity {
+        entity_example(
+            offset,
+            length,
+            TextEntityType::Bold(TextEntityTypeBold::builder().build()),
+        )
+    }
+
+    fn positions(editor: &TextEditor) -> Vec<(i32, i32)> {
+        editor
+            .entities()
+            .iter()
+            .map(|entity| (entity.offset(), entity.length()))
+            .collect()
+    }
+
+    #[test]
+    fn test_replace() {
+        // "hello" and "world" are bold
+        let formatted_text = FormattedText::builder()
+            .text("hello big world")
+            .entities(vec![bold(0, 5), bold(10, 5)])
+            .build();
+
+        let mut editor = TextEditor::new(&formatted_text);
+        editor.replace(6, 9, "small");
+
+        assert_eq!("hello small world", editor.build().text());
+        assert_eq!(vec![(0, 5), (12, 5)], positions(&editor));
+
+        // Replacement inside entity takes its formatting
+        editor.replace(12, 17, "everyone");
+        assert_eq!("hello small everyone", editor.build().text());
+        assert_eq!(vec![(0, 5), (12, 8)], positions(&editor));
+    }
+
+    #[test]
+    fn test_replace_overlapping() {
+        // "hello big" is bold
+        let formatted_text = FormattedText::builder()
+            .text("hello big world")
+            .entities(vec![bold(0, 9), bold(6, 3)])
+            .build();
+
+        let mut editor = TextEditor::new(&formatted_text);
+        editor.replace(4, 15, "");
+
+        // Overlapping entity is clipped, entity inside the range is dropped
+        assert_eq!("hell", editor.build().text());
+        assert_eq!(vec![(0, 4)], positions(&editor));
+    }
+
+    #[test]
+    fn test_insert_utf16() {
+        // Emoji takes 2 UTF-16 code units
+        let formatted_text = FormattedText::builder()
+            .text("🔥 hot")
+            .entities(vec![bold(3, 3)])
+            .build();
+
+        let mut editor = TextEditor::new(&formatted_text);
+        editor.insert(3, "very ");
+
+        assert_eq!("🔥 very hot", editor.build().text());
+        assert_eq!(vec![(8, 3)], positions(&editor));
+        assert_eq!("hot", editor.slice(8, 11));
+    }
+
+    #[test]
+    fn test_replace_matches() {
+        // "🔥" and "hot" are bold
+        let formatted_text = FormattedText::builder()
+            .text("🔥 hot 🔥 hot")
+            .entities(vec![bold(0, 2), bold(3, 3), bold(10, 3)])
+            .build();
+
+        let mut editor = TextEditor::new(&formatted_text);
+        let matches = editor
+            .text()
+            .match_indices("🔥")
+            .map(|(start, found)| (start..start + found.len(), "*".to_string()))
+            .collect();
+        editor.replace_matches(matches);
+
+        assert_eq!("* hot * hot", editor.build().text());
+        assert_eq!(vec![(0, 1), (2, 3), (8, 3)], positions(&editor));
+    }
+
+    #[cfg(feature = "storage")]
+    #[test]
+    fn test_insert_formatted() {
+        let message = FormattedText::builder()
+            .text("hot news")
+            .entities(vec![bold(0, 3)])
+            .build();
+
+        let mut editor = TextEditor::new(&FormattedText::builder().text("🔥  🔥").build());
+        editor.insert_formatted(3, &message);
+
+        assert_eq!("🔥 hot news 🔥", editor.build().text());
+        assert_eq!(vec![(3, 3)], positions(&editor));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::storage::{queue, storage, Storage, StorageError, StorageResult, StoredEntry};
+use argh::FromArgs;
+use std::fs;
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "storage")]
+/// inspect and maintain stored state of filters
+pub struct StorageCommand {
+    #[argh(subcommand)]
+    action: StorageAction,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum StorageAction {
+    Usage(UsageAction),
+    Purge(PurgeAction),
+    Export(ExportAction),
+    Import(ImportAction),
+    Queue(QueueAction),
+    Cancel(CancelAction),
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "usage")]
+/// show number of entries per namespace
+struct UsageAction {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "purge")]
+/// remove everything related to chat or user id. Entries migrated from key-value.db
+/// (except per destination Unique) and seen fingerprints of previous versions have no ids and stay
+struct PurgeAction {
+    #[argh(positional)]
+    /// chat or user id
+    id: i64,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "export")]
+/// write all entries into json file
+struct ExportAction {
+    #[argh(positional)]
+    /// path of json file
+    path: String,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "import")]
+/// read entries from json file made by export, existing keys are replaced
+struct ImportAction {
+    #[argh(positional)]
+    /// path of json file
+    path: String,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "queue")]
+/// list messages waiting to be sent
+struct QueueAction {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "cancel")]
+/// remove message from queue
+struct CancelAction {
+    #[argh(positional)]
+    /// id of queued message, as listed by queue
+    id: String,
+}
+
+impl StorageCommand {
+    /// Run command against initialized storage
+    pub fn run(&self) -> StorageResult<()> {
+        let storage = storage();
+
+        match &self.action {
+            StorageAction::Usage(_) => print_usage(storage.as_ref())?,
+            StorageAction::Purge(action) => {
+                println!("Removed {} entries", storage.purge(action.id)?);
+            }
+            StorageAction::Export(action) => {
+                let entries = storage.export()?;
+                let json = serde_json::to_string_pretty(&entries)
+                    .map_err(|e| StorageError(e.to_string()))?;
+                fs::write(&action.path, json).map_err(|e| StorageError(e.to_string()))?;
+                println!("Exported {} entries", entries.len());
+            }
+            StorageAction::Import(action) => {
+                let json =
+                    fs::read_to_string(&action.path).map_err(|e| StorageError(e.to_string()))?;
+                let entries: Vec<StoredEntry> =
+                    serde_json::from_str(&json).map_err(|e| StorageError(e.to_string()))?;
+                let count = entries.len();
+                storage.import(entries)?;
+                println!("Imported {} entries", count);
+            }
+            StorageAction::Queue(_) => print_queue()?,
+            StorageAction::Cancel(action) => match queue::cancel(&action.id)? {
+                true => println!("Cancelled {}", action.id),
+                false => println!("{} is not queued", action.id),
+            },
+        }
+
+        Ok(())
+    }
+}
+
+fn print_usage(storage: &dyn Storage) -> StorageResult<()> {
+    let usage = storage.usage()?;
+    let width = usage
+        .iter()
+        .map(|(namespace, _)| namespace.len())
+        .max()
+        .unwrap_or_default()
+        .max("Namespace".len());
+
+    println!("{:width$}  Entries", "Namespace", width = width);
+    for (namespace, entries) in usage {
+        println!("{:width$}  {}", namespace, entries, width = width);
+    }
+
+    Ok(())
+}
+
+fn print_queue() -> StorageResult<()> {
+    let items = queue::items()?;
+
+    for (key, item) in &items {
+        println!(
+            "{}  {} -> {}  {} message(s), pipeline '{}'",
+            key,
+            item.source_chat_id,
+            item.dest_chat_id,
+            item.messages.len(),
+            item.pipeline
+        );
+    }
+    println!("{} queued", items.len());
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Digest batches: items collected per route until their digest is posted
+use crate::processing::digest::DigestItem;
+use crate::storage::{now, storage, StorageError, StorageResult};
+use serde::{Deserialize, Serialize};
+
+/// Namespace of collected batches, one per pipeline and route
+const NAMESPACE: &str = "digest";
+/// Seconds after which digest which wasn't posted is tried again
+const RETRY_DELAY: u64 = 60;
+
+/// Items of route waiting for their digest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestBatch {
+    /// Unix time to post digest at
+    pub flush_at: u64,
+    pub pipeline: String,
+    pub source_chat_id: i64,
+    pub dest_chat_id: i64,
+    /// Destination forum topic
+    pub thread_id: Option<i64>,
+    pub items: Vec<DigestItem>,
+}
+
+impl DigestBatch {
+    fn key(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            self.source_chat_id, self.dest_chat_id, self.pipeline
+        )
+    }
+}
+
+fn read(key: &str) -> StorageResult<Option<DigestBatch>> {
+    storage()
+        .get(NAMESPACE, key)?
+        .map(|value| serde_json::from_str(&value).map_err(|e| StorageError(e.to_string())))
+        .transpose()
+}
+
+fn write(batch: &DigestBatch) -> StorageResult<()> {
+    let value = serde_json::to_string(batch).map_err(|e| StorageError(e.to_string()))?;
+    storage().set(
+        NAMESPACE,
+        &batch.key(),
+        &value,
+        None,
+        &[batch.source_chat_id, batch.dest_chat_id],
+    )
+}
+
+/// Batches of all routes. Batches which can't be read are skipped
+fn batches() -> StorageResult<Vec<(String, DigestBatch)>> {
+    Ok(storage()
+        .entries(NAMESPACE)?
+        .into_iter()
+        .filter_map(|(key, value)| match serde_json::from_str(&value) {
+            Ok(batch) => Some((key, batch)),
+            Err(e) => {
+                eprintln!("Invalid {} entry {}: {}", NAMESPACE, key, e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Add items of batch to the collected ones of its route. The first batch of route sets time
+/// of posting. Returns the whole batch when it has `max_items`, it's kept until `remove_posted`
+pub fn collect(batch: DigestBatch, max_items: usize) -> StorageResult<Option<DigestBatch>> {
+    let batch = match read(&batch.key())? {
+        Some(mut collected) => {
+            collected.items.extend(batch.items);
+            collected
+        }
+        None => batch,
+    };
+
+    write(&batch)?;
+    Ok((batch.items.len() >= max_items).then_some(batch))
+}
+
+/// Time of the earliest digest
+pub fn next_flush_at() -> StorageResult<Option<u64>> {
+    Ok(batches()?.iter().map(|(_, batch)| batch.flush_at).min())
+}
+
+/// Batches which time has come. They are kept until `remove_posted`
+pub fn due(time: u64) -> StorageResult<Vec<DigestBatch>> {
+    Ok(batches()?
+        .into_iter()
+        .map(|(_, batch)| batch)
+        .filter(|batch| batch.flush_at <= time)
+        .collect())
+}
+
+/// Remove items of posted batch. Items collected meanwhile wait for the next digest
+pub fn remove_posted(batch: &DigestBatch) -> StorageResult<()> {
+    let Some(mut collected) = read(&batch.key())? else {
+        return Ok(());
+    };
+
+    if collected.items.len() <= batch.items.len() {
+        storage().remove(NAMESPACE, &batch.key())?;
+        return Ok(());
+    }
+    collected.items.drain(..batch.items.len());
+    write(&collected)
+}
+
+/// Post batch which wasn't posted `RETRY_DELAY` later
+pub fn postpone(batch: &DigestBatch) -> StorageResult<()> {
+    let Some(mut collected) = read(&batch.key())? else {
+        return Ok(());
+    };
+
+    collected.flush_at = now() + RETRY_DELAY;
+    write(&collected)
+}
+
+/// Batch of one item to collect, posted after `interval` seconds unless route has one already
+pub fn batch(
+    pipeline: &str,
+    source_chat_id: i64,
+    dest_chat_id: i64,
+    thread_id: Option<i64>,
+    interval: u64,
+    item: DigestItem,
+) -> DigestBatch {
+    DigestBatch {
+        flush_at: now() + interval,
+        pipeline: pipeline.to_string(),
+        source_chat_id,
+        dest_chat_id,
+        thread_id,
+        items: vec![item],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{batch, collect, due, next_flush_at, postpone, remove_posted, RETRY_DELAY};
+    use crate::processing::digest::DigestItem;
+    use crate::storage::{isolate, now};
+
+    fn item(first_line: &str) -> DigestItem {
+        DigestItem {
+            title: "News".to_string(),
+            link: String::new(),
+            first_line: first_line.to_string(),
+            date: 0,
+        }
+    }
+
+    #[test]
+    fn test_collect() {
+        isolate();
+        let first = batch("digest_test", -3001, -3002, None, 600, item("1"));
+        let flush_at = first.flush_at;
+
+        assert_eq!(None, collect(first, 3).unwrap());
+        // Time of the first item is kept
+        assert_eq!(
+            None,
+            collect(batch("digest_test", -3001, -3002, None, 60, item("2")), 3).unwrap()
+        );
+        assert_eq!(Some(flush_at), next_flush_at().unwrap());
+
+        let full = collect(batch("digest_test", -3001, -3002, None, 60, item("3")), 3)
+            .unwrap()
+            .unwrap();
+        assert_eq!(flush_at, full.flush_at);
+        assert_eq!(
+            vec!["1", "2", "3"],
+            full.items
+                .iter()
+                .map(|item| item.first_line.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        // Full batch is kept until it's posted, items collected meanwhile wait for the next one
+        assert_eq!(Some(flush_at), next_flush_at().unwrap());
+        assert!(
+            collect(batch("digest_test", -3001, -3002, None, 0, item("4")), 3)
+                .unwrap()
+                .is_some()
+        );
+        remove_posted(&full).unwrap();
+        assert_eq!(Some(flush_at), next_flush_at().unwrap());
+        assert!(due(now() + 1).unwrap().is_empty());
+
+        let due = due(flush_at).unwrap();
+        assert_eq!(1, due.len());
+        assert_eq!("4", due[0].items[0].first_line);
+        remove_posted(&due[0]).unwrap();
+        assert_eq!(None, next_flush_at().unwrap());
+    }
+
+    #[test]
+    fn test_postpone() {
+        isolate();
+        let collected = batch("digest_test", -3001, -3002, None, 0, item("1"));
+        collect(collected.clone(), 3).unwrap();
+
+        // Batch which wasn't posted stays with all its items
+        postpone(&collected).unwrap();
+        assert!(due(now()).unwrap().is_empty());
+        let retried = due(now() + RETRY_DELAY).unwrap();
+        assert_eq!(vec![item("1")], retried[0].items);
+    }
+}
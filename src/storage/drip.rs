@@ -0,0 +1,210 @@
+//! Drip-feed of destination chats: messages are queued one per interval,
+//! full backlog drops or merges messages
+use crate::config::{DripConf, DripFullConf};
+use crate::processing::digest::append_to_digest;
+use crate::storage::queue::{self, QueueItem};
+use crate::storage::{now, storage, StorageResult};
+
+/// Namespace of the last slots of destination chats
+const NAMESPACE: &str = "drip";
+
+/// What happened to message put into drip-feed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dripped {
+    /// Queued to be sent at unix time
+    Queued(u64),
+    /// Backlog is full, message is dropped
+    Dropped,
+    /// Backlog is full, message is merged into the last queued one to be sent at unix time
+    Merged(u64),
+}
+
+/// Put item into drip-feed of its destination. It's sent in the first free slot
+/// not earlier than its `send_at`
+pub fn push(drip: &DripConf, mut item: QueueItem) -> StorageResult<Dripped> {
+    let backlog: Vec<(String, QueueItem)> = queue::items()?
+        .into_iter()
+        .filter(|(_, queued)| queued.dest_chat_id == drip.chat)
+        .collect();
+
+    let mut free_slot = None;
+
+    if backlog.len() >= drip.max_backlog {
+        match drip.when_full {
+            DripFullConf::DropNewest => return Ok(Dripped::Dropped),
+            DripFullConf::DropOldest => free_slot = drop_oldest(backlog)?,
+            DripFullConf::Digest => {
+                if let Some((_, last)) = backlog.into_iter().last() {
+                    let mut digest = last;
+                    append_to_digest(&mut digest.messages, item.messages);
+                    digest
+                        .merged_sources
+                        .push((item.source_chat_id, item.source_message_id));
+                    digest.merged_sources.extend(item.merged_sources);
+                    // Same key, so the queued item is replaced
+                    queue::push(&digest)?;
+                    return Ok(Dripped::Merged(digest.send_at));
+                }
+                return Ok(Dripped::Dropped);
+            }
+        }
+    }
+
+    let key = drip.chat.to_string();
+    let slot = match free_slot {
+        Some(slot) => slot,
+        None => storage()
+            .get(NAMESPACE, &key)?
+            .and_then(|slot| slot.parse::<u64>().ok())
+            .map_or(0, |slot| slot + drip.interval),
+    }
+    .max(item.send_at)
+    .max(now());
+
+    storage().set(
+        NAMESPACE,
+        &key,
+        &slot.to_string(),
+        Some(slot.saturating_sub(now()) + drip.interval),
+        &[drip.chat],
+    )?;
+
+    item.send_at = slot;
+    queue::push(&item)?;
+    Ok(Dripped::Queued(slot))
+}
+
+/// Cancel the oldest item of backlog and move the others one slot earlier,
+/// so the schedule doesn't drift. Returns the last slot, which is free now
+fn drop_oldest(backlog: Vec<(String, QueueItem)>) -> StorageResult<Option<u64>> {
+    let mut free_slot = None;
+
+    for (key, mut queued) in backlog {
+        // Already sent or cancelled
+        if !queue::cancel(&key)? {
+            continue;
+        }
+
+        let slot = queued.send_at;
+        if let Some(free_slot) = free_slot {
+            queued.send_at = free_slot;
+            queue::push(&queued)?;
+        }
+        free_slot = Some(slot);
+    }
+    Ok(free_slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{push, Dripped};
+    use crate::config::{DripConf, DripFullConf};
+    use crate::storage::queue::{self, QueueItem};
+    use crate::storage::{isolate, now};
+    use rust_tdlib::types::{FormattedText, InputMessageContent, InputMessageText};
+
+    fn item(dest_chat_id: i64, source_message_id: i64, text: &str) -> QueueItem {
+        QueueItem {
+            send_at: now() + 1000,
+            pipeline: "drip_test".to_string(),
+            source_chat_id: -2000,
+            source_message_id,
+            dest_chat_id,
+            thread_id: None,
+            messages: vec![InputMessageContent::InputMessageText(
+                InputMessageText::builder()
+                    .text(FormattedText::builder().text(text).build())
+                    .build(),
+            )],
+            attempts: 0,
+            merged_sources: vec![],
+        }
+    }
+
+    fn drip(chat: i64, when_full: DripFullConf) -> DripConf {
+        DripConf {
+            chat,
+            interval: 600,
+            max_backlog: 2,
+            when_full,
+        }
+    }
+
+    fn backlog(chat: i64) -> Vec<QueueItem> {
+        queue::items()
+            .unwrap()
+            .into_iter()
+            .map(|(_, item)| item)
+            .filter(|item| item.dest_chat_id == chat)
+            .collect()
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        isolate();
+        let drip = drip(-2001, DripFullConf::DropNewest);
+        let Dripped::Queued(first) = push(&drip, item(-2001, 1, "1")).unwrap() else {
+            panic!("first message isn't queued")
+        };
+        assert!(first >= now() + 1000);
+        assert_eq!(
+            Dripped::Queued(first + 600),
+            push(&drip, item(-2001, 2, "2")).unwrap()
+        );
+        assert_eq!(Dripped::Dropped, push(&drip, item(-2001, 3, "3")).unwrap());
+
+        let ids: Vec<i64> = backlog(-2001).iter().map(|i| i.source_message_id).collect();
+        assert_eq!(vec![1, 2], ids);
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        isolate();
+        let drip = drip(-2002, DripFullConf::DropOldest);
+
+        let Dripped::Queued(first) = push(&drip, item(-2002, 1, "1")).unwrap() else {
+            panic!("first message isn't queued")
+        };
+        push(&drip, item(-2002, 2, "2")).unwrap();
+        // Freed slot is reused, the schedule doesn't move later
+        for id in 3..=5 {
+            assert_eq!(
+                Dripped::Queued(first + 600),
+                push(&drip, item(-2002, id, "text")).unwrap()
+            );
+        }
+
+        let backlog: Vec<(i64, u64)> = backlog(-2002)
+            .iter()
+            .map(|i| (i.source_message_id, i.send_at))
+            .collect();
+        assert_eq!(vec![(4, first), (5, first + 600)], backlog);
+    }
+
+    #[test]
+    fn test_digest() {
+        isolate();
+        let drip = drip(-2003, DripFullConf::Digest);
+
+        push(&drip, item(-2003, 1, "1")).unwrap();
+        let Dripped::Queued(second) = push(&drip, item(-2003, 2, "2")).unwrap() else {
+            panic!("second message isn't queued")
+        };
+        assert_eq!(
+            Dripped::Merged(second),
+            push(&drip, item(-2003, 3, "3")).unwrap()
+        );
+
+        let backlog = backlog(-2003);
+        assert_eq!(2, backlog.len());
+        let InputMessageContent::InputMessageText(digest) = &backlog[1].messages[0] else {
+            panic!("digest isn't text")
+        };
+        assert_eq!("2\n\n3", digest.text().text());
+        assert_eq!(vec![(-2000, 3)], backlog[1].merged_sources);
+
+        // Deletion of merged source message cancels digest
+        assert_eq!(1, queue::cancel_source(-2000, &[3]).unwrap());
+        assert_eq!(1, queue::items().unwrap().len());
+    }
+}
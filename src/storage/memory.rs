@@ -0,0 +1,144 @@
+use crate::storage::{Storage, StorageResult, StoredEntry};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Storage which lives until restart, used in tests
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    entries: Mutex<BTreeMap<(String, String), StoredEntry>>,
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, namespace: &str, key: &str) -> StorageResult<Option<String>> {
+        let entries = self.entries.lock().unwrap();
+
+        Ok(entries
+            .get(&(namespace.to_string(), key.to_string()))
+            .filter(|entry| entry.is_alive())
+            .map(|entry| entry.value.clone()))
+    }
+
+    fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: &str,
+        ttl: Option<u64>,
+        related: &[i64],
+    ) -> StorageResult<()> {
+        self.entries.lock().unwrap().insert(
+            (namespace.to_string(), key.to_string()),
+            StoredEntry::new(namespace, key, value, ttl, related),
+        );
+
+        Ok(())
+    }
+
+    fn compare_and_set(
+        &self,
+        namespace: &str,
+        key: &str,
+        current: Option<&str>,
+        value: &str,
+        ttl: Option<u64>,
+        related: &[i64],
+    ) -> StorageResult<bool> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry_key = (namespace.to_string(), key.to_string());
+
+        let stored = entries
+            .get(&entry_key)
+            .filter(|entry| entry.is_alive())
+            .map(|entry| entry.value.as_str());
+        if stored != current {
+            return Ok(false);
+        }
+
+        entries.insert(
+            entry_key,
+            StoredEntry::new(namespace, key, value, ttl, related),
+        );
+        Ok(true)
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> StorageResult<bool> {
+        let removed = self
+            .entries
+            .lock()
+            .unwrap()
+            .remove(&(namespace.to_string(), key.to_string()));
+
+        Ok(removed.is_some_and(|entry| entry.is_alive()))
+    }
+
+    fn entries(&self, namespace: &str) -> StorageResult<Vec<(String, String)>> {
+        let entries = self.entries.lock().unwrap();
+
+        Ok(entries
+            .values()
+            .filter(|entry| entry.namespace == namespace && entry.is_alive())
+            .map(|entry| (entry.key.clone(), entry.value.clone()))
+            .collect())
+    }
+
+    fn entries_with_prefix(
+        &self,
+        namespace: &str,
+        prefix: &str,
+    ) -> StorageResult<Vec<(String, String)>> {
+        let entries = self.entries.lock().unwrap();
+
+        Ok(entries
+            .range((namespace.to_string(), prefix.to_string())..)
+            .take_while(|((entry_namespace, key), _)| {
+                entry_namespace == namespace && key.starts_with(prefix)
+            })
+            .filter(|(_, entry)| entry.is_alive())
+            .map(|(_, entry)| (entry.key.clone(), entry.value.clone()))
+            .collect())
+    }
+
+    fn usage(&self) -> StorageResult<Vec<(String, usize)>> {
+        let mut usage = BTreeMap::new();
+        for entry in self.entries.lock().unwrap().values() {
+            *usage.entry(entry.namespace.clone()).or_insert(0) += 1;
+        }
+
+        Ok(usage.into_iter().collect())
+    }
+
+    fn remove_stale(&self, namespace: &str, updated_before: Option<u64>) -> StorageResult<usize> {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+
+        entries.retain(|_, entry| {
+            entry.namespace != namespace
+                || (entry.is_alive()
+                    && updated_before.is_none_or(|before| entry.updated_at > before))
+        });
+
+        Ok(count - entries.len())
+    }
+
+    fn purge(&self, id: i64) -> StorageResult<usize> {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+
+        entries.retain(|_, entry| !entry.related.contains(&id));
+
+        Ok(count - entries.len())
+    }
+
+    fn export(&self) -> StorageResult<Vec<StoredEntry>> {
+        Ok(self.entries.lock().unwrap().values().cloned().collect())
+    }
+
+    fn import(&self, entries: Vec<StoredEntry>) -> StorageResult<()> {
+        let mut stored = self.entries.lock().unwrap();
+        for entry in entries {
+            stored.insert((entry.namespace.clone(), entry.key.clone()), entry);
+        }
+
+        Ok(())
+    }
+}
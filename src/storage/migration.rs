@@ -0,0 +1,134 @@
+use crate::storage::{now, Storage, StorageError, StorageResult};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// PickleDb file of previous versions
+const LEGACY_PATH: &str = "storage/key-value.db";
+
+/// Namespace of messages seen by `Unique` of previous versions, which were shared by all pipelines.
+/// Every `Unique` filter rejects them, whatever its namespace is
+pub(crate) const LEGACY_UNIQUE_NAMESPACE: &str = "unique/";
+
+/// Import PickleDb data of previous versions and rename its file to `key-value.db.migrated`.
+/// Seen messages of `Unique` (md5 digests) go to namespace `unique/`
+pub(crate) fn migrate_legacy(storage: &dyn Storage) -> StorageResult<()> {
+    let path = Path::new(LEGACY_PATH);
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| StorageError(e.to_string()))?;
+    // PickleDb json dump is a pair of key-value map and lists map
+    let (map, _): (HashMap<String, String>, HashMap<String, Vec<String>>) =
+        serde_json::from_str(&content).map_err(|e| StorageError(e.to_string()))?;
+
+    let mut migrated = 0;
+    for (key, value) in map {
+        migrated += migrate_entry(storage, &key, &value)?;
+    }
+
+    fs::rename(path, path.with_extension("db.migrated"))
+        .map_err(|e| StorageError(e.to_string()))?;
+    println!("Migrated {} entries from {}", migrated, LEGACY_PATH);
+
+    Ok(())
+}
+
+/// Set value unless key already exists, so newer data is kept
+fn import(
+    storage: &dyn Storage,
+    namespace: &str,
+    key: &str,
+    value: &str,
+    related: &[i64],
+) -> StorageResult<()> {
+    if storage.get(namespace, key)?.is_none() {
+        storage.set(namespace, key, value, None, related)?;
+    }
+
+    Ok(())
+}
+
+/// Import one PickleDb entry, returns number of imported entries
+fn migrate_entry(storage: &dyn Storage, key: &str, value: &str) -> StorageResult<usize> {
+    // "unique:<namespace>:<key>", key of per destination filter is "<destination>:<digest>".
+    // Other legacy entries don't know their chats, so they can't be purged by id
+    if let Some((namespace, key)) = key.strip_prefix("unique:").and_then(|k| k.split_once(':')) {
+        let related: Vec<i64> = key
+            .split_once(':')
+            .and_then(|(destination, _)| destination.parse().ok())
+            .into_iter()
+            .collect();
+        import(
+            storage,
+            &format!("unique/{}", namespace),
+            key,
+            value,
+            &related,
+        )?;
+        return Ok(1);
+    }
+
+    // "near_duplicate:<namespace>" and "similar_image:<namespace>" with [[fingerprint, seen]]
+    if let Some((filter, namespace)) = key.split_once(':') {
+        if filter == "near_duplicate" || filter == "similar_image" {
+            let seen: Vec<(u64, u64)> = serde_json::from_str(value).unwrap_or_default();
+            for (fingerprint, seen) in &seen {
+                import(
+                    storage,
+                    &format!("{}/{}", filter, namespace),
+                    &format!("{:016x}", fingerprint),
+                    &seen.to_string(),
+                    &[],
+                )?;
+            }
+            return Ok(seen.len());
+        }
+    }
+
+    // md5 digest of text, Unique of previous versions
+    if key.len() == 32 && key.chars().all(|c| c.is_ascii_hexdigit()) {
+        import(
+            storage,
+            LEGACY_UNIQUE_NAMESPACE,
+            key,
+            &now().to_string(),
+            &[],
+        )?;
+        return Ok(1);
+    }
+
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{migrate_entry, LEGACY_UNIQUE_NAMESPACE};
+    use crate::storage::{MemoryStorage, Storage};
+
+    #[test]
+    fn test_migrate_entry() {
+        let storage = MemoryStorage::default();
+
+        migrate_entry(&storage, "df49b60423903e095b80d9b4a92eb065", "true").unwrap();
+        migrate_entry(&storage, "unique:news:-100:abc", "1700000000").unwrap();
+        migrate_entry(&storage, "near_duplicate:news", "[[255,1700000000]]").unwrap();
+        migrate_entry(&storage, "unknown", "true").unwrap();
+
+        assert!(storage
+            .get(LEGACY_UNIQUE_NAMESPACE, "df49b60423903e095b80d9b4a92eb065")
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            Some("1700000000".to_string()),
+            storage.get("unique/news", "-100:abc").unwrap()
+        );
+        // Destination of per destination key is known
+        assert_eq!(1, storage.purge(-100).unwrap());
+        assert_eq!(
+            vec![("00000000000000ff".to_string(), "1700000000".to_string())],
+            storage.entries("near_duplicate/news").unwrap()
+        );
+    }
+}
@@ -0,0 +1,472 @@
+//! Persistent state of filters (seen messages, fingerprints, counters), queue of delayed messages and digests.
+//! Values are kept under namespaced keys, optionally expiring after TTL
+pub mod command;
+#[cfg(feature = "templating")]
+pub mod digest;
+pub mod drip;
+mod memory;
+mod migration;
+pub mod queue;
+mod sqlite;
+
+pub use self::memory::MemoryStorage;
+pub(crate) use self::migration::LEGACY_UNIQUE_NAMESPACE;
+pub use self::sqlite::SqliteStorage;
+
+use crate::config::{StorageBackendConf, StorageConf};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+lazy_static::lazy_static! {
+    static ref STORAGE: RwLock<Option<Arc<dyn Storage>>> = RwLock::new(None);
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Storage of one test, instead of the shared one
+    static TEST_STORAGE: std::cell::RefCell<Option<Arc<dyn Storage>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Give the current test its own empty storage, so it sees only its own entries
+#[cfg(test)]
+pub(crate) fn isolate() {
+    TEST_STORAGE.with(|storage| *storage.borrow_mut() = Some(Arc::new(MemoryStorage::default())));
+}
+
+#[derive(Debug)]
+pub struct StorageError(pub String);
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Storage error: {}", self.0)
+    }
+}
+impl Error for StorageError {}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        StorageError(e.to_string())
+    }
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// Stored value with its metadata, also the format of export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredEntry {
+    pub namespace: String,
+    pub key: String,
+    pub value: String,
+    /// Unix time after which entry is missing
+    pub expires_at: Option<u64>,
+    /// Unix time of the last change
+    pub updated_at: u64,
+    /// Chat and user ids which entry relates to, e.g. source chat and sender of seen message
+    #[serde(default)]
+    pub related: Vec<i64>,
+}
+
+impl StoredEntry {
+    pub fn new(namespace: &str, key: &str, value: &str, ttl: Option<u64>, related: &[i64]) -> Self {
+        let now = now();
+
+        StoredEntry {
+            namespace: namespace.to_string(),
+            key: key.to_string(),
+            value: value.to_string(),
+            expires_at: ttl.map(|ttl| now.saturating_add(ttl)),
+            updated_at: now,
+            related: related.to_vec(),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.expires_at.is_none_or(|expires_at| now() < expires_at)
+    }
+}
+
+/// Key-value store with namespaces and expiring entries.
+/// Expired entries are treated as missing
+pub trait Storage: Send + Sync {
+    /// Value of key
+    fn get(&self, namespace: &str, key: &str) -> StorageResult<Option<String>>;
+
+    /// Set value of key, which expires after `ttl` seconds.
+    /// `related` chat/user ids are used to purge their data
+    fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: &str,
+        ttl: Option<u64>,
+        related: &[i64],
+    ) -> StorageResult<()>;
+
+    /// Atomically set value of key when its current value is `current` (None - missing).
+    /// Returns whether value was set
+    fn compare_and_set(
+        &self,
+        namespace: &str,
+        key: &str,
+        current: Option<&str>,
+        value: &str,
+        ttl: Option<u64>,
+        related: &[i64],
+    ) -> StorageResult<bool>;
+
+    /// Remove key. Returns whether it was there
+    fn remove(&self, namespace: &str, key: &str) -> StorageResult<bool>;
+
+    /// All keys and values of namespace
+    fn entries(&self, namespace: &str) -> StorageResult<Vec<(String, String)>>;
+
+    /// Keys starting with `prefix` and their values
+    fn entries_with_prefix(
+        &self,
+        namespace: &str,
+        prefix: &str,
+    ) -> StorageResult<Vec<(String, String)>>;
+
+    /// Namespaces with number of their entries
+    fn usage(&self) -> StorageResult<Vec<(String, usize)>>;
+
+    /// Remove expired entries and entries of namespace not changed since `updated_before`
+    /// (when provided). Returns number of removed entries
+    fn remove_stale(&self, namespace: &str, updated_before: Option<u64>) -> StorageResult<usize>;
+
+    /// Remove entries related to chat/user id. Returns number of removed entries
+    fn purge(&self, id: i64) -> StorageResult<usize>;
+
+    /// All entries, including expired ones
+    fn export(&self) -> StorageResult<Vec<StoredEntry>>;
+
+    /// Insert entries, replacing existing keys
+    fn import(&self, entries: Vec<StoredEntry>) -> StorageResult<()>;
+}
+
+/// Open storage from config and share it with filters.
+/// Data of previous versions (`storage/key-value.db`) is migrated into SQLite on first start.
+/// In-memory storage doesn't migrate it, the file is kept for persistent storage
+pub fn init(conf: &StorageConf) -> StorageResult<()> {
+    let storage: Arc<dyn Storage> = match conf.backend {
+        StorageBackendConf::Sqlite => {
+            let storage = SqliteStorage::open(Path::new(&conf.path))?;
+            migration::migrate_legacy(&storage)?;
+            Arc::new(storage)
+        }
+        StorageBackendConf::Memory => Arc::new(MemoryStorage::default()),
+    };
+
+    *STORAGE.write().unwrap() = Some(storage);
+    Ok(())
+}
+
+/// Periodically remove expired entries and entries older than retention of their namespace
+pub fn start_compaction(conf: &StorageConf) {
+    let retention = conf.retention.clone();
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        conf.compaction_interval.max(1),
+    ));
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+
+            let retention = retention.clone();
+            match blocking(move || compact(storage().as_ref(), &retention)).await {
+                Ok(0) => {}
+                Ok(removed) => log::info!("Storage compaction removed {} entries", removed),
+                Err(e) => eprintln!("Storage compaction failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Retention period of namespace, e.g. `unique/news`. Configured for the namespace itself
+/// or for all namespaces of the filter (`unique`)
+pub(crate) fn retention_of(retention: &BTreeMap<String, u64>, namespace: &str) -> Option<u64> {
+    retention.get(namespace).copied().or_else(|| {
+        let (filter, _) = namespace.split_once('/')?;
+        retention.get(filter).copied()
+    })
+}
+
+/// Remove expired and outdated entries. Returns number of removed entries
+pub(crate) fn compact(
+    storage: &dyn Storage,
+    retention: &BTreeMap<String, u64>,
+) -> StorageResult<usize> {
+    let now = now();
+    let mut removed = 0;
+
+    for (namespace, _) in storage.usage()? {
+        let updated_before =
+            retention_of(retention, &namespace).map(|period| now.saturating_sub(period));
+        removed += storage.remove_stale(&namespace, updated_before)?;
+    }
+
+    Ok(removed)
+}
+
+/// Shared storage. In-memory one until storage is initialized (e.g. in tests)
+pub(crate) fn storage() -> Arc<dyn Storage> {
+    #[cfg(test)]
+    if let Some(storage) = TEST_STORAGE.with(|storage| storage.borrow().clone()) {
+        return storage;
+    }
+
+    if let Some(storage) = STORAGE.read().unwrap().as_ref() {
+        return storage.clone();
+    }
+
+    STORAGE
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(MemoryStorage::default()))
+        .clone()
+}
+
+/// Run storage calls on a thread of blocking pool, so runtime threads don't wait for
+/// the database lock, disk and scans of namespaces
+pub(crate) async fn blocking<T, F>(f: F) -> StorageResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> StorageResult<T> + Send + 'static,
+{
+    // Storage of test is passed to the thread of blocking pool
+    #[cfg(test)]
+    let test_storage = TEST_STORAGE.with(|storage| storage.borrow().clone());
+
+    tokio::task::spawn_blocking(move || {
+        #[cfg(test)]
+        TEST_STORAGE.with(|storage| *storage.borrow_mut() = test_storage);
+
+        f()
+    })
+    .await
+    .map_err(|e| StorageError(e.to_string()))?
+}
+
+/// Current unix time in seconds
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Prefixes of buckets of fingerprint, one per band.
+/// Fingerprint is split into `distance + 1` bands, so fingerprints within the distance
+/// have at least one equal band and share its bucket
+fn fingerprint_buckets(scope: &str, fingerprint: u64, distance: u32) -> Vec<String> {
+    let bands = (distance + 1).min(64);
+
+    (0..bands)
+        .map(|band| {
+            let start = band * 64 / bands;
+            let end = (band + 1) * 64 / bands;
+            let mask = u64::MAX >> (64 - (end - start));
+            let value = (fingerprint >> start) & mask;
+
+            format!("{}b{}:{:x}:", scope, band, value)
+        })
+        .collect()
+}
+
+/// Whether fingerprint is within Hamming distance of one seen in the last `window` seconds
+/// with the same scope (e.g. "-100123:", empty for the whole namespace).
+/// Otherwise it's remembered in the namespace
+pub(crate) fn seen_similar(
+    namespace: &str,
+    scope: &str,
+    fingerprint: u64,
+    distance: u32,
+    window: u64,
+    related: &[i64],
+) -> StorageResult<bool> {
+    let storage = storage();
+    let buckets = fingerprint_buckets(scope, fingerprint, distance);
+
+    // Keys are bucket and hex fingerprint, only fingerprints of the same buckets are compared
+    for bucket in &buckets {
+        let similar = storage
+            .entries_with_prefix(namespace, bucket)?
+            .iter()
+            .filter_map(|(key, _)| u64::from_str_radix(key.strip_prefix(bucket.as_str())?, 16).ok())
+            .any(|other| (fingerprint ^ other).count_ones() <= distance);
+
+        if similar {
+            return Ok(true);
+        }
+    }
+
+    // Same fingerprint remembered meanwhile by another message is seen
+    let value = now().to_string();
+    for bucket in &buckets {
+        let key = format!("{}{:016x}", bucket, fingerprint);
+        if !storage.compare_and_set(namespace, &key, None, &value, Some(window), related)? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compact, fingerprint_buckets, isolate, retention_of, seen_similar, MemoryStorage,
+        SqliteStorage, Storage, StoredEntry,
+    };
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    /// Behaviour which every storage must have
+    fn check_storage(storage: &dyn Storage) {
+        assert_eq!(None, storage.get("unique/news", "a").unwrap());
+
+        storage
+            .set("unique/news", "a", "1", None, &[1, 10])
+            .unwrap();
+        storage.set("unique/other", "a", "2", None, &[2]).unwrap();
+        assert_eq!(
+            Some("1".to_string()),
+            storage.get("unique/news", "a").unwrap()
+        );
+        assert_eq!(
+            Some("2".to_string()),
+            storage.get("unique/other", "a").unwrap()
+        );
+
+        // Expired entries are missing
+        storage.set("unique/news", "b", "1", Some(0), &[]).unwrap();
+        assert_eq!(None, storage.get("unique/news", "b").unwrap());
+        assert_eq!(
+            vec![("a".to_string(), "1".to_string())],
+            storage.entries("unique/news").unwrap()
+        );
+        storage.set("unique/news", "ab", "2", None, &[]).unwrap();
+        assert_eq!(
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("ab".to_string(), "2".to_string())
+            ],
+            storage.entries_with_prefix("unique/news", "a").unwrap()
+        );
+        assert!(storage
+            .entries_with_prefix("unique/news", "b")
+            .unwrap()
+            .is_empty());
+        storage.remove("unique/news", "ab").unwrap();
+
+        assert!(!storage
+            .compare_and_set("unique/news", "a", None, "3", None, &[1])
+            .unwrap());
+        assert!(storage
+            .compare_and_set("unique/news", "a", Some("1"), "3", None, &[1])
+            .unwrap());
+        assert!(storage
+            .compare_and_set("unique/news", "b", None, "4", None, &[1])
+            .unwrap());
+        assert_eq!(
+            Some("3".to_string()),
+            storage.get("unique/news", "a").unwrap()
+        );
+        assert_eq!(
+            Some("4".to_string()),
+            storage.get("unique/news", "b").unwrap()
+        );
+
+        assert_eq!(
+            vec![
+                ("unique/news".to_string(), 2),
+                ("unique/other".to_string(), 1)
+            ],
+            storage.usage().unwrap()
+        );
+
+        assert!(storage.remove("unique/news", "b").unwrap());
+        assert!(!storage.remove("unique/news", "b").unwrap());
+        storage.set("unique/news", "b", "4", None, &[1]).unwrap();
+
+        // Export and import
+        let exported = storage.export().unwrap();
+        assert_eq!(3, exported.len());
+        storage
+            .import(vec![StoredEntry::new("counter/x", "k", "5", None, &[3])])
+            .unwrap();
+        assert_eq!(
+            Some("5".to_string()),
+            storage.get("counter/x", "k").unwrap()
+        );
+
+        // Purge by related id
+        assert_eq!(2, storage.purge(1).unwrap());
+        assert_eq!(None, storage.get("unique/news", "a").unwrap());
+        assert_eq!(
+            Some("2".to_string()),
+            storage.get("unique/other", "a").unwrap()
+        );
+
+        // Retention
+        storage.set("unique/other", "b", "1", Some(0), &[]).unwrap();
+        let mut retention = BTreeMap::new();
+        retention.insert("counter".to_string(), 0);
+        assert_eq!(2, compact(storage, &retention).unwrap());
+        assert_eq!(None, storage.get("counter/x", "k").unwrap());
+        assert_eq!(
+            vec![("unique/other".to_string(), 1)],
+            storage.usage().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        check_storage(&MemoryStorage::default());
+    }
+
+    #[test]
+    fn test_sqlite_storage() {
+        check_storage(&SqliteStorage::open(Path::new(":memory:")).unwrap());
+    }
+
+    #[test]
+    fn test_fingerprint_buckets() {
+        let buckets = fingerprint_buckets("1:", 0xffff_0000_0000_0001, 3);
+
+        assert_eq!(vec!["1:b0:1:", "1:b1:0:", "1:b2:0:", "1:b3:ffff:"], buckets);
+        assert_eq!(64, fingerprint_buckets("", 0, 100).len());
+    }
+
+    #[test]
+    fn test_seen_similar() {
+        isolate();
+        let fingerprint = 0x0123_4567_89ab_cdef;
+
+        assert!(!seen_similar("near_duplicate/test", "", fingerprint, 3, 60, &[]).unwrap());
+        // Three bits differ, in different bands
+        let near = fingerprint ^ (1 | 1 << 20 | 1 << 40);
+        assert!(seen_similar("near_duplicate/test", "", near, 3, 60, &[]).unwrap());
+        // Other scope
+        assert!(!seen_similar("near_duplicate/test", "1:", near, 3, 60, &[]).unwrap());
+
+        let far = fingerprint ^ 0xff;
+        assert!(!seen_similar("near_duplicate/test", "", far, 3, 60, &[]).unwrap());
+    }
+
+    #[test]
+    fn test_retention_of() {
+        let retention =
+            BTreeMap::from([("unique".to_string(), 100), ("unique/news".to_string(), 10)]);
+
+        assert_eq!(Some(10), retention_of(&retention, "unique/news"));
+        assert_eq!(Some(100), retention_of(&retention, "unique/other"));
+        assert_eq!(None, retention_of(&retention, "near_duplicate/news"));
+    }
+}
@@ -0,0 +1,301 @@
+//! Output messages waiting for their time. Queued items are sent by App scheduler,
+//! messages scheduled on Telegram server are tracked to cancel them with their source
+use crate::storage::{now, storage, StorageError, StorageResult};
+use rust_tdlib::types::InputMessageContent;
+use serde::{Deserialize, Serialize};
+
+/// Namespace of queued items, keys are ordered by time
+const NAMESPACE: &str = "queue";
+/// Namespace of messages scheduled on Telegram server
+const SCHEDULED_NAMESPACE: &str = "queue/scheduled";
+/// Seconds after which item which wasn't sent is tried again
+const RETRY_DELAY: u64 = 60;
+/// Tries to send item, it's dropped after the last one
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Output of pipeline waiting to be sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueItem {
+    /// Unix time to send messages at
+    pub send_at: u64,
+    pub pipeline: String,
+    pub source_chat_id: i64,
+    pub source_message_id: i64,
+    pub dest_chat_id: i64,
+    /// Destination forum topic
+    pub thread_id: Option<i64>,
+    pub messages: Vec<InputMessageContent>,
+    /// Failed tries to send messages
+    #[serde(default)]
+    pub attempts: u32,
+    /// Source chat and message ids merged into messages of item, e.g. by drip-feed digest
+    #[serde(default)]
+    pub merged_sources: Vec<(i64, i64)>,
+}
+
+impl QueueItem {
+    /// Key of item, which is also its id in `storage queue` command
+    pub fn key(&self) -> String {
+        format!(
+            "{:010}:{}:{}:{}:{}",
+            self.send_at,
+            self.source_chat_id,
+            self.source_message_id,
+            self.dest_chat_id,
+            self.pipeline
+        )
+    }
+
+    fn is_from(&self, chat_id: i64, message_ids: &[i64]) -> bool {
+        std::iter::once(&(self.source_chat_id, self.source_message_id))
+            .chain(&self.merged_sources)
+            .any(|(source_chat_id, id)| *source_chat_id == chat_id && message_ids.contains(id))
+    }
+}
+
+/// Messages of item scheduled on Telegram server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScheduledMessages {
+    source_chat_id: i64,
+    source_message_id: i64,
+    dest_chat_id: i64,
+    message_ids: Vec<i64>,
+}
+
+fn to_json<T: Serialize>(value: &T) -> StorageResult<String> {
+    serde_json::to_string(value).map_err(|e| StorageError(e.to_string()))
+}
+
+/// Entries of namespace, entries which can't be read are skipped
+fn read<T: for<'a> Deserialize<'a>>(namespace: &str) -> StorageResult<Vec<(String, T)>> {
+    Ok(storage()
+        .entries(namespace)?
+        .into_iter()
+        .filter_map(|(key, value)| match serde_json::from_str(&value) {
+            Ok(item) => Some((key, item)),
+            Err(e) => {
+                eprintln!("Invalid {} entry {}: {}", namespace, key, e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Add item to queue
+pub fn push(item: &QueueItem) -> StorageResult<()> {
+    storage().set(
+        NAMESPACE,
+        &item.key(),
+        &to_json(item)?,
+        None,
+        &[item.source_chat_id, item.dest_chat_id],
+    )
+}
+
+/// Queued items with their keys, the earliest first
+pub fn items() -> StorageResult<Vec<(String, QueueItem)>> {
+    read(NAMESPACE)
+}
+
+/// Time of the earliest queued item
+pub fn next_send_at() -> StorageResult<Option<u64>> {
+    Ok(items()?.first().map(|(_, item)| item.send_at))
+}
+
+/// Remove and return items which time has come. Items cancelled meanwhile aren't returned
+pub fn take_due(time: u64) -> StorageResult<Vec<QueueItem>> {
+    let mut due = vec![];
+
+    for (key, item) in items()? {
+        if item.send_at > time {
+            break;
+        }
+        if storage().remove(NAMESPACE, &key)? {
+            due.push(item);
+        }
+    }
+    Ok(due)
+}
+
+/// Queue item which wasn't sent again, to be tried `RETRY_DELAY` later.
+/// Returns false when item is dropped after `MAX_ATTEMPTS` tries
+pub fn retry(mut item: QueueItem) -> StorageResult<bool> {
+    item.attempts += 1;
+    if item.attempts >= MAX_ATTEMPTS {
+        return Ok(false);
+    }
+
+    item.send_at = now() + RETRY_DELAY;
+    push(&item)?;
+    Ok(true)
+}
+
+/// Remove item from queue. Returns whether it was there
+pub fn cancel(key: &str) -> StorageResult<bool> {
+    storage().remove(NAMESPACE, key)
+}
+
+/// Remove queued items of deleted source messages. Returns number of removed items
+pub fn cancel_source(chat_id: i64, message_ids: &[i64]) -> StorageResult<usize> {
+    let mut removed = 0;
+
+    for (key, item) in items()? {
+        if item.is_from(chat_id, message_ids) && storage().remove(NAMESPACE, &key)? {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Remember messages of item scheduled on Telegram server until they are sent
+pub fn track_scheduled(item: &QueueItem, message_ids: Vec<i64>) -> StorageResult<()> {
+    let scheduled = ScheduledMessages {
+        source_chat_id: item.source_chat_id,
+        source_message_id: item.source_message_id,
+        dest_chat_id: item.dest_chat_id,
+        message_ids,
+    };
+
+    storage().set(
+        SCHEDULED_NAMESPACE,
+        &item.key(),
+        &to_json(&scheduled)?,
+        Some(item.send_at.saturating_sub(now())),
+        &[item.source_chat_id, item.dest_chat_id],
+    )
+}
+
+/// Replace temporary id of scheduled message with the one given by server
+pub fn replace_scheduled_id(chat_id: i64, old_id: i64, new_id: i64) -> StorageResult<()> {
+    for (key, mut scheduled) in read::<ScheduledMessages>(SCHEDULED_NAMESPACE)? {
+        if scheduled.dest_chat_id != chat_id || !scheduled.message_ids.contains(&old_id) {
+            continue;
+        }
+
+        for id in scheduled.message_ids.iter_mut().filter(|id| **id == old_id) {
+            *id = new_id;
+        }
+        // Key starts with unix time of sending
+        let send_at = key.split(':').next().and_then(|at| at.parse::<u64>().ok());
+        storage().set(
+            SCHEDULED_NAMESPACE,
+            &key,
+            &to_json(&scheduled)?,
+            send_at.map(|at| at.saturating_sub(now())),
+            &[scheduled.source_chat_id, scheduled.dest_chat_id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Stop tracking scheduled messages of deleted source messages.
+/// Returns destination chats with ids of messages to delete
+pub fn take_scheduled(chat_id: i64, message_ids: &[i64]) -> StorageResult<Vec<(i64, Vec<i64>)>> {
+    let mut taken = vec![];
+
+    for (key, scheduled) in read::<ScheduledMessages>(SCHEDULED_NAMESPACE)? {
+        if scheduled.source_chat_id == chat_id
+            && message_ids.contains(&scheduled.source_message_id)
+            && storage().remove(SCHEDULED_NAMESPACE, &key)?
+        {
+            taken.push((scheduled.dest_chat_id, scheduled.message_ids));
+        }
+    }
+    Ok(taken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cancel, cancel_source, items, next_send_at, push, replace_scheduled_id, retry, take_due,
+        take_scheduled, track_scheduled, QueueItem, MAX_ATTEMPTS, RETRY_DELAY,
+    };
+    use crate::storage::{isolate, now};
+    use rust_tdlib::types::{FormattedText, InputMessageContent, InputMessageText};
+
+    fn item(send_at: u64, source_message_id: i64) -> QueueItem {
+        QueueItem {
+            send_at,
+            pipeline: "queue_test".to_string(),
+            source_chat_id: -1001,
+            source_message_id,
+            dest_chat_id: -1002,
+            thread_id: None,
+            messages: vec![InputMessageContent::InputMessageText(
+                InputMessageText::builder()
+                    .text(FormattedText::builder().text("text").build())
+                    .build(),
+            )],
+            attempts: 0,
+            merged_sources: vec![],
+        }
+    }
+
+    #[test]
+    fn test_queue() {
+        isolate();
+        let now = now();
+        push(&item(now + 100, 2)).unwrap();
+        push(&item(now - 10, 1)).unwrap();
+        push(&item(now + 200, 3)).unwrap();
+
+        assert_eq!(Some(now - 10), next_send_at().unwrap());
+
+        let due = take_due(now).unwrap();
+        assert_eq!(1, due.len());
+        assert_eq!(1, due[0].source_message_id);
+        assert!(matches!(
+            &due[0].messages[0],
+            InputMessageContent::InputMessageText(_)
+        ));
+
+        assert_eq!(1, cancel_source(-1001, &[3, 4]).unwrap());
+        let (key, _) = items().unwrap().pop().unwrap();
+        assert!(cancel(&key).unwrap());
+        assert!(!cancel(&key).unwrap());
+        assert_eq!(None, next_send_at().unwrap());
+
+        // Scheduled on server
+        track_scheduled(&item(now + 100, 5), vec![10, 11]).unwrap();
+        replace_scheduled_id(-1002, 11, 21).unwrap();
+        assert_eq!(
+            Vec::<(i64, Vec<i64>)>::new(),
+            take_scheduled(-1001, &[6]).unwrap()
+        );
+        assert_eq!(
+            vec![(-1002, vec![10, 21])],
+            take_scheduled(-1001, &[5]).unwrap()
+        );
+        assert!(take_scheduled(-1001, &[5]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_retry() {
+        isolate();
+        let mut failed = item(now() - 10, 7);
+
+        assert!(retry(failed.clone()).unwrap());
+        let (key, queued) = items().unwrap().pop().unwrap();
+        assert_eq!(1, queued.attempts);
+        assert!(queued.send_at >= now() + RETRY_DELAY - 1);
+        assert!(cancel(&key).unwrap());
+
+        // The last try drops item
+        failed.attempts = MAX_ATTEMPTS - 1;
+        assert!(!retry(failed).unwrap());
+        assert_eq!(None, next_send_at().unwrap());
+    }
+
+    #[test]
+    fn test_cancel_merged() {
+        isolate();
+        let mut merged = item(now() + 100, 1);
+        merged.merged_sources = vec![(-1001, 2), (-1003, 3)];
+        push(&merged).unwrap();
+
+        // Deletion of any merged source message cancels item
+        assert_eq!(0, cancel_source(-1001, &[3]).unwrap());
+        assert_eq!(1, cancel_source(-1003, &[3]).unwrap());
+        assert!(items().unwrap().is_empty());
+    }
+}
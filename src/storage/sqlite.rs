@@ -0,0 +1,261 @@
+use crate::storage::{now, Storage, StorageError, StorageResult, StoredEntry};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Storage in SQLite database file. Every change is a transaction, so the file is never
+/// rewritten as a whole.
+/// The connection is used synchronously under mutex, so async code calls storage
+/// through `storage::blocking`, which runs it on a thread of blocking pool.
+/// With WAL and `synchronous = NORMAL` commits don't wait for fsync (only checkpoints do)
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    /// Open (or create) database. `:memory:` opens in-memory database
+    pub fn open(path: &Path) -> StorageResult<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| StorageError(e.to_string()))?;
+        }
+
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            PRAGMA synchronous = NORMAL;
+            CREATE TABLE IF NOT EXISTS entries (
+                namespace TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                expires_at INTEGER,
+                updated_at INTEGER NOT NULL,
+                related TEXT NOT NULL,
+                PRIMARY KEY (namespace, key)
+            );",
+        )?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+/// Related ids are kept as ",1,2," so single id is found with LIKE '%,1,%'
+fn related_column(related: &[i64]) -> String {
+    match related.is_empty() {
+        true => String::new(),
+        false => related
+            .iter()
+            .fold(",".to_string(), |column, id| format!("{}{},", column, id)),
+    }
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<StoredEntry> {
+    let related: String = row.get(5)?;
+
+    Ok(StoredEntry {
+        namespace: row.get(0)?,
+        key: row.get(1)?,
+        value: row.get(2)?,
+        expires_at: row.get::<_, Option<i64>>(3)?.map(|time| time as u64),
+        updated_at: row.get::<_, i64>(4)? as u64,
+        related: related
+            .split(',')
+            .filter_map(|id| id.parse().ok())
+            .collect(),
+    })
+}
+
+/// Value of not expired entry
+fn get(connection: &Connection, namespace: &str, key: &str) -> rusqlite::Result<Option<String>> {
+    connection
+        .query_row(
+            "SELECT value FROM entries
+            WHERE namespace = ?1 AND key = ?2 AND (expires_at IS NULL OR expires_at > ?3)",
+            params![namespace, key, now() as i64],
+            |row| row.get(0),
+        )
+        .optional()
+}
+
+fn insert(connection: &Connection, entry: &StoredEntry) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO entries (namespace, key, value, expires_at, updated_at, related)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            entry.namespace,
+            entry.key,
+            entry.value,
+            entry.expires_at.map(|time| time as i64),
+            entry.updated_at as i64,
+            related_column(&entry.related)
+        ],
+    )?;
+
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn get(&self, namespace: &str, key: &str) -> StorageResult<Option<String>> {
+        Ok(get(&self.connection.lock().unwrap(), namespace, key)?)
+    }
+
+    fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: &str,
+        ttl: Option<u64>,
+        related: &[i64],
+    ) -> StorageResult<()> {
+        Ok(insert(
+            &self.connection.lock().unwrap(),
+            &StoredEntry::new(namespace, key, value, ttl, related),
+        )?)
+    }
+
+    fn compare_and_set(
+        &self,
+        namespace: &str,
+        key: &str,
+        current: Option<&str>,
+        value: &str,
+        ttl: Option<u64>,
+        related: &[i64],
+    ) -> StorageResult<bool> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        if get(&transaction, namespace, key)?.as_deref() != current {
+            return Ok(false);
+        }
+
+        insert(
+            &transaction,
+            &StoredEntry::new(namespace, key, value, ttl, related),
+        )?;
+        transaction.commit()?;
+
+        Ok(true)
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> StorageResult<bool> {
+        let removed = self.connection.lock().unwrap().execute(
+            "DELETE FROM entries
+            WHERE namespace = ?1 AND key = ?2 AND (expires_at IS NULL OR expires_at > ?3)",
+            params![namespace, key, now() as i64],
+        )?;
+
+        Ok(removed > 0)
+    }
+
+    fn entries(&self, namespace: &str) -> StorageResult<Vec<(String, String)>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT key, value FROM entries
+            WHERE namespace = ?1 AND (expires_at IS NULL OR expires_at > ?2)
+            ORDER BY key",
+        )?;
+
+        let entries = statement
+            .query_map(params![namespace, now() as i64], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<(String, String)>>>()?;
+
+        Ok(entries)
+    }
+
+    fn entries_with_prefix(
+        &self,
+        namespace: &str,
+        prefix: &str,
+    ) -> StorageResult<Vec<(String, String)>> {
+        let connection = self.connection.lock().unwrap();
+        // Range of keys is looked up by primary key index, keys are ASCII
+        let mut statement = connection.prepare(
+            "SELECT key, value FROM entries
+            WHERE namespace = ?1 AND key >= ?2 AND key < ?3
+                AND (expires_at IS NULL OR expires_at > ?4)
+            ORDER BY key",
+        )?;
+
+        let entries = statement
+            .query_map(
+                params![
+                    namespace,
+                    prefix,
+                    format!("{}\u{10FFFF}", prefix),
+                    now() as i64
+                ],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?
+            .collect::<rusqlite::Result<Vec<(String, String)>>>()?;
+
+        Ok(entries)
+    }
+
+    fn usage(&self) -> StorageResult<Vec<(String, usize)>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT namespace, COUNT(*) FROM entries GROUP BY namespace ORDER BY namespace",
+        )?;
+
+        let usage = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+            .collect::<rusqlite::Result<Vec<(String, usize)>>>()?;
+
+        Ok(usage)
+    }
+
+    fn remove_stale(&self, namespace: &str, updated_before: Option<u64>) -> StorageResult<usize> {
+        let removed = self.connection.lock().unwrap().execute(
+            "DELETE FROM entries
+            WHERE namespace = ?1 AND (expires_at <= ?2 OR updated_at <= ?3)",
+            params![
+                namespace,
+                now() as i64,
+                updated_before.map(|time| time as i64)
+            ],
+        )?;
+
+        Ok(removed)
+    }
+
+    fn purge(&self, id: i64) -> StorageResult<usize> {
+        let removed = self.connection.lock().unwrap().execute(
+            "DELETE FROM entries WHERE related LIKE ?1",
+            params![format!("%,{},%", id)],
+        )?;
+
+        Ok(removed)
+    }
+
+    fn export(&self) -> StorageResult<Vec<StoredEntry>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT namespace, key, value, expires_at, updated_at, related FROM entries
+            ORDER BY namespace, key",
+        )?;
+
+        let entries = statement
+            .query_map([], entry_from_row)?
+            .collect::<rusqlite::Result<Vec<StoredEntry>>>()?;
+
+        Ok(entries)
+    }
+
+    fn import(&self, entries: Vec<StoredEntry>) -> StorageResult<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        for entry in &entries {
+            insert(&transaction, entry)?;
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+}